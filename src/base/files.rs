@@ -16,6 +16,17 @@ pub fn read_file_content(file_path: &str) -> Result<String, NetRouteError> {
     Ok(content)
 }
 
+/// 写入文件内容，文件已存在时覆盖
+///
+/// # Arguments
+///
+/// * `file_path` - 文件路径
+/// * `content` - 文件内容
+pub fn write_file_content(file_path: &str, content: &str) -> Result<(), NetRouteError> {
+    fs::write(file_path, content)
+        .map_err(|e| NetRouteError::new(format!("写入文件 {} 失败: {}", file_path, e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
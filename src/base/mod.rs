@@ -1,5 +1,6 @@
-pub mod user_input;
 pub mod files;
+pub mod output;
+pub mod user_input;
 
 use std::error::Error;
 use std::fmt;
@@ -54,3 +55,6 @@ impl From<&str> for NetRouteError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,240 @@
+use crate::base::NetRouteError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+/// 列表命令的输出格式
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 表格
+    #[default]
+    Table,
+    /// JSON 数组
+    Json,
+    /// 每行一个 JSON 对象
+    Ndjson,
+    /// CSV，第一行为字段名
+    Csv,
+    /// 使用 `--format` 指定的模板，每条记录一行
+    Template,
+}
+
+/// 全局输出设置
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// 输出格式
+    pub format: OutputFormat,
+    /// 模板，如 `{destination}/{prefix} via {gateway}`
+    pub template: Option<String>,
+}
+
+/// 通过 `--output` 与 `--format` 指定的输出设置
+static OUTPUT: OnceLock<OutputOptions> = OnceLock::new();
+
+impl OutputOptions {
+    /// 根据命令行参数生成输出设置
+    ///
+    /// 只指定 `--format` 时使用模板格式，使用模板格式却未指定模板时报错
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - 输出格式
+    /// * `template` - 模板
+    ///
+    pub fn new(
+        format: OutputFormat,
+        template: Option<String>,
+    ) -> Result<OutputOptions, NetRouteError> {
+        let format = match (format, &template) {
+            (OutputFormat::Table, Some(_)) => OutputFormat::Template,
+            (OutputFormat::Template, None) => {
+                return Err(NetRouteError::new(
+                    "--output template 需要通过 --format 指定模板，如 '{destination}/{prefix} via {gateway}'",
+                ));
+            }
+            (format, Some(_)) if format != OutputFormat::Template => {
+                return Err(NetRouteError::new(
+                    "--format 只能与 --output template 一起使用",
+                ));
+            }
+            (format, _) => format,
+        };
+        Ok(OutputOptions { format, template })
+    }
+}
+
+/// 设置输出格式，只在程序启动时调用一次
+///
+/// # Arguments
+///
+/// * `options` - 输出设置
+///
+pub fn set_output(options: OutputOptions) {
+    let _ = OUTPUT.set(options);
+}
+
+/// 当前是否以表格形式输出
+pub fn is_table() -> bool {
+    output().format == OutputFormat::Table
+}
+
+fn output() -> &'static OutputOptions {
+    OUTPUT.get_or_init(OutputOptions::default)
+}
+
+/// 按全局输出格式打印列表，表格格式时调用 `show_table`
+///
+/// # Arguments
+///
+/// * `rows` - 列表记录
+/// * `show_table` - 打印表格
+///
+pub fn print_rows<T: Serialize>(
+    rows: &[T],
+    show_table: impl FnOnce(),
+) -> Result<(), NetRouteError> {
+    let options = output();
+    if options.format == OutputFormat::Table {
+        show_table();
+        return Ok(());
+    }
+    print!("{}", render_rows(rows, options)?);
+    Ok(())
+}
+
+/// 将列表记录渲染为机器可读的文本
+///
+/// # Arguments
+///
+/// * `rows` - 列表记录
+/// * `options` - 输出设置
+///
+pub fn render_rows<T: Serialize>(
+    rows: &[T],
+    options: &OutputOptions,
+) -> Result<String, NetRouteError> {
+    let objects = rows
+        .iter()
+        .map(to_object)
+        .collect::<Result<Vec<Map<String, Value>>, NetRouteError>>()?;
+    let mut output = String::new();
+    match options.format {
+        OutputFormat::Table => {}
+        OutputFormat::Json => {
+            output = serde_json::to_string_pretty(&objects)
+                .map_err(|e| NetRouteError::new(format!("输出序列化失败: {}", e)))?;
+            output.push('\n');
+        }
+        OutputFormat::Ndjson => {
+            for object in objects.iter() {
+                let line = serde_json::to_string(object)
+                    .map_err(|e| NetRouteError::new(format!("输出序列化失败: {}", e)))?;
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        OutputFormat::Csv => {
+            if let Some(first) = objects.first() {
+                let header = first.keys().map(|key| csv_field(key)).collect::<Vec<_>>();
+                output.push_str(&header.join(","));
+                output.push('\n');
+            }
+            for object in objects.iter() {
+                let line = object
+                    .values()
+                    .map(|value| csv_field(&plain_value(value)))
+                    .collect::<Vec<String>>();
+                output.push_str(&line.join(","));
+                output.push('\n');
+            }
+        }
+        OutputFormat::Template => {
+            let template = options.template.as_deref().unwrap_or_default();
+            for object in objects.iter() {
+                output.push_str(&render_template(template, object)?);
+                output.push('\n');
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// 将记录序列化为 JSON 对象
+fn to_object<T: Serialize>(row: &T) -> Result<Map<String, Value>, NetRouteError> {
+    match serde_json::to_value(row) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(NetRouteError::new("输出记录必须是对象")),
+        Err(e) => Err(NetRouteError::new(format!("输出序列化失败: {}", e))),
+    }
+}
+
+/// 将字段值转换为不带引号的文本，空值为空字符串
+fn plain_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 按 CSV 规则转义字段
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 使用记录中的字段替换模板中的 `{字段名}`，`{{` 与 `}}` 输出花括号本身
+///
+/// # Arguments
+///
+/// * `template` - 模板
+/// * `object` - 记录
+///
+pub fn render_template(
+    template: &str,
+    object: &Map<String, Value>,
+) -> Result<String, NetRouteError> {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            return Err(NetRouteError::new(format!(
+                                "模板 {} 中的 {{ 没有闭合",
+                                template
+                            )));
+                        }
+                    }
+                }
+                let Some(value) = object.get(name.trim()) else {
+                    let fields = object.keys().cloned().collect::<Vec<String>>();
+                    return Err(NetRouteError::new(format!(
+                        "模板字段 {} 不存在，可用字段: {}",
+                        name,
+                        fields.join(", ")
+                    )));
+                };
+                output.push_str(&plain_value(value));
+            }
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
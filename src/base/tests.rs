@@ -0,0 +1,55 @@
+use crate::base::output::{self, OutputFormat, OutputOptions};
+use serde::Serialize;
+use test_case::test_case;
+
+#[derive(Serialize)]
+struct Row {
+    destination: String,
+    prefix: u8,
+    gateway: Option<String>,
+}
+
+fn rows() -> Vec<Row> {
+    vec![
+        Row {
+            destination: "10.0.0.0".to_string(),
+            prefix: 8,
+            gateway: Some("192.168.1.1".to_string()),
+        },
+        Row {
+            destination: "a,b".to_string(),
+            prefix: 32,
+            gateway: None,
+        },
+    ]
+}
+
+#[test_case(OutputFormat::Json, None, "[\n  {\n    \"destination\": \"10.0.0.0\",\n    \"prefix\": 8,\n    \"gateway\": \"192.168.1.1\"\n  },\n  {\n    \"destination\": \"a,b\",\n    \"prefix\": 32,\n    \"gateway\": null\n  }\n]\n" ; "测试输出JSON")]
+#[test_case(OutputFormat::Ndjson, None, "{\"destination\":\"10.0.0.0\",\"prefix\":8,\"gateway\":\"192.168.1.1\"}\n{\"destination\":\"a,b\",\"prefix\":32,\"gateway\":null}\n" ; "测试输出NDJSON")]
+#[test_case(OutputFormat::Csv, None, "destination,prefix,gateway\n10.0.0.0,8,192.168.1.1\n\"a,b\",32,\n" ; "测试输出CSV并转义逗号")]
+#[test_case(OutputFormat::Template, Some("{destination}/{prefix} via {gateway}"), "10.0.0.0/8 via 192.168.1.1\na,b/32 via \n" ; "测试输出模板")]
+#[test_case(OutputFormat::Template, Some("{{{prefix}}}"), "{8}\n{32}\n" ; "测试模板转义花括号")]
+fn output_render_rows(format: OutputFormat, template: Option<&str>, expected: &str) {
+    let options = OutputOptions::new(format, template.map(str::to_string)).unwrap();
+    assert_eq!(output::render_rows(&rows(), &options).unwrap(), expected);
+}
+
+#[test_case("{metric}" ; "测试模板字段不存在")]
+#[test_case("{destination" ; "测试模板花括号未闭合")]
+fn output_render_template_error(template: &str) {
+    let options = OutputOptions::new(OutputFormat::Template, Some(template.to_string())).unwrap();
+    assert!(output::render_rows(&rows(), &options).is_err());
+}
+
+#[test_case(OutputFormat::Table, Some("{prefix}"), Some(OutputFormat::Template) ; "测试只指定模板时使用模板格式")]
+#[test_case(OutputFormat::Template, None, None ; "测试模板格式缺少模板")]
+#[test_case(OutputFormat::Csv, Some("{prefix}"), None ; "测试模板与其他格式冲突")]
+#[test_case(OutputFormat::Json, None, Some(OutputFormat::Json) ; "测试指定JSON格式")]
+fn output_options_new(
+    format: OutputFormat,
+    template: Option<&str>,
+    expected: Option<OutputFormat>,
+) {
+    let result = OutputOptions::new(format, template.map(str::to_string));
+    assert_eq!(result.ok().map(|options| options.format), expected);
+}
@@ -1,4 +1,15 @@
+use crate::base::NetRouteError;
+use crate::base::output::OutputFormat;
+use crate::interface::selector::InterfaceSelector;
+use crate::probe::check::Check;
+use crate::route::cidr::{Cidr, HostBits};
+use crate::route::config::format::ConfigFormat;
+use crate::route::family::Family;
+use crate::route::filter::RouteSort;
+use crate::route::select::{IfIndex, SelectScope};
+use crate::route::snapshot;
 use clap::{Parser, Subcommand};
+use std::net::IpAddr;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -7,6 +18,26 @@ pub struct Cli {
     #[arg(short, long, default_value_t = 0)]
     pub debug: u8,
 
+    /// 使用模拟网络环境夹具文件，所有命令只操作内存中的路由表
+    #[arg(long, global = true)]
+    pub simulate: Option<String>,
+
+    /// 记录由本工具添加的路由的状态文件路径
+    #[arg(long, global = true)]
+    pub state: Option<String>,
+
+    /// 修改路由表前自动保存的快照数量，为 0 时不保存
+    #[arg(long, global = true, default_value_t = snapshot::DEFAULT_KEEP)]
+    pub snapshot_keep: usize,
+
+    /// 列表命令的输出格式
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// 输出模板，如 '{destination}/{prefix} via {gateway}'，指定后使用模板格式输出
+    #[arg(long, global = true)]
+    pub format: Option<String>,
+
     /// 命令行参数
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -34,15 +65,83 @@ pub enum Commands {
     },
     /// 使用配置文件
     Config {
-        /// 配置文件路径
-        #[arg(long)]
+        /// 配置文件执行计划相关指令，为空时直接应用配置文件
+        #[command(subcommand)]
+        action: Option<ConfigActions>,
+        /// 配置文件路径，也可以是包含多个配置片段的目录
+        #[arg(long, global = true)]
         path: Option<String>,
+        /// 配置文件格式，为空时根据扩展名判断，无法识别时为 JSON
+        #[arg(long, global = true, value_enum)]
+        config_format: Option<ConfigFormat>,
         /// 跳过确认
-        #[arg(short = 'y', long, default_value_t = false)]
+        #[arg(short = 'y', long, global = true, default_value_t = false)]
         no_confirm: bool,
         /// 取消应用此配置文件
-        #[arg(short = 'c', long, default_value_t = false)]
+        #[arg(short = 'c', long, global = true, default_value_t = false)]
         cancel: bool,
+        /// 配置的 CIDR 包含主机位时的处理方式
+        #[arg(long, global = true, value_enum, default_value_t = HostBits::Reject)]
+        host_bits: HostBits,
+        /// 将绑定到相同网卡与网关的路由聚合为最少的 CIDR 集合
+        #[arg(long, global = true, default_value_t = false)]
+        aggregate: bool,
+    },
+    /// 以守护进程方式运行配置文件，按 DNS TTL 重新解析域名并更新路由
+    Daemon {
+        /// 配置文件路径，也可以是包含多个配置片段的目录
+        #[arg(long)]
+        config: String,
+        /// 配置文件格式，为空时根据扩展名判断，无法识别时为 JSON
+        #[arg(long, value_enum)]
+        config_format: Option<ConfigFormat>,
+        /// 重新解析域名的最短间隔，秒，TTL 小于该值或无法获取时使用
+        #[arg(long, default_value_t = 60)]
+        min_ttl: u64,
+        /// 重新解析域名的最长间隔，秒，TTL 大于该值时使用
+        #[arg(long, default_value_t = 3600)]
+        max_ttl: u64,
+        /// IP 地址从解析结果中消失后保留路由的时间，秒
+        #[arg(long, default_value_t = 600)]
+        grace: u64,
+        /// 退出时删除由该配置文件添加的所有路由
+        #[arg(long, default_value_t = false)]
+        cleanup: bool,
+        /// 配置的 CIDR 包含主机位时的处理方式
+        #[arg(long, value_enum, default_value_t = HostBits::Reject)]
+        host_bits: HostBits,
+    },
+}
+
+/// 配置文件执行计划相关指令
+#[derive(Subcommand)]
+pub enum ConfigActions {
+    /// 对比配置文件与路由表，展示需要添加、删除、修改与保持不变的路由
+    Plan {
+        /// 计划文件保存路径
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// 应用配置文件或已保存的计划
+    Apply {
+        /// 已保存的计划文件路径，路由表在生成计划后发生变化时拒绝执行
+        #[arg(long)]
+        plan: Option<String>,
+    },
+    /// 检查配置文件，一次报告所有问题，存在问题时以非零状态退出
+    Validate {
+        /// 解析域名，检查域名能否解析以及解析结果是否指向不同网卡
+        #[arg(long, default_value_t = false)]
+        resolve: bool,
+    },
+    /// 将配置文件转换为其他格式，原配置文件中的注释不会保留
+    Convert {
+        /// 目标格式
+        #[arg(long, value_enum)]
+        to: ConfigFormat,
+        /// 转换结果保存路径，为空时输出到标准输出
+        #[arg(long)]
+        out: Option<String>,
     },
 }
 
@@ -58,6 +157,46 @@ pub enum RouteActions {
         /// 当前页码
         #[arg(long, default_value_t = 1, value_parser = less_than_one_error)]
         page: usize,
+
+        /// 不分页，展示全部路由
+        #[arg(long, default_value_t = false)]
+        all: bool,
+
+        /// 标记由本工具添加的路由及其来源
+        #[arg(long, default_value_t = false)]
+        managed: bool,
+
+        /// 只展示由本工具添加的路由
+        #[arg(long, default_value_t = false)]
+        managed_only: bool,
+
+        /// 只展示指定网卡的路由，支持网卡选择器
+        #[arg(long, value_parser = parse_interface)]
+        ifindex: Option<InterfaceSelector>,
+
+        /// 只展示目标网段位于该网段之内的路由，如 10.0.0.0/8
+        #[arg(long, value_parser = parse_cidr)]
+        dest_within: Option<Cidr>,
+
+        /// 只展示目标网段包含该 IP 地址的路由
+        #[arg(long)]
+        dest_contains: Option<IpAddr>,
+
+        /// 只展示指定网关的路由
+        #[arg(long)]
+        gateway: Option<IpAddr>,
+
+        /// 只展示指定地址族的路由
+        #[arg(long, value_enum)]
+        family: Option<Family>,
+
+        /// 只展示度量值不超过该值的路由
+        #[arg(long)]
+        metric_max: Option<u32>,
+
+        /// 排序方式，默认保持路由表中的顺序
+        #[arg(long, value_enum)]
+        sort: Option<RouteSort>,
     },
     /// 添加路由
     Add {
@@ -65,9 +204,64 @@ pub enum RouteActions {
         #[command(subcommand)]
         action: RouteAddActions,
     },
+    /// 修改已存在路由的度量值、网关或网卡
+    Set {
+        /// 目标 IP 地址或 CIDR，如 10.0.0.0/8
+        #[arg(long = "dest")]
+        destination: String,
+
+        /// 目标 IP 子网掩码，默认为单个主机地址（IPv4 为 32，IPv6 为 128）
+        #[arg(long)]
+        prefix: Option<u8>,
+
+        /// 需要修改的路由所在的网卡，匹配到多条路由时必须指定，支持网卡选择器
+        #[arg(long = "ifindex", value_parser = parse_interface)]
+        if_index: Option<InterfaceSelector>,
+
+        /// 新的路由度量值
+        #[arg(long)]
+        metric: Option<u32>,
+
+        /// 新的网关 IP 地址
+        #[arg(long)]
+        gateway: Option<String>,
+
+        /// 新的网卡，未指定 --gateway 时使用该网卡的网关，支持网卡选择器
+        #[arg(long = "new-ifindex", value_parser = parse_interface)]
+        new_if_index: Option<InterfaceSelector>,
+
+        /// 目标地址包含主机位时的处理方式
+        #[arg(long, value_enum, default_value_t = HostBits::Reject)]
+        host_bits: HostBits,
+    },
+    /// 备份路由表到快照文件
+    Backup {
+        /// 快照文件保存路径
+        #[arg(long)]
+        out: String,
+    },
+    /// 将路由表恢复到快照时的状态
+    Restore {
+        /// 快照文件路径
+        path: Option<String>,
+
+        /// 使用最近一次自动快照
+        #[arg(long, default_value_t = false)]
+        latest: bool,
+
+        /// 跳过确认
+        #[arg(short = 'y', long, default_value_t = false)]
+        no_confirm: bool,
+    },
+    /// 查询目标地址实际使用的路由
+    Lookup {
+        /// 目标 IP 地址或域名
+        #[arg(long = "dest")]
+        destination: String,
+    },
     /// 删除路由
     Remove {
-        /// 目标 IP 地址
+        /// 目标 IP 地址或 CIDR，如 10.0.0.0/8
         #[arg(long = "dest", default_value_t = String::new())]
         destination: String,
 
@@ -75,13 +269,25 @@ pub enum RouteActions {
         #[arg(long, default_value_t = String::new())]
         domain: String,
 
-        /// 网卡索引
-        #[arg(long = "ifindex")]
-        if_index: Option<u32>,
+        /// 网卡，支持网卡选择器
+        #[arg(long = "ifindex", value_parser = parse_interface)]
+        if_index: Option<InterfaceSelector>,
+
+        /// 目标 IP 子网掩码，默认为单个主机地址（IPv4 为 32，IPv6 为 128）
+        #[arg(long)]
+        prefix: Option<u8>,
 
-        /// 目标 IP 子网掩码
-        #[arg(long, default_value_t = 32)]
-        prefix: u8,
+        /// 删除域名路由时的地址族，默认删除所有地址族
+        #[arg(long, value_enum)]
+        family: Option<Family>,
+
+        /// 目标地址包含主机位时的处理方式
+        #[arg(long, value_enum, default_value_t = HostBits::Reject)]
+        host_bits: HostBits,
+
+        /// 跳过确认
+        #[arg(short = 'y', long, default_value_t = false)]
+        no_confirm: bool,
     },
 }
 
@@ -90,17 +296,17 @@ pub enum RouteActions {
 pub enum RouteAddActions {
     /// 使用 IP 地址添加路由
     Ip {
-        /// 目标 IP 地址
+        /// 目标 IP 地址或 CIDR，如 10.0.0.0/8
         #[arg(long = "dest")]
         destination: String,
 
-        /// 目标 IP 子网掩码
-        #[arg(long, default_value_t = 32)]
-        prefix: u8,
+        /// 目标 IP 子网掩码，默认为单个主机地址（IPv4 为 32，IPv6 为 128）
+        #[arg(long)]
+        prefix: Option<u8>,
 
-        /// 网卡索引
-        #[arg(long = "ifindex")]
-        if_index: u32,
+        /// 网卡，支持网卡索引、名称、MAC 地址、glob:、regex: 与 type: 选择器
+        #[arg(long = "ifindex", value_parser = parse_interface)]
+        if_index: InterfaceSelector,
 
         /// 网关 IP 地址
         #[arg(long)]
@@ -113,6 +319,14 @@ pub enum RouteAddActions {
         /// 是否检查目标地址是否可达
         #[arg(long, default_value_t = false)]
         no_check: bool,
+
+        /// 连通性检查方式：icmp、tcp:<端口>、tls:<端口>、http://<主机>/<路径> 或 https://<主机>/<路径>
+        #[arg(long, value_parser = parse_check, default_value = "icmp")]
+        check: Check,
+
+        /// 目标地址包含主机位时的处理方式
+        #[arg(long, value_enum, default_value_t = HostBits::Reject)]
+        host_bits: HostBits,
     },
     /// 使用域名添加路由
     Domain {
@@ -120,9 +334,17 @@ pub enum RouteAddActions {
         #[arg(long)]
         domain: String,
 
-        /// 网卡索引
-        #[arg(long = "ifindex")]
-        if_index: u32,
+        /// 网卡，支持网卡选择器，auto 表示从候选网卡中按连通性测试结果自动选择
+        #[arg(long = "ifindex", value_parser = parse_if_index)]
+        if_index: IfIndex,
+
+        /// 自动选择网卡时的候选网卡，如 12,28,31，支持网卡选择器
+        #[arg(long, value_delimiter = ',', value_parser = parse_interface)]
+        candidates: Vec<InterfaceSelector>,
+
+        /// 自动选择网卡的粒度
+        #[arg(long, value_enum, default_value_t = SelectScope::Ip)]
+        select_per: SelectScope,
 
         /// 路由度量值，值越小优先级越高
         #[arg(long, default_value_t = 0)]
@@ -131,6 +353,18 @@ pub enum RouteAddActions {
         /// 是否检查目标地址是否可达
         #[arg(long, default_value_t = false)]
         no_check: bool,
+
+        /// 连通性检查方式：icmp、tcp:<端口>、tls:<端口>、http://<主机>/<路径> 或 https://<主机>/<路径>
+        #[arg(long, value_parser = parse_check, default_value = "icmp")]
+        check: Check,
+
+        /// 需要添加路由的地址族
+        #[arg(long, value_enum, default_value_t = Family::V4)]
+        family: Family,
+
+        /// 将解析结果聚合为最少的 CIDR 集合
+        #[arg(long, default_value_t = false)]
+        aggregate: bool,
     },
 }
 
@@ -149,6 +383,32 @@ pub enum NetActions {
         /// 解析的域名
         #[arg(long)]
         domain: String,
+
+        /// 需要展示的地址族
+        #[arg(long, value_enum, default_value_t = Family::V4)]
+        family: Family,
+    },
+    /// 使用指定网卡测试到目标地址的连通性
+    Ping {
+        /// 网卡，支持网卡索引、名称、MAC 地址、glob:、regex: 与 type: 选择器
+        #[arg(long = "ifindex", value_parser = parse_interface)]
+        if_index: InterfaceSelector,
+
+        /// 目标 IP 地址或域名，域名会并发测试所有解析结果
+        #[arg(long = "dest")]
+        destination: String,
+
+        /// 每个目标地址发送的请求数量
+        #[arg(long, default_value_t = 4)]
+        count: u16,
+
+        /// 每个请求的超时时间，毫秒
+        #[arg(long, default_value_t = 1000)]
+        timeout: u64,
+
+        /// 目标为域名时需要测试的地址族
+        #[arg(long, value_enum, default_value_t = Family::V4)]
+        family: Family,
     },
 }
 
@@ -169,3 +429,43 @@ fn less_than_one_error(s: &str) -> Result<usize, String> {
 
     Ok(value)
 }
+
+/// 解析 CIDR 网段参数
+///
+/// # Arguments
+///
+/// * `s` - 输入的字符串
+///
+fn parse_cidr(s: &str) -> Result<Cidr, String> {
+    Cidr::parse(s, None, &HostBits::Reject).map_err(|e| e.message)
+}
+
+/// 解析网卡选择器
+///
+/// # Arguments
+///
+/// * `s` - 输入的字符串
+///
+fn parse_interface(s: &str) -> Result<InterfaceSelector, String> {
+    s.parse().map_err(|e: NetRouteError| e.message)
+}
+
+/// 解析网卡选择器或 auto
+///
+/// # Arguments
+///
+/// * `s` - 输入的字符串
+///
+fn parse_if_index(s: &str) -> Result<IfIndex, String> {
+    s.parse().map_err(|e: NetRouteError| e.message)
+}
+
+/// 解析连通性检查方式
+///
+/// # Arguments
+///
+/// * `s` - 输入的字符串
+///
+fn parse_check(s: &str) -> Result<Check, String> {
+    s.parse().map_err(|e: NetRouteError| e.message)
+}
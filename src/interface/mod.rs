@@ -1,55 +1,69 @@
-use crate::base::NetRouteError;
-use ipconfig;
-use ipconfig::IfType;
-use network_interface::NetworkInterface;
-use network_interface::NetworkInterfaceConfig;
+use crate::base::{NetRouteError, output};
+use crate::route::simulate;
 use prettytable::Table;
+use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 
+pub mod selector;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use windows as platform;
+#[cfg(not(windows))]
+mod unix;
+#[cfg(not(windows))]
+use unix as platform;
+
 pub struct Interface;
 
+/// 网卡类型
+///
+/// 部分类型仅在 Windows 下能够识别
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfType {
+    Other,
+    EthernetCsmacd,
+    Iso88025Tokenring,
+    Ppp,
+    SoftwareLoopback,
+    Atm,
+    Ieee80211,
+    Tunnel,
+    Ieee1394,
+    Unsupported,
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AdapterInfo {
     pub name: String,
     pub index: u32,
     pub mac_address: String,
     pub ip_address: String,
     pub gateway: String,
+    #[serde(default = "not_available")]
+    pub ipv6_address: String,
+    #[serde(default = "not_available")]
+    pub ipv6_gateway: String,
     pub if_type: IfType,
 }
 
+fn not_available() -> String {
+    "N/A".to_string()
+}
+
 impl Interface {
     pub fn new() -> Self {
         Interface
     }
 
     pub fn get_interfaces(&self) -> Result<Vec<AdapterInfo>, NetRouteError> {
-        // 使用 network-interface 获取适配器信息，备用
-        let ni_interfaces =
-            NetworkInterface::show().map_err(|e| NetRouteError::new(e.to_string()))?;
-        // 获取所有适配器信息
-        let adapter_info_vec = ipconfig::get_adapters()
-            .map_err(|e| NetRouteError::new(e.to_string()))?
-            .into_iter()
-            .map(|adapter| {
-                let mac_address = parse_mac_address(adapter.physical_address());
-                // 判断当前是否有获取不到 index 的适配器
-                let mut index = adapter.ipv6_if_index();
-                if index == 0 {
-                    index = find_interface_index_by_mac(&ni_interfaces, &mac_address);
-                }
-                // 转换对象
-                AdapterInfo {
-                    name: adapter.friendly_name().to_string(),
-                    index,
-                    mac_address: mac_address.unwrap_or("N/A".to_string()),
-                    ip_address: parse_address_list_to_string(adapter.ip_addresses()),
-                    gateway: parse_address_list_to_string(adapter.gateways()),
-                    if_type: adapter.if_type(),
-                }
-            })
-            .collect();
-
-        Ok(adapter_info_vec)
+        // 启用模拟网络环境时使用模拟的网卡列表
+        if let Some(adapters) = simulate::adapters() {
+            return Ok(adapters);
+        }
+        platform::get_adapters()
     }
 
     pub fn get_interface_by_index(&self, index: &u32) -> Result<AdapterInfo, NetRouteError> {
@@ -67,56 +81,105 @@ impl Interface {
     }
 
     pub fn get_ipv4_gateway(adapter: &AdapterInfo) -> Result<IpAddr, NetRouteError> {
-        // 获取网关地址
-        let gateway = adapter
-            .gateway
-            .parse::<IpAddr>()
-            .map_err(|e| NetRouteError::new(e.to_string()))?;
-        Ok(gateway)
+        // 获取网关地址，存在多个网关时使用第一个
+        parse_first_address(&adapter.gateway).map_err(|e| {
+            NetRouteError::new(format!("网卡 {} 没有可用的 IPv4 网关: {}", adapter.name, e))
+        })
     }
-}
 
-fn find_interface_index_by_mac(
-    network_interfaces: &Vec<NetworkInterface>,
-    mac_address: &Option<String>,
-) -> u32 {
-    let mac_address = match mac_address {
-        Some(mac) => mac,
-        None => return 0,
-    };
-    network_interfaces
-        .iter()
-        .find(|interface| {
-            if let Some(mac) = &interface.mac_addr {
-                mac.to_uppercase() == mac_address.to_uppercase()
-            } else {
-                false
-            }
+    pub fn get_ipv6_gateway(adapter: &AdapterInfo) -> Result<IpAddr, NetRouteError> {
+        parse_first_address(&adapter.ipv6_gateway).map_err(|e| {
+            NetRouteError::new(format!("网卡 {} 没有可用的 IPv6 网关: {}", adapter.name, e))
         })
-        .map(|interface| interface.index)
-        .unwrap_or(0)
+    }
+
+    /// 根据目标地址的地址族获取网卡的网关地址
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - 网卡信息
+    /// * `destination` - 目标 IP 地址
+    ///
+    pub fn get_gateway_for(
+        adapter: &AdapterInfo,
+        destination: &IpAddr,
+    ) -> Result<IpAddr, NetRouteError> {
+        match destination {
+            IpAddr::V4(_) => Self::get_ipv4_gateway(adapter),
+            IpAddr::V6(_) => Self::get_ipv6_gateway(adapter),
+        }
+    }
+
+    /// 根据目标地址的地址族获取网卡的源地址
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - 网卡信息
+    /// * `destination` - 目标 IP 地址
+    ///
+    pub fn get_source_address_for(
+        adapter: &AdapterInfo,
+        destination: &IpAddr,
+    ) -> Result<IpAddr, NetRouteError> {
+        let addresses = match destination {
+            IpAddr::V4(_) => &adapter.ip_address,
+            IpAddr::V6(_) => &adapter.ipv6_address,
+        };
+        parse_first_address(addresses).map_err(|e| {
+            NetRouteError::new(format!(
+                "网卡 {} 没有可用的 IPv{} 地址: {}",
+                adapter.name,
+                if destination.is_ipv4() { 4 } else { 6 },
+                e
+            ))
+        })
+    }
+}
+
+/// 解析逗号分隔的地址列表中的第一个地址
+///
+/// # Arguments
+///
+/// * `addresses` - 地址列表字符串，如 "192.168.1.1, 192.168.2.1"
+///
+fn parse_first_address(addresses: &str) -> Result<IpAddr, NetRouteError> {
+    addresses
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .parse::<IpAddr>()
+        .map_err(|e| NetRouteError::new(e.to_string()))
 }
 
 /// 将 IP 地址列表转换为字符串
 ///
-/// 如果没有 IPv4 地址，则返回 "N/A"
-/// 如果有 IPv4 地址，则返回以逗号分隔的字符串
+/// 如果没有对应地址族的地址，则返回 "N/A"
+/// 如果有对应地址族的地址，则返回以逗号分隔的字符串，IPv6 链路本地地址排在最后
 ///
 /// # Arguments
 ///
 /// * `addresses` - IP 地址列表
+/// * `ipv4` - 为 true 时只保留 IPv4 地址，否则只保留 IPv6 地址
 ///
-fn parse_address_list_to_string(addresses: &[IpAddr]) -> String {
-    let ipv4_addresses = addresses
+fn format_address_list(addresses: &[IpAddr], ipv4: bool) -> String {
+    let mut filtered_addresses = addresses
         .iter()
-        .filter(|ip| ip.is_ipv4())
-        .map(|ip| ip.to_string())
-        .collect::<Vec<String>>();
+        .filter(|ip| ip.is_ipv4() == ipv4)
+        .collect::<Vec<&IpAddr>>();
+    filtered_addresses.sort_by_key(|ip| match ip {
+        IpAddr::V6(v6) => v6.is_unicast_link_local(),
+        IpAddr::V4(_) => false,
+    });
 
-    if ipv4_addresses.is_empty() {
+    if filtered_addresses.is_empty() {
         "N/A".to_string()
     } else {
-        ipv4_addresses.join(", ")
+        filtered_addresses
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
     }
 }
 
@@ -139,35 +202,25 @@ fn parse_if_type(if_type: IfType) -> String {
         IfType::Tunnel => String::from("隧道"),
         IfType::Ieee1394 => String::from("IEEE 1394"),
         IfType::Unsupported => String::from("不支持"),
-        _ => String::from("未知"),
+        IfType::Unknown => String::from("未知"),
     }
 }
 
-/// 将 MAC 地址转换为字符串
-///
-/// 如果没有 MAC 地址，则返回 "N/A"
-///
-/// # Arguments
-///
-/// * `mac_address` - MAC 地址
-///
-fn parse_mac_address(mac_address: Option<&[u8]>) -> Option<String> {
-    mac_address
-        // 将 u8 数组转换为十六进制字符串，中间用冒号分隔
-        .map(|mac| {
-            mac.iter()
-                .map(|b| format!("{:02x}", b))
-                .collect::<Vec<String>>()
-                .join(":")
-        })
-}
-
 /// 将 IP 地址列表转换为字符串
 ///
 pub fn show_interface_list() -> Result<(), NetRouteError> {
     let interface = Interface::new();
     let adapters = interface.get_interfaces()?;
+    output::print_rows(&adapters, || show_interface_table(&adapters))
+}
 
+/// 展示网卡列表表格
+///
+/// # Arguments
+///
+/// * `adapters` - 网卡列表
+///
+fn show_interface_table(adapters: &[AdapterInfo]) {
     // 实现表格展示路由列表
     let mut table = Table::new();
     table.add_row(row![
@@ -176,7 +229,9 @@ pub fn show_interface_list() -> Result<(), NetRouteError> {
         "网卡名称",
         "IP地址",
         "MAC地址",
-        "网关地址"
+        "网关地址",
+        "IPv6地址",
+        "IPv6网关"
     ]);
     for adapter in adapters {
         table.add_row(row![
@@ -186,10 +241,11 @@ pub fn show_interface_list() -> Result<(), NetRouteError> {
             adapter.ip_address,
             adapter.mac_address,
             adapter.gateway,
+            adapter.ipv6_address,
+            adapter.ipv6_gateway,
         ]);
     }
     table.printstd();
-    Ok(())
 }
 
 #[cfg(test)]
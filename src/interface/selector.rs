@@ -0,0 +1,290 @@
+use crate::base::NetRouteError;
+use crate::interface::{AdapterInfo, IfType, Interface, parse_if_type};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// 可以识别的网卡类型
+const IF_TYPES: [IfType; 11] = [
+    IfType::Other,
+    IfType::EthernetCsmacd,
+    IfType::Iso88025Tokenring,
+    IfType::Ppp,
+    IfType::SoftwareLoopback,
+    IfType::Atm,
+    IfType::Ieee80211,
+    IfType::Tunnel,
+    IfType::Ieee1394,
+    IfType::Unsupported,
+    IfType::Unknown,
+];
+
+/// 网卡选择器
+///
+/// 网卡索引会在重启、更新驱动或重连 VPN 后变化，选择器可以通过名称、MAC 地址、
+/// 名称的通配符或正则表达式以及网卡类型选择网卡：
+///
+/// - `12`：网卡索引
+/// - `WLAN` 或 `name:WLAN`：网卡名称
+/// - `00:11:22:33:44:55` 或 `mac:00-11-22-33-44-55`：MAC 地址，不区分大小写与分隔符
+/// - `glob:以太网*`：名称通配符，`*` 匹配任意字符，`?` 匹配单个字符
+/// - `regex:^eth\d+$`：名称正则表达式
+/// - `type:Ieee80211`：该类型中索引最小的网卡
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceSelector {
+    /// 网卡索引
+    Index(u32),
+    /// 网卡名称
+    Name(String),
+    /// MAC 地址，已转换为小写并以冒号分隔
+    Mac(String),
+    /// 名称通配符
+    Glob(String),
+    /// 名称正则表达式，解析时已检查能够编译
+    Regex(String),
+    /// 网卡类型
+    Type(IfType),
+}
+
+impl FromStr for InterfaceSelector {
+    type Err = NetRouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(NetRouteError::new("网卡选择器不能为空".to_string()));
+        }
+        let Some((kind, value)) = s.split_once(':').filter(|(kind, _)| {
+            matches!(*kind, "index" | "name" | "mac" | "glob" | "regex" | "type")
+        }) else {
+            // 没有前缀时依次尝试索引、MAC 地址与名称
+            if let Ok(index) = s.parse::<u32>() {
+                return Ok(InterfaceSelector::Index(index));
+            }
+            if let Some(mac) = normalize_mac(s) {
+                return Ok(InterfaceSelector::Mac(mac));
+            }
+            return Ok(InterfaceSelector::Name(s.to_string()));
+        };
+        match kind {
+            "index" => value
+                .parse::<u32>()
+                .map(InterfaceSelector::Index)
+                .map_err(|_| NetRouteError::new(format!("无效的网卡索引 {}", value))),
+            "name" => Ok(InterfaceSelector::Name(value.to_string())),
+            "mac" => normalize_mac(value)
+                .map(InterfaceSelector::Mac)
+                .ok_or_else(|| NetRouteError::new(format!("无效的 MAC 地址 {}", value))),
+            "glob" => Ok(InterfaceSelector::Glob(value.to_string())),
+            "regex" => Regex::new(value)
+                .map(|_| InterfaceSelector::Regex(value.to_string()))
+                .map_err(|e| NetRouteError::new(format!("无效的正则表达式 {}: {}", value, e))),
+            _ => parse_type(value).map(InterfaceSelector::Type),
+        }
+    }
+}
+
+impl fmt::Display for InterfaceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterfaceSelector::Index(index) => write!(f, "{}", index),
+            InterfaceSelector::Name(name) => write!(f, "name:{}", name),
+            InterfaceSelector::Mac(mac) => write!(f, "mac:{}", mac),
+            InterfaceSelector::Glob(pattern) => write!(f, "glob:{}", pattern),
+            InterfaceSelector::Regex(pattern) => write!(f, "regex:{}", pattern),
+            InterfaceSelector::Type(if_type) => write!(f, "type:{:?}", if_type),
+        }
+    }
+}
+
+impl Serialize for InterfaceSelector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            InterfaceSelector::Index(index) => serializer.serialize_u32(*index),
+            selector => serializer.serialize_str(&selector.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InterfaceSelector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// 配置文件中的选择器可以是网卡索引或字符串
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Index(u32),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Index(index) => Ok(InterfaceSelector::Index(index)),
+            Raw::Text(text) => text
+                .parse()
+                .map_err(|e: NetRouteError| serde::de::Error::custom(e.message)),
+        }
+    }
+}
+
+impl InterfaceSelector {
+    /// 判断网卡是否满足选择器
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - 网卡信息
+    ///
+    pub fn matches(&self, adapter: &AdapterInfo) -> bool {
+        match self {
+            InterfaceSelector::Index(index) => adapter.index == *index,
+            InterfaceSelector::Name(name) => adapter.name == *name,
+            InterfaceSelector::Mac(mac) => {
+                normalize_mac(&adapter.mac_address).is_some_and(|adapter_mac| adapter_mac == *mac)
+            }
+            InterfaceSelector::Glob(pattern) => glob_match(pattern, &adapter.name),
+            InterfaceSelector::Regex(pattern) => {
+                Regex::new(pattern).is_ok_and(|regex| regex.is_match(&adapter.name))
+            }
+            InterfaceSelector::Type(if_type) => adapter.if_type == *if_type,
+        }
+    }
+
+    /// 从网卡列表中选择唯一满足选择器的网卡
+    ///
+    /// 按类型选择时使用索引最小的网卡，其他选择器没有或有多个网卡满足时返回错误
+    ///
+    /// # Arguments
+    ///
+    /// * `adapters` - 网卡列表
+    ///
+    pub fn select(&self, adapters: &[AdapterInfo]) -> Result<AdapterInfo, NetRouteError> {
+        let mut matched = adapters
+            .iter()
+            .filter(|adapter| self.matches(adapter))
+            .collect::<Vec<&AdapterInfo>>();
+        matched.sort_by_key(|adapter| adapter.index);
+        match matched.as_slice() {
+            [] => Err(NetRouteError::new(format!(
+                "没有与 {} 匹配的网卡，可用网卡: {}",
+                self,
+                describe_adapters(adapters.iter())
+            ))),
+            [adapter] => Ok((*adapter).clone()),
+            [first, ..] if matches!(self, InterfaceSelector::Type(_)) => Ok((*first).clone()),
+            _ => Err(NetRouteError::new(format!(
+                "{} 匹配到多个网卡: {}，请使用更精确的选择器",
+                self,
+                describe_adapters(matched.into_iter())
+            ))),
+        }
+    }
+
+    /// 通过系统网卡列表解析选择器对应的网卡索引
+    pub fn resolve_index(&self) -> Result<u32, NetRouteError> {
+        if let InterfaceSelector::Index(index) = self {
+            // 直接指定索引时不需要读取网卡列表，网卡是否存在由使用方检查
+            return Ok(*index);
+        }
+        let adapters = Interface::new().get_interfaces()?;
+        self.select(&adapters).map(|adapter| adapter.index)
+    }
+}
+
+/// 解析可选的网卡选择器对应的网卡索引
+///
+/// # Arguments
+///
+/// * `selector` - 网卡选择器，为空时返回空
+///
+pub fn resolve_optional_index(
+    selector: &Option<InterfaceSelector>,
+) -> Result<Option<u32>, NetRouteError> {
+    selector
+        .as_ref()
+        .map(InterfaceSelector::resolve_index)
+        .transpose()
+}
+
+/// 将网卡列表格式化为 `名称 (索引)` 的列表
+fn describe_adapters<'a>(adapters: impl Iterator<Item = &'a AdapterInfo>) -> String {
+    let list = adapters
+        .map(|adapter| format!("{} ({})", adapter.name, adapter.index))
+        .collect::<Vec<String>>();
+    if list.is_empty() {
+        "无".to_string()
+    } else {
+        list.join(", ")
+    }
+}
+
+/// 将 MAC 地址转换为小写并以冒号分隔，不是 MAC 地址时返回 None
+///
+/// # Arguments
+///
+/// * `mac` - MAC 地址，支持 `:`、`-` 分隔
+///
+fn normalize_mac(mac: &str) -> Option<String> {
+    let octets = mac.trim().split([':', '-']).collect::<Vec<&str>>();
+    if octets.len() != 6
+        || !octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return None;
+    }
+    Some(octets.join(":").to_ascii_lowercase())
+}
+
+/// 解析网卡类型，支持类型名称（不区分大小写）与中文描述
+///
+/// # Arguments
+///
+/// * `value` - 类型名称，如 `Ieee80211` 或 `无线局域网`
+///
+fn parse_type(value: &str) -> Result<IfType, NetRouteError> {
+    IF_TYPES
+        .into_iter()
+        .find(|if_type| {
+            format!("{:?}", if_type).eq_ignore_ascii_case(value) || parse_if_type(*if_type) == value
+        })
+        .ok_or_else(|| {
+            let names = IF_TYPES
+                .iter()
+                .map(|if_type| format!("{:?}", if_type))
+                .collect::<Vec<String>>();
+            NetRouteError::new(format!(
+                "无效的网卡类型 {}，支持 {}",
+                value,
+                names.join("、")
+            ))
+        })
+}
+
+/// 判断名称是否满足通配符，`*` 匹配任意数量的字符，`?` 匹配单个字符
+///
+/// # Arguments
+///
+/// * `pattern` - 通配符
+/// * `name` - 名称
+///
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 `*` 的位置以及它匹配到的名称位置，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
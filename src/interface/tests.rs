@@ -1,4 +1,5 @@
-use crate::interface::Interface;
+use crate::interface::selector::{InterfaceSelector, glob_match};
+use crate::interface::{IfType, Interface};
 use test_case::test_case;
 
 #[test_case(true ; "测试创建接口类")]
@@ -15,3 +16,83 @@ fn interface_get_interfaces_success(expected: bool) {
     let interfaces = result.unwrap();
     assert_eq!(!interfaces.is_empty(), expected);
 }
+
+#[test_case(12, true ; "测试获取模拟网卡信息")]
+#[test_case(99, false ; "测试获取不存在的模拟网卡信息")]
+fn interface_get_simulated_interface(index: u32, expected: bool) {
+    let fixture = crate::route::simulate::load_fixture("simulate.example.json").unwrap();
+    crate::route::simulate::enable(fixture);
+    let interface = Interface::new();
+    let result = interface.get_interface_by_index(&index);
+    assert_eq!(result.is_ok(), expected);
+    if expected {
+        let adapter = result.unwrap();
+        assert_eq!(adapter.name, "以太网");
+        assert_eq!(
+            Interface::get_ipv4_gateway(&adapter).unwrap().to_string(),
+            "192.168.1.1"
+        );
+    }
+}
+
+#[test_case("12", InterfaceSelector::Index(12) ; "测试解析网卡索引")]
+#[test_case("WLAN", InterfaceSelector::Name("WLAN".to_string()) ; "测试解析网卡名称")]
+#[test_case("name:12", InterfaceSelector::Name("12".to_string()) ; "测试解析数字网卡名称")]
+#[test_case("00-11-22-33-44-AA", InterfaceSelector::Mac("00:11:22:33:44:aa".to_string()) ; "测试解析MAC地址")]
+#[test_case("glob:以太网*", InterfaceSelector::Glob("以太网*".to_string()) ; "测试解析通配符")]
+#[test_case("regex:^W", InterfaceSelector::Regex("^W".to_string()) ; "测试解析正则表达式")]
+#[test_case("type:ieee80211", InterfaceSelector::Type(IfType::Ieee80211) ; "测试解析网卡类型")]
+#[test_case("type:无线局域网", InterfaceSelector::Type(IfType::Ieee80211) ; "测试解析网卡类型中文描述")]
+fn interface_selector_parse(input: &str, expected: InterfaceSelector) {
+    let selector = input.parse::<InterfaceSelector>().unwrap();
+    assert_eq!(selector, expected);
+    // 格式化后能够重新解析为相同的选择器
+    assert_eq!(
+        selector.to_string().parse::<InterfaceSelector>().unwrap(),
+        expected
+    );
+}
+
+#[test_case("" ; "测试空选择器")]
+#[test_case("mac:00:11:22" ; "测试无效的MAC地址")]
+#[test_case("regex:(" ; "测试无效的正则表达式")]
+#[test_case("type:Bluetooth" ; "测试无效的网卡类型")]
+fn interface_selector_parse_invalid(input: &str) {
+    assert!(input.parse::<InterfaceSelector>().is_err());
+}
+
+#[test_case("以太网*", "以太网 2", true ; "测试星号匹配任意字符")]
+#[test_case("eth?", "eth0", true ; "测试问号匹配单个字符")]
+#[test_case("eth?", "eth10", false ; "测试问号不匹配多个字符")]
+#[test_case("*VPN*", "Corp VPN Adapter", true ; "测试多个星号")]
+#[test_case("wlan*", "WLAN", false ; "测试区分大小写")]
+fn interface_glob_match(pattern: &str, name: &str, expected: bool) {
+    assert_eq!(glob_match(pattern, name), expected);
+}
+
+#[test_case("12", Some(12) ; "测试按索引选择")]
+#[test_case("WLAN", Some(28) ; "测试按名称选择")]
+#[test_case("66:77:88:99:AA:BB", Some(28) ; "测试按MAC地址选择")]
+#[test_case("glob:以太*", Some(12) ; "测试按通配符选择")]
+#[test_case("regex:^(WLAN|VPN)$", Some(28) ; "测试按正则表达式选择")]
+#[test_case("type:EthernetCsmacd", Some(12) ; "测试按类型选择第一个网卡")]
+#[test_case("蓝牙", None ; "测试没有匹配的网卡")]
+#[test_case("type:Tunnel", None ; "测试没有匹配类型的网卡")]
+#[test_case("regex:.", None ; "测试匹配到多个网卡")]
+fn interface_selector_select(input: &str, expected: Option<u32>) {
+    let fixture = crate::route::simulate::load_fixture("simulate.example.json").unwrap();
+    let mut adapters = fixture.adapters.clone();
+    // 添加一个索引更大的同类型网卡，按类型选择时使用索引最小的网卡
+    let mut second = adapters[0].clone();
+    second.index = 31;
+    second.name = "Ethernet 2".to_string();
+    second.mac_address = "00:11:22:33:44:66".to_string();
+    adapters.insert(0, second);
+    let selector = input.parse::<InterfaceSelector>().unwrap();
+    let result = selector.select(&adapters);
+    assert_eq!(result.as_ref().ok().map(|adapter| adapter.index), expected);
+    if let Err(err) = result {
+        // 错误信息列出可用或匹配到的网卡
+        assert!(err.message.contains("("));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::base::NetRouteError;
+use crate::interface::{AdapterInfo, IfType, format_address_list};
+use network_interface::Addr;
+use network_interface::NetworkInterface;
+use network_interface::NetworkInterfaceConfig;
+use std::net::IpAddr;
+
+/// 通过 network-interface 获取类 Unix 系统中的所有适配器信息
+///
+/// 网关地址取自路由表中各网卡的默认路由
+///
+pub fn get_adapters() -> Result<Vec<AdapterInfo>, NetRouteError> {
+    let default_gateways = find_default_gateways();
+    let adapter_info_vec = NetworkInterface::show()
+        .map_err(|e| NetRouteError::new(e.to_string()))?
+        .into_iter()
+        .map(|interface| {
+            let addresses = interface
+                .addr
+                .iter()
+                .map(|addr| match addr {
+                    Addr::V4(v4) => IpAddr::V4(v4.ip),
+                    Addr::V6(v6) => IpAddr::V6(v6.ip),
+                })
+                .collect::<Vec<IpAddr>>();
+            let gateways = default_gateways
+                .iter()
+                .filter(|(index, _)| *index == interface.index)
+                .map(|(_, gateway)| *gateway)
+                .collect::<Vec<IpAddr>>();
+            AdapterInfo {
+                if_type: detect_if_type(&interface.name),
+                index: interface.index,
+                mac_address: interface.mac_addr.unwrap_or("N/A".to_string()),
+                ip_address: format_address_list(&addresses, true),
+                gateway: format_address_list(&gateways, true),
+                ipv6_address: format_address_list(&addresses, false),
+                ipv6_gateway: format_address_list(&gateways, false),
+                name: interface.name,
+            }
+        })
+        .collect();
+    Ok(adapter_info_vec)
+}
+
+/// 根据 /sys/class/net 中的信息判断网卡类型
+///
+/// # Arguments
+///
+/// * `name` - 网卡名称
+#[cfg(target_os = "linux")]
+fn detect_if_type(name: &str) -> IfType {
+    let base = std::path::Path::new("/sys/class/net").join(name);
+    if base.join("wireless").exists() || base.join("phy80211").exists() {
+        return IfType::Ieee80211;
+    }
+    // ARPHRD_* 类型值，见 linux/if_arp.h
+    let arp_type = std::fs::read_to_string(base.join("type"))
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok());
+    match arp_type {
+        Some(1) => IfType::EthernetCsmacd,
+        Some(512) => IfType::Ppp,
+        Some(772) => IfType::SoftwareLoopback,
+        Some(768) | Some(769) | Some(776) | Some(778) | Some(65534) => IfType::Tunnel,
+        Some(19) => IfType::Atm,
+        Some(24) => IfType::Ieee1394,
+        Some(_) => IfType::Other,
+        None => IfType::Unknown,
+    }
+}
+
+/// 非 Linux 系统只能根据名称粗略判断网卡类型
+///
+/// # Arguments
+///
+/// * `name` - 网卡名称
+#[cfg(not(target_os = "linux"))]
+fn detect_if_type(name: &str) -> IfType {
+    if name.starts_with("lo") {
+        IfType::SoftwareLoopback
+    } else {
+        IfType::Unknown
+    }
+}
+
+/// 从路由表的默认路由中查找各网卡的网关地址
+///
+/// 路由表读取失败时返回空列表，不影响网卡信息的获取
+fn find_default_gateways() -> Vec<(u32, IpAddr)> {
+    use crate::route::backend;
+
+    backend::new_backend()
+        .and_then(|backend| backend.get_routes())
+        .map(|routes| {
+            routes
+                .into_iter()
+                .filter(|route| route.prefix == 0 && !route.gateway.is_unspecified())
+                .filter_map(|route| route.ifindex.map(|index| (index, route.gateway)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
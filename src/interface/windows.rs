@@ -0,0 +1,98 @@
+use crate::base::NetRouteError;
+use crate::interface::{AdapterInfo, IfType, format_address_list};
+use network_interface::NetworkInterface;
+use network_interface::NetworkInterfaceConfig;
+
+/// 通过 ipconfig 获取 Windows 系统中的所有适配器信息
+///
+pub fn get_adapters() -> Result<Vec<AdapterInfo>, NetRouteError> {
+    // 使用 network-interface 获取适配器信息，备用
+    let ni_interfaces = NetworkInterface::show().map_err(|e| NetRouteError::new(e.to_string()))?;
+    // 获取所有适配器信息
+    let adapter_info_vec = ipconfig::get_adapters()
+        .map_err(|e| NetRouteError::new(e.to_string()))?
+        .into_iter()
+        .map(|adapter| {
+            let mac_address = parse_mac_address(adapter.physical_address());
+            // 判断当前是否有获取不到 index 的适配器
+            let mut index = adapter.ipv6_if_index();
+            if index == 0 {
+                index = find_interface_index_by_mac(&ni_interfaces, &mac_address);
+            }
+            // 转换对象
+            AdapterInfo {
+                name: adapter.friendly_name().to_string(),
+                index,
+                mac_address: mac_address.unwrap_or("N/A".to_string()),
+                ip_address: format_address_list(adapter.ip_addresses(), true),
+                gateway: format_address_list(adapter.gateways(), true),
+                ipv6_address: format_address_list(adapter.ip_addresses(), false),
+                ipv6_gateway: format_address_list(adapter.gateways(), false),
+                if_type: convert_if_type(adapter.if_type()),
+            }
+        })
+        .collect();
+
+    Ok(adapter_info_vec)
+}
+
+fn find_interface_index_by_mac(
+    network_interfaces: &[NetworkInterface],
+    mac_address: &Option<String>,
+) -> u32 {
+    let mac_address = match mac_address {
+        Some(mac) => mac,
+        None => return 0,
+    };
+    network_interfaces
+        .iter()
+        .find(|interface| {
+            if let Some(mac) = &interface.mac_addr {
+                mac.to_uppercase() == mac_address.to_uppercase()
+            } else {
+                false
+            }
+        })
+        .map(|interface| interface.index)
+        .unwrap_or(0)
+}
+
+/// 将 ipconfig 的网卡类型转换为本地网卡类型
+///
+/// # Arguments
+///
+/// * `if_type` - ipconfig 网卡类型
+fn convert_if_type(if_type: ipconfig::IfType) -> IfType {
+    match if_type {
+        ipconfig::IfType::Other => IfType::Other,
+        ipconfig::IfType::EthernetCsmacd => IfType::EthernetCsmacd,
+        ipconfig::IfType::Iso88025Tokenring => IfType::Iso88025Tokenring,
+        ipconfig::IfType::Ppp => IfType::Ppp,
+        ipconfig::IfType::SoftwareLoopback => IfType::SoftwareLoopback,
+        ipconfig::IfType::Atm => IfType::Atm,
+        ipconfig::IfType::Ieee80211 => IfType::Ieee80211,
+        ipconfig::IfType::Tunnel => IfType::Tunnel,
+        ipconfig::IfType::Ieee1394 => IfType::Ieee1394,
+        ipconfig::IfType::Unsupported => IfType::Unsupported,
+        _ => IfType::Unknown,
+    }
+}
+
+/// 将 MAC 地址转换为字符串
+///
+/// 如果没有 MAC 地址，则返回 "N/A"
+///
+/// # Arguments
+///
+/// * `mac_address` - MAC 地址
+///
+fn parse_mac_address(mac_address: Option<&[u8]>) -> Option<String> {
+    mac_address
+        // 将 u8 数组转换为十六进制字符串，中间用冒号分隔
+        .map(|mac| {
+            mac.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<String>>()
+                .join(":")
+        })
+}
@@ -3,22 +3,64 @@ extern crate prettytable;
 mod base;
 mod command;
 mod interface;
+mod probe;
 mod route;
 
 use crate::base::NetRouteError;
-use crate::command::{Cli, Commands, InterfaceActions, NetActions, RouteActions, RouteAddActions};
+use crate::base::output::{self, OutputOptions};
+use crate::command::{
+    Cli, Commands, ConfigActions, InterfaceActions, NetActions, RouteActions, RouteAddActions,
+};
+use crate::interface::selector::resolve_optional_index;
+use crate::route::select::AdapterSelection;
 use clap::Parser;
+use std::time::Duration;
 
 /// 程序入口主方法
 ///
 pub fn run() -> Result<(), NetRouteError> {
     let cli = Cli::parse();
 
+    if let Some(state_path) = &cli.state {
+        route::state::set_path(state_path);
+    }
+    route::snapshot::set_keep(cli.snapshot_keep);
+    output::set_output(OutputOptions::new(cli.output, cli.format.clone())?);
+    // 启用模拟网络环境
+    if let Some(fixture_path) = &cli.simulate {
+        route::simulate::enable(route::simulate::load_fixture(fixture_path)?);
+        eprintln!("当前为模拟模式，不会修改系统路由表");
+    }
+
     // 处理子命令
     match &cli.command {
         Some(command) => match command {
             Commands::Route { action } => match action {
-                RouteActions::List { page, page_size } => route::show_route_list(*page_size, *page),
+                RouteActions::List {
+                    page,
+                    page_size,
+                    all,
+                    managed,
+                    managed_only,
+                    ifindex,
+                    dest_within,
+                    dest_contains,
+                    gateway,
+                    family,
+                    metric_max,
+                    sort,
+                } => {
+                    let filter = route::filter::RouteFilter {
+                        ifindex: resolve_optional_index(ifindex)?,
+                        dest_within: *dest_within,
+                        dest_contains: *dest_contains,
+                        gateway: *gateway,
+                        family: *family,
+                        metric_max: *metric_max,
+                        managed_only: *managed_only,
+                    };
+                    route::show_route_list(*page_size, *page, all, managed, &filter, sort)
+                }
                 RouteActions::Add { action } => match action {
                     RouteAddActions::Ip {
                         destination,
@@ -27,24 +69,84 @@ pub fn run() -> Result<(), NetRouteError> {
                         gateway,
                         metric,
                         no_check,
-                    } => route::add_route(destination, prefix, if_index, gateway, metric, no_check),
+                        check,
+                        host_bits,
+                    } => route::add_route(
+                        destination,
+                        prefix,
+                        &if_index.resolve_index()?,
+                        gateway,
+                        metric,
+                        (!no_check).then_some(check),
+                        host_bits,
+                    ),
                     RouteAddActions::Domain {
                         domain,
                         if_index,
+                        candidates,
+                        select_per,
                         metric,
                         no_check,
-                    } => route::add_domain_route(domain, if_index, metric, no_check),
+                        check,
+                        family,
+                        aggregate,
+                    } => route::add_domain_route(
+                        domain,
+                        &AdapterSelection::new(if_index, candidates, *select_per)?,
+                        metric,
+                        (!no_check).then_some(check),
+                        family,
+                        aggregate,
+                    ),
                 },
+                RouteActions::Set {
+                    destination,
+                    prefix,
+                    if_index,
+                    metric,
+                    gateway,
+                    new_if_index,
+                    host_bits,
+                } => route::set_route(
+                    destination,
+                    prefix,
+                    &resolve_optional_index(if_index)?,
+                    metric,
+                    gateway,
+                    &resolve_optional_index(new_if_index)?,
+                    host_bits,
+                ),
+                RouteActions::Backup { out } => route::backup_route_table(out),
+                RouteActions::Restore {
+                    path,
+                    latest,
+                    no_confirm,
+                } => route::restore_route_table(path, latest, no_confirm),
+                RouteActions::Lookup { destination } => route::show_route_lookup(destination),
                 RouteActions::Remove {
                     destination,
                     domain,
                     if_index,
                     prefix,
+                    family,
+                    host_bits,
+                    no_confirm,
                 } => {
                     if destination.is_empty() {
-                        Ok(route::remove_domain_route(domain, if_index)?)
+                        Ok(route::remove_domain_route(
+                            domain,
+                            &resolve_optional_index(if_index)?,
+                            no_confirm,
+                            family,
+                        )?)
                     } else if domain.is_empty() {
-                        Ok(route::remove_route(destination, prefix)?)
+                        Ok(route::remove_route(
+                            destination,
+                            prefix,
+                            &resolve_optional_index(if_index)?,
+                            no_confirm,
+                            host_bits,
+                        )?)
                     } else {
                         Err(NetRouteError::new(
                             "目标 IP 地址和域名必须有一个不为空".to_string(),
@@ -56,13 +158,83 @@ pub fn run() -> Result<(), NetRouteError> {
                 InterfaceActions::List {} => interface::show_interface_list(),
             },
             Commands::Net { action } => match action {
-                NetActions::Dns { domain } => route::show_domain_ips_info(domain),
+                NetActions::Dns { domain, family } => route::show_domain_ips_info(domain, family),
+                NetActions::Ping {
+                    if_index,
+                    destination,
+                    count,
+                    timeout,
+                    family,
+                } => {
+                    let options = probe::PingOptions {
+                        count: *count,
+                        timeout: Duration::from_millis(*timeout),
+                    };
+                    route::ping_destination(
+                        &if_index.resolve_index()?,
+                        destination,
+                        &options,
+                        family,
+                    )
+                }
             },
             Commands::Config {
+                action,
                 path,
+                config_format,
                 no_confirm,
                 cancel,
-            } => Ok(route::apply_config_file(path, no_confirm, cancel)?),
+                host_bits,
+                aggregate,
+            } => match action {
+                Some(ConfigActions::Plan { out }) => Ok(route::plan_config_file(
+                    path,
+                    config_format,
+                    cancel,
+                    host_bits,
+                    aggregate,
+                    out,
+                )?),
+                Some(ConfigActions::Apply { plan: Some(plan) }) => {
+                    Ok(route::apply_plan_file(plan, no_confirm)?)
+                }
+                Some(ConfigActions::Validate { resolve }) => Ok(route::validate_config_file(
+                    path,
+                    config_format,
+                    host_bits,
+                    resolve,
+                )?),
+                Some(ConfigActions::Convert { to, out }) => {
+                    Ok(route::convert_config_file(path, config_format, to, out)?)
+                }
+                Some(ConfigActions::Apply { plan: None }) | None => Ok(route::apply_config_file(
+                    path,
+                    config_format,
+                    no_confirm,
+                    cancel,
+                    host_bits,
+                    aggregate,
+                )?),
+            },
+            Commands::Daemon {
+                config,
+                config_format,
+                min_ttl,
+                max_ttl,
+                grace,
+                cleanup,
+                host_bits,
+            } => route::run_daemon(
+                config,
+                config_format,
+                host_bits,
+                &route::daemon::DaemonOptions {
+                    min_ttl: *min_ttl,
+                    max_ttl: *max_ttl,
+                    grace: *grace,
+                    cleanup: *cleanup,
+                },
+            ),
         },
         None => {
             println!("无效的命令");
@@ -0,0 +1,290 @@
+use crate::base::NetRouteError;
+use crate::probe::bind_device;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// 连通性检查方式
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Check {
+    /// 发送 ICMP 回显请求
+    #[default]
+    Icmp,
+    /// 建立 TCP 连接，如 `tcp:443`
+    Tcp(u16),
+    /// 完成 TLS 握手，如 `tls:443`
+    Tls(u16),
+    /// 发送 HTTP 请求并收到响应，如 `http://host/path`
+    Http(HttpCheck),
+}
+
+/// HTTP 检查的请求地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpCheck {
+    /// 是否使用 HTTPS
+    pub tls: bool,
+    /// 请求的主机名，用于 Host 请求头与 TLS 的 SNI
+    pub host: String,
+    /// 端口
+    pub port: u16,
+    /// 请求路径
+    pub path: String,
+}
+
+impl FromStr for Check {
+    type Err = NetRouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            NetRouteError::new(format!(
+                "无效的检查方式 {}，支持 icmp、tcp:<端口>、tls:<端口>、http://<主机>/<路径> 与 https://<主机>/<路径>",
+                s
+            ))
+        };
+        let parse_port = |port: &str| port.parse::<u16>().ok().filter(|port| *port != 0);
+        if s == "icmp" {
+            return Ok(Check::Icmp);
+        }
+        if let Some(port) = s.strip_prefix("tcp:") {
+            return parse_port(port).map(Check::Tcp).ok_or_else(invalid);
+        }
+        if let Some(port) = s.strip_prefix("tls:") {
+            return parse_port(port).map(Check::Tls).ok_or_else(invalid);
+        }
+        let (tls, rest) = match (s.strip_prefix("http://"), s.strip_prefix("https://")) {
+            (Some(rest), _) => (false, rest),
+            (_, Some(rest)) => (true, rest),
+            _ => return Err(invalid()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let default_port = if tls { 443 } else { 80 };
+        // IPv6 地址的主机名写在方括号中，如 http://[2001:db8::1]:8080/
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, parse_port(port).ok_or_else(invalid)?)
+            }
+            _ => (authority, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Check::Http(HttpCheck {
+            tls,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        }))
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Icmp => write!(f, "icmp"),
+            Check::Tcp(port) => write!(f, "tcp:{}", port),
+            Check::Tls(port) => write!(f, "tls:{}", port),
+            Check::Http(http) => {
+                let scheme = if http.tls { "https" } else { "http" };
+                write!(f, "{}://{}{}", scheme, http.authority(), http.path)
+            }
+        }
+    }
+}
+
+impl Serialize for Check {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Check {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map_err(|e: NetRouteError| serde::de::Error::custom(e.message))
+    }
+}
+
+impl HttpCheck {
+    /// Host 请求头，使用默认端口时省略端口
+    fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        let default_port = if self.tls { 443 } else { 80 };
+        if self.port == default_port {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+}
+
+/// 从源地址建立到目标地址的 TCP 连接
+///
+/// # Arguments
+///
+/// * `source` - 源 IP 地址
+/// * `device` - 网卡名称，Linux 下尝试绑定到该网卡
+/// * `target` - 目标地址与端口
+/// * `timeout` - 连接与读写超时时间
+///
+pub fn connect(
+    source: IpAddr,
+    device: Option<&str>,
+    target: SocketAddr,
+    timeout: std::time::Duration,
+) -> Result<TcpStream, NetRouteError> {
+    let socket = Socket::new(
+        Domain::for_address(target),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )
+    .map_err(|e| NetRouteError::new(format!("创建 TCP 套接字失败: {}", e)))?;
+    bind_device(&socket, device);
+    socket
+        .bind(&SockAddr::from(SocketAddr::new(source, 0)))
+        .map_err(|e| NetRouteError::new(format!("绑定源地址 {} 失败: {}", source, e)))?;
+    socket
+        .connect_timeout(&SockAddr::from(target), timeout)
+        .map_err(|e| NetRouteError::new(format!("连接 {} 失败: {}", target, e)))?;
+    let stream = TcpStream::from(socket);
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| NetRouteError::new(format!("设置超时时间失败: {}", e)))?;
+    Ok(stream)
+}
+
+/// 在 TCP 连接上完成 TLS 握手，返回加密连接与协商的协议版本
+///
+/// 检查只关心目标能否完成握手，不校验证书是否可信，但仍然校验握手签名
+///
+/// # Arguments
+///
+/// * `stream` - TCP 连接
+/// * `server_name` - TLS 的 SNI，域名或 IP 地址
+///
+pub fn tls_handshake(
+    mut stream: TcpStream,
+    server_name: &str,
+) -> Result<(StreamOwned<ClientConnection, TcpStream>, String), NetRouteError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = AcceptAnyCertificate(provider.signature_verification_algorithms);
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| NetRouteError::new(format!("TLS 配置错误: {}", e)))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let name = ServerName::try_from(server_name.to_string())
+        .map_err(|e| NetRouteError::new(format!("无效的 TLS 服务器名称 {}: {}", server_name, e)))?;
+    let mut connection = ClientConnection::new(Arc::new(config), name)
+        .map_err(|e| NetRouteError::new(format!("TLS 配置错误: {}", e)))?;
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut stream)
+            .map_err(|e| NetRouteError::new(format!("TLS 握手失败: {}", e)))?;
+    }
+    let version = connection
+        .protocol_version()
+        .map_or("TLS".to_string(), |version| {
+            format!("{:?}", version).replace('_', ".")
+        });
+    Ok((StreamOwned::new(connection, stream), version))
+}
+
+/// 发送 HTTP GET 请求并读取响应状态码
+///
+/// # Arguments
+///
+/// * `stream` - 已建立的连接
+/// * `http` - 请求地址
+///
+pub fn http_request<S: Read + Write>(
+    stream: &mut S,
+    http: &HttpCheck,
+) -> Result<u16, NetRouteError> {
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: net-route-rs\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+        http.path,
+        http.authority()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| NetRouteError::new(format!("发送 HTTP 请求失败: {}", e)))?;
+    // 只需要读取状态行
+    let mut response = vec![];
+    let mut buf = [0u8; 512];
+    while !response.windows(2).any(|window| window == b"\r\n") && response.len() < 4096 {
+        let len = stream
+            .read(&mut buf)
+            .map_err(|e| NetRouteError::new(format!("读取 HTTP 响应失败: {}", e)))?;
+        if len == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..len]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    status_line
+        .strip_prefix("HTTP/")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| NetRouteError::new(format!("无效的 HTTP 响应: {}", status_line)))
+}
+
+/// 接受任何证书的校验器，只校验握手签名
+#[derive(Debug)]
+struct AcceptAnyCertificate(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
@@ -0,0 +1,210 @@
+use crate::base::NetRouteError;
+use crate::probe::bind_device;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+/// ICMP 回显请求类型
+const ICMP_ECHO_REQUEST: u8 = 8;
+/// ICMP 回显应答类型
+const ICMP_ECHO_REPLY: u8 = 0;
+/// ICMPv6 回显请求类型
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+/// ICMPv6 回显应答类型
+const ICMPV6_ECHO_REPLY: u8 = 129;
+/// 回显请求携带的数据
+const PAYLOAD: &[u8] = b"net-route-rs ping";
+
+/// 为每个套接字分配不同的标识符，原始套接字会收到所有回显应答，需要按标识符区分
+static NEXT_IDENTIFIER: AtomicU16 = AtomicU16::new(0);
+
+/// 绑定到指定源地址的 ICMP 回显套接字
+///
+/// 优先使用无需特权的 ICMP 数据报套接字（Linux 需要当前用户组位于 `net.ipv4.ping_group_range` 内），
+/// 不可用时退回到需要管理员权限的原始套接字
+pub struct IcmpSocket {
+    socket: Socket,
+    /// 是否为原始套接字
+    raw: bool,
+    /// 回显请求的标识符，数据报套接字中由内核改写
+    identifier: u16,
+    /// 目标地址
+    target: IpAddr,
+}
+
+impl IcmpSocket {
+    /// 创建 ICMP 套接字，绑定源地址并连接到目标地址
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - 源 IP 地址
+    /// * `device` - 网卡名称，Linux 下尝试绑定到该网卡，失败时只绑定源地址
+    /// * `target` - 目标 IP 地址
+    ///
+    pub fn open(
+        source: IpAddr,
+        device: Option<&str>,
+        target: IpAddr,
+    ) -> Result<IcmpSocket, NetRouteError> {
+        if source.is_ipv4() != target.is_ipv4() {
+            return Err(NetRouteError::new(format!(
+                "源地址 {} 与目标地址 {} 的地址族不一致",
+                source, target
+            )));
+        }
+        let (domain, protocol) = match target {
+            IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+            IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+        };
+        let (socket, raw) = match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+            Ok(socket) => (socket, false),
+            Err(dgram_err) => match Socket::new(domain, Type::RAW, Some(protocol)) {
+                Ok(socket) => (socket, true),
+                Err(raw_err) => {
+                    return Err(NetRouteError::new(format!(
+                        "创建 ICMP 套接字失败: {}，原始套接字: {}。\
+                         Linux 下可将用户组加入 net.ipv4.ping_group_range，或以管理员权限运行",
+                        dgram_err, raw_err
+                    )));
+                }
+            },
+        };
+        bind_device(&socket, device);
+        socket
+            .bind(&SockAddr::from(SocketAddr::new(source, 0)))
+            .map_err(|e| NetRouteError::new(format!("绑定源地址 {} 失败: {}", source, e)))?;
+        socket
+            .connect(&SockAddr::from(SocketAddr::new(target, 0)))
+            .map_err(|e| NetRouteError::new(format!("连接目标地址 {} 失败: {}", target, e)))?;
+        let identifier = (std::process::id() as u16)
+            .wrapping_add(NEXT_IDENTIFIER.fetch_add(1, Ordering::Relaxed));
+        Ok(IcmpSocket {
+            socket,
+            raw,
+            identifier,
+            target,
+        })
+    }
+
+    /// 发送一次回显请求并等待应答，超时返回 None
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence` - 序号
+    /// * `timeout` - 超时时间
+    ///
+    pub fn echo(
+        &mut self,
+        sequence: u16,
+        timeout: Duration,
+    ) -> Result<Option<Duration>, NetRouteError> {
+        let packet = build_echo_request(&self.target, self.identifier, sequence);
+        let start = Instant::now();
+        self.socket.send(&packet).map_err(|e| {
+            NetRouteError::new(format!("发送 ICMP 请求到 {} 失败: {}", self.target, e))
+        })?;
+        let mut buf = [0u8; 2048];
+        loop {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .map_err(|e| NetRouteError::new(format!("设置超时时间失败: {}", e)))?;
+            match self.socket.read(&mut buf) {
+                Ok(len) => {
+                    let identifier = self.raw.then_some(self.identifier);
+                    if parse_echo_reply(&buf[..len], &self.target, identifier) == Some(sequence) {
+                        return Ok(Some(start.elapsed()));
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    return Err(NetRouteError::new(format!(
+                        "接收 {} 的 ICMP 应答失败: {}",
+                        self.target, e
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// 构造回显请求报文
+///
+/// ICMPv6 的校验和包含 IP 伪首部，由内核计算
+///
+/// # Arguments
+///
+/// * `target` - 目标 IP 地址，决定报文类型
+/// * `identifier` - 标识符
+/// * `sequence` - 序号
+///
+pub fn build_echo_request(target: &IpAddr, identifier: u16, sequence: u16) -> Vec<u8> {
+    let kind = match target {
+        IpAddr::V4(_) => ICMP_ECHO_REQUEST,
+        IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+    };
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    if target.is_ipv4() {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// 计算 Internet 校验和
+///
+/// # Arguments
+///
+/// * `data` - 报文
+///
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// 解析回显应答报文，返回应答的序号，不是本次请求的应答时返回 None
+///
+/// # Arguments
+///
+/// * `data` - 收到的报文，IPv4 原始套接字收到的报文带有 IP 首部
+/// * `target` - 目标 IP 地址，决定报文类型
+/// * `identifier` - 期望的标识符，数据报套接字的标识符由内核改写，不检查
+///
+pub fn parse_echo_reply(data: &[u8], target: &IpAddr, identifier: Option<u16>) -> Option<u16> {
+    let (data, reply) = match target {
+        IpAddr::V4(_) => {
+            // 带有 IP 首部时跳过首部，ICMP 报文的第一个字节为类型，不会以 4 开头
+            let data = if data.first()? >> 4 == 4 {
+                data.get(usize::from(data[0] & 0x0f) * 4..)?
+            } else {
+                data
+            };
+            (data, ICMP_ECHO_REPLY)
+        }
+        IpAddr::V6(_) => (data, ICMPV6_ECHO_REPLY),
+    };
+    if data.len() < 8 || data[0] != reply {
+        return None;
+    }
+    if identifier.is_some_and(|identifier| u16::from_be_bytes([data[4], data[5]]) != identifier) {
+        return None;
+    }
+    Some(u16::from_be_bytes([data[6], data[7]]))
+}
@@ -0,0 +1,406 @@
+use crate::base::NetRouteError;
+use crate::interface::{AdapterInfo, Interface};
+use crate::probe::check::Check;
+use crate::route::simulate;
+use prettytable::Table;
+use serde::Serialize;
+use socket2::Socket;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+pub mod check;
+pub mod icmp;
+
+/// 连通性测试参数
+#[derive(Debug, Clone, Copy)]
+pub struct PingOptions {
+    /// 发送的回显请求数量
+    pub count: u16,
+    /// 每个请求的超时时间
+    pub timeout: Duration,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        PingOptions {
+            count: 2,
+            timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// 连通性测试结果
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PingStats {
+    /// 目标地址
+    pub target: IpAddr,
+    /// 源地址
+    pub source: IpAddr,
+    /// 发送的请求数量
+    pub sent: u16,
+    /// 收到的应答数量
+    pub received: u16,
+    /// 丢包率，百分比
+    pub loss: f64,
+    /// 最小往返时间，毫秒，没有收到应答时为空
+    pub min_ms: Option<f64>,
+    /// 平均往返时间，毫秒，没有收到应答时为空
+    pub avg_ms: Option<f64>,
+    /// 最大往返时间，毫秒，没有收到应答时为空
+    pub max_ms: Option<f64>,
+}
+
+impl PingStats {
+    /// 根据每个应答的往返时间统计测试结果
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - 目标地址
+    /// * `source` - 源地址
+    /// * `sent` - 发送的请求数量
+    /// * `rtts` - 收到的应答的往返时间
+    ///
+    pub fn new(target: IpAddr, source: IpAddr, sent: u16, rtts: &[Duration]) -> PingStats {
+        let millis = rtts
+            .iter()
+            .map(|rtt| rtt.as_secs_f64() * 1000.0)
+            .collect::<Vec<f64>>();
+        let received = millis.len() as u16;
+        let loss = if sent == 0 {
+            0.0
+        } else {
+            f64::from(sent - received.min(sent)) * 100.0 / f64::from(sent)
+        };
+        PingStats {
+            target,
+            source,
+            sent,
+            received,
+            loss,
+            min_ms: millis.iter().copied().reduce(f64::min),
+            avg_ms: (!millis.is_empty()).then(|| millis.iter().sum::<f64>() / millis.len() as f64),
+            max_ms: millis.iter().copied().reduce(f64::max),
+        }
+    }
+
+    /// 是否收到了应答
+    pub fn is_reachable(&self) -> bool {
+        self.received > 0
+    }
+}
+
+/// 使用网卡的源地址测试到目标地址的连通性
+///
+/// 模拟网络环境中直接使用夹具中的连通性结果
+///
+/// # Arguments
+///
+/// * `adapter` - 网卡信息
+/// * `target` - 目标 IP 地址
+/// * `options` - 测试参数
+///
+pub fn ping_from_adapter(
+    adapter: &AdapterInfo,
+    target: &IpAddr,
+    options: &PingOptions,
+) -> Result<PingStats, NetRouteError> {
+    let source = Interface::get_source_address_for(adapter, target)?;
+    if let Some(reachable) = simulate::is_reachable(target) {
+        let rtts = if reachable {
+            let rtt = simulate::latency(adapter.index, target).unwrap_or_default();
+            vec![rtt; usize::from(options.count)]
+        } else {
+            vec![]
+        };
+        return Ok(PingStats::new(*target, source, options.count, &rtts));
+    }
+    let mut socket = icmp::IcmpSocket::open(source, Some(&adapter.name), *target)?;
+    let mut rtts = vec![];
+    for sequence in 0..options.count {
+        if let Some(rtt) = socket.echo(sequence, options.timeout)? {
+            rtts.push(rtt);
+        }
+    }
+    Ok(PingStats::new(*target, source, options.count, &rtts))
+}
+
+/// 并发测试到多个目标地址的连通性，结果与目标地址的顺序一致
+///
+/// # Arguments
+///
+/// * `adapter` - 网卡信息
+/// * `targets` - 目标 IP 地址列表
+/// * `options` - 测试参数
+///
+pub fn ping_all_from_adapter(
+    adapter: &AdapterInfo,
+    targets: &[IpAddr],
+    options: &PingOptions,
+) -> Vec<Result<PingStats, NetRouteError>> {
+    probe_concurrently(targets, |target| {
+        ping_from_adapter(adapter, target, options)
+    })
+}
+
+/// 连通性检查结果
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// 目标地址
+    pub target: IpAddr,
+    /// 源地址
+    pub source: IpAddr,
+    /// 检查方式
+    pub check: String,
+    /// 是否可达
+    pub success: bool,
+    /// 丢包率，百分比，只有 ICMP 检查可能部分丢包
+    pub loss: f64,
+    /// 握手或响应耗时，毫秒，不可达时为空
+    pub latency_ms: Option<f64>,
+    /// 检查详情或失败原因
+    pub detail: String,
+}
+
+/// 使用网卡的源地址按指定方式检查到目标地址的连通性
+///
+/// 连接、握手或请求失败时返回不可达的结果，无法确定源地址等错误时返回错误；
+/// 模拟网络环境中直接使用夹具中的连通性结果
+///
+/// # Arguments
+///
+/// * `adapter` - 网卡信息
+/// * `target` - 目标 IP 地址
+/// * `check` - 检查方式
+/// * `server_name` - TLS 检查使用的服务器名称，为空时使用目标 IP 地址
+/// * `options` - 测试参数，TCP、TLS 与 HTTP 检查只使用其中的超时时间
+///
+pub fn check_from_adapter(
+    adapter: &AdapterInfo,
+    target: &IpAddr,
+    check: &Check,
+    server_name: Option<&str>,
+    options: &PingOptions,
+) -> Result<CheckResult, NetRouteError> {
+    let source = Interface::get_source_address_for(adapter, target)?;
+    let result = |outcome: Result<(Duration, String), NetRouteError>| {
+        let (success, latency_ms, detail) = match outcome {
+            Ok((latency, detail)) => (true, Some(latency.as_secs_f64() * 1000.0), detail),
+            Err(err) => (false, None, err.message),
+        };
+        CheckResult {
+            target: *target,
+            source,
+            check: check.to_string(),
+            success,
+            loss: if success { 0.0 } else { 100.0 },
+            latency_ms,
+            detail,
+        }
+    };
+    let server_name = server_name.map_or(target.to_string(), str::to_string);
+    let device = Some(adapter.name.as_str());
+    let start = Instant::now();
+    // ICMP 检查使用 ping，其他检查在模拟网络环境中直接使用夹具中的连通性结果
+    let outcome = match (check, simulate::is_reachable(target)) {
+        (Check::Icmp, _) => {
+            let stats = ping_from_adapter(adapter, target, options)?;
+            return Ok(CheckResult {
+                target: *target,
+                source,
+                check: check.to_string(),
+                success: stats.is_reachable(),
+                loss: stats.loss,
+                latency_ms: stats.avg_ms,
+                detail: format!("接收 {}/{}", stats.received, stats.sent),
+            });
+        }
+        (_, Some(true)) => {
+            let latency = simulate::latency(adapter.index, target).unwrap_or_default();
+            Ok((latency, "模拟".to_string()))
+        }
+        (_, Some(false)) => Err(NetRouteError::new("模拟不可达")),
+        (Check::Tcp(port), None) => check::connect(
+            source,
+            device,
+            SocketAddr::new(*target, *port),
+            options.timeout,
+        )
+        .map(|_| (start.elapsed(), "TCP 连接成功".to_string())),
+        (Check::Tls(port), None) => check::connect(
+            source,
+            device,
+            SocketAddr::new(*target, *port),
+            options.timeout,
+        )
+        .and_then(|stream| check::tls_handshake(stream, &server_name))
+        .map(|(_, version)| (start.elapsed(), format!("{} 握手成功", version))),
+        (Check::Http(http), None) => check::connect(
+            source,
+            device,
+            SocketAddr::new(*target, http.port),
+            options.timeout,
+        )
+        .and_then(|stream| {
+            if http.tls {
+                let (mut stream, _) = check::tls_handshake(stream, &http.host)?;
+                check::http_request(&mut stream, http)
+            } else {
+                let mut stream = stream;
+                check::http_request(&mut stream, http)
+            }
+        })
+        .map(|status| (start.elapsed(), format!("HTTP {}", status))),
+    };
+    Ok(result(outcome))
+}
+
+/// 并发检查到多个目标地址的连通性，结果与目标地址的顺序一致
+///
+/// # Arguments
+///
+/// * `adapter` - 网卡信息
+/// * `targets` - 目标 IP 地址列表
+/// * `check` - 检查方式
+/// * `server_name` - TLS 检查使用的服务器名称，为空时使用目标 IP 地址
+/// * `options` - 测试参数
+///
+pub fn check_all_from_adapter(
+    adapter: &AdapterInfo,
+    targets: &[IpAddr],
+    check: &Check,
+    server_name: Option<&str>,
+    options: &PingOptions,
+) -> Vec<Result<CheckResult, NetRouteError>> {
+    probe_concurrently(targets, |target| {
+        check_from_adapter(adapter, target, check, server_name, options)
+    })
+}
+
+/// 对每个目标地址并发执行测试，结果与目标地址的顺序一致
+///
+/// 模拟网络环境只在当前线程中启用，启用时依次在当前线程中执行
+///
+/// # Arguments
+///
+/// * `targets` - 目标 IP 地址列表
+/// * `probe` - 对单个目标地址执行的测试
+///
+fn probe_concurrently<T: Send>(
+    targets: &[IpAddr],
+    probe: impl Fn(&IpAddr) -> Result<T, NetRouteError> + Sync,
+) -> Vec<Result<T, NetRouteError>> {
+    if simulate::is_enabled() {
+        return targets.iter().map(probe).collect();
+    }
+    let probe = &probe;
+    std::thread::scope(|scope| {
+        let handles = targets
+            .iter()
+            .map(|target| scope.spawn(move || probe(target)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(NetRouteError::new("连通性测试线程异常退出")))
+            })
+            .collect()
+    })
+}
+
+/// 将套接字绑定到网卡
+///
+/// 只在 Linux 下生效，绑定网卡需要 CAP_NET_RAW，没有权限时由源地址决定出口
+///
+/// # Arguments
+///
+/// * `socket` - 套接字
+/// * `device` - 网卡名称
+///
+fn bind_device(socket: &Socket, device: Option<&str>) {
+    #[cfg(target_os = "linux")]
+    if let Some(device) = device {
+        let _ = socket.bind_device(Some(device.as_bytes()));
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (socket, device);
+}
+
+/// 将往返时间格式化为毫秒
+fn format_rtt(rtt: Option<f64>) -> String {
+    rtt.map_or("-".to_string(), |rtt| format!("{:.2}", rtt))
+}
+
+/// 展示连通性测试结果表格
+///
+/// # Arguments
+///
+/// * `stats` - 测试结果
+///
+pub fn show_ping_table(stats: &[PingStats]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "序号",
+        "目标地址",
+        "源地址",
+        "发送",
+        "接收",
+        "丢包率",
+        "最小(ms)",
+        "平均(ms)",
+        "最大(ms)"
+    ]);
+    for (idx, stats) in stats.iter().enumerate() {
+        table.add_row(row![
+            idx,
+            stats.target,
+            stats.source,
+            stats.sent,
+            stats.received,
+            format!("{:.0}%", stats.loss),
+            format_rtt(stats.min_ms),
+            format_rtt(stats.avg_ms),
+            format_rtt(stats.max_ms)
+        ]);
+    }
+    table.printstd();
+}
+
+/// 展示连通性检查结果表格
+///
+/// # Arguments
+///
+/// * `results` - 检查结果
+///
+pub fn show_check_table(results: &[CheckResult]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "序号",
+        "目标地址",
+        "源地址",
+        "检查方式",
+        "结果",
+        "耗时(ms)",
+        "详情"
+    ]);
+    for (idx, result) in results.iter().enumerate() {
+        table.add_row(row![
+            idx,
+            result.target,
+            result.source,
+            result.check,
+            if result.success {
+                "可达"
+            } else {
+                "不可达"
+            },
+            format_rtt(result.latency_ms),
+            result.detail
+        ]);
+    }
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,213 @@
+use crate::base::NetRouteError;
+use crate::route::cidr::Cidr;
+use crate::route::plan::same_record;
+use crate::route::{show_route_table, simulate};
+use std::net::IpAddr;
+use winroute::Route;
+
+/// 路由表操作后端
+///
+/// `route` 模块中的所有命令只通过此 trait 读写路由表，
+/// 具体使用哪一种实现由 [`new_backend`] 在运行时决定。
+pub trait RouteBackend {
+    /// 获取路由表中的全部路由
+    fn get_routes(&self) -> Result<Vec<Route>, NetRouteError>;
+
+    /// 添加一条路由
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 需要添加的路由
+    fn add_route(&self, route: &Route) -> Result<(), NetRouteError>;
+
+    /// 删除一条路由
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 需要删除的路由
+    fn remove_route(&self, route: &Route) -> Result<(), NetRouteError>;
+
+    /// 路由表是否不允许目标网段与度量值都相同的多条路由同时存在
+    ///
+    /// Linux 内核以目标网段与度量值定位路由，添加同一目标网段与度量值的路由会失败；
+    /// Windows 允许不同网卡上存在目标网段与度量值都相同的路由
+    fn exclusive_metric(&self) -> bool {
+        false
+    }
+
+    /// 将已存在的路由替换为新路由
+    ///
+    /// 默认先添加新路由再删除旧路由，使目标网段在替换过程中始终有路由可用；
+    /// 新旧路由只有度量值不同时无法同时存在（如 Windows 中度量值不属于路由的标识），
+    /// 只能先删除再添加，期间目标网段短暂没有路由，添加失败时恢复旧路由
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - 已存在的路由
+    /// * `route` - 新路由
+    fn replace_route(&self, current: &Route, route: &Route) -> Result<(), NetRouteError> {
+        replace_by_add_remove(self, current, route)
+    }
+
+    /// 按目标地址查询路由
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - 目标 IP 地址
+    /// * `prefix` - 目标 IP 子网掩码
+    /// * `if_index` - 网卡索引，为空时不过滤
+    /// * `gateway` - 网关地址，为空时不过滤
+    ///
+    fn search_route_by_ip(
+        &self,
+        dest: &IpAddr,
+        prefix: &u8,
+        if_index: Option<&u32>,
+        gateway: Option<&IpAddr>,
+    ) -> Result<Vec<Route>, NetRouteError> {
+        Ok(self
+            .get_routes()?
+            .into_iter()
+            .filter(|route| {
+                // 根据提供的参数过滤路由
+                (route.destination == *dest)
+                    && (route.prefix == *prefix)
+                    && (if_index.is_none() || route.ifindex == if_index.copied())
+                    && (gateway.is_none() || Some(&route.gateway) == gateway)
+            })
+            .collect::<Vec<Route>>())
+    }
+
+    /// 按目标网段列表查询路由
+    ///
+    /// # Arguments
+    ///
+    /// * `cidr_vec` - 目标网段列表
+    /// * `if_index` - 网卡索引，为空时不过滤
+    ///
+    fn search_cidr_routes(
+        &self,
+        cidr_vec: &[Cidr],
+        if_index: &Option<u32>,
+    ) -> Result<Vec<Route>, NetRouteError> {
+        Ok(self
+            .get_routes()?
+            .into_iter()
+            .filter(|route| {
+                // 根据提供的参数过滤路由
+                cidr_vec.iter().any(|cidr| cidr.matches_route(route))
+                    && (if_index.is_none() || route.ifindex == *if_index)
+            })
+            .collect::<Vec<Route>>())
+    }
+
+    /// 创建并添加一条 IP 路由，路由已存在时返回错误
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - 目标 IP 地址
+    /// * `prefix` - 目标 IP 子网掩码
+    /// * `if_index` - 网卡索引
+    /// * `gateway` - 网关 IP 地址
+    /// * `metric` - 路由度量值
+    ///
+    fn add_ip_route(
+        &self,
+        destination: IpAddr,
+        prefix: &u8,
+        if_index: &u32,
+        gateway: IpAddr,
+        metric: &u32,
+    ) -> Result<Route, NetRouteError> {
+        // 创建路由
+        let route = Route::new(destination, *prefix)
+            .ifindex(*if_index)
+            .gateway(gateway)
+            .metric(*metric);
+        // 查询路由表
+        let search_route_vec =
+            self.search_route_by_ip(&destination, prefix, Some(if_index), None)?;
+        if !search_route_vec.is_empty() {
+            println!("路由表中已存在匹配的路由！");
+            show_route_table(&search_route_vec);
+            return Err(NetRouteError::new("路由已存在".to_string()));
+        }
+        self.add_route(&route)?;
+        Ok(route)
+    }
+}
+
+/// 通过添加与删除路由替换已存在的路由，见 [`RouteBackend::replace_route`]
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+/// * `current` - 已存在的路由
+/// * `route` - 新路由
+///
+pub fn replace_by_add_remove<B: RouteBackend + ?Sized>(
+    backend: &B,
+    current: &Route,
+    route: &Route,
+) -> Result<(), NetRouteError> {
+    if same_record(current, route) {
+        backend.remove_route(current)?;
+        if let Err(err) = backend.add_route(route) {
+            backend.add_route(current).map_err(|restore_err| {
+                NetRouteError::new(format!(
+                    "{}，且恢复原路由失败: {}",
+                    err.message, restore_err.message
+                ))
+            })?;
+            return Err(err);
+        }
+    } else {
+        backend.add_route(route)?;
+        if let Err(err) = backend.remove_route(current) {
+            backend.remove_route(route).map_err(|restore_err| {
+                NetRouteError::new(format!(
+                    "{}，且撤销新路由失败: {}",
+                    err.message, restore_err.message
+                ))
+            })?;
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// 创建路由表后端
+///
+/// 启用模拟网络环境时使用模拟路由表，否则根据当前运行平台选择
+///
+pub fn new_backend() -> Result<Box<dyn RouteBackend>, NetRouteError> {
+    if simulate::is_enabled() {
+        return Ok(Box::new(simulate::SimRoute::new()?));
+    }
+    new_platform_backend()
+}
+
+#[cfg(windows)]
+fn new_platform_backend() -> Result<Box<dyn RouteBackend>, NetRouteError> {
+    Ok(Box::new(crate::route::windows::WinRoute::new()?))
+}
+
+/// Linux 下优先使用 netlink，netlink 不可用时退回到 /proc/net 只读路由表
+#[cfg(target_os = "linux")]
+fn new_platform_backend() -> Result<Box<dyn RouteBackend>, NetRouteError> {
+    match crate::route::netlink::NetlinkRoute::new() {
+        Ok(backend) => Ok(Box::new(backend)),
+        Err(err) => {
+            eprintln!("netlink 不可用 ({})，使用 /proc/net 只读路由表", err);
+            Ok(Box::new(crate::route::procfs::ProcRoute::new()))
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn new_platform_backend() -> Result<Box<dyn RouteBackend>, NetRouteError> {
+    Err(NetRouteError::new(format!(
+        "当前平台 ({}) 暂无可用的路由表后端",
+        std::env::consts::OS
+    )))
+}
@@ -0,0 +1,253 @@
+use crate::base::NetRouteError;
+use crate::route::family::host_prefix;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use winroute::Route;
+
+/// 将 IP 地址的主机位清零，得到网络地址
+///
+/// # Arguments
+///
+/// * `ip` - IP 地址
+/// * `prefix` - 子网掩码长度，超过地址族长度时按最大长度处理
+///
+pub fn network_address(ip: &IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX
+                .checked_shl(32 - prefix.min(32) as u32)
+                .unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(*v4) & mask))
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX
+                .checked_shl(128 - prefix.min(128) as u32)
+                .unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(*v6) & mask))
+        }
+    }
+}
+
+/// 判断 IP 地址是否在网段内，地址族不同时返回 false
+///
+/// # Arguments
+///
+/// * `network` - 网段地址
+/// * `prefix` - 网段子网掩码长度
+/// * `ip` - IP 地址
+///
+pub fn contains(network: &IpAddr, prefix: u8, ip: &IpAddr) -> bool {
+    if network.is_ipv4() != ip.is_ipv4() {
+        return false;
+    }
+    network_address(network, prefix) == network_address(ip, prefix)
+}
+
+/// 目标地址包含主机位时的处理方式
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HostBits {
+    /// 拒绝包含主机位的目标地址
+    #[default]
+    Reject,
+    /// 将主机位清零后使用对应的网络地址
+    Normalize,
+}
+
+/// CIDR 网段，如 `10.0.0.0/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    /// 网络地址
+    pub address: IpAddr,
+    /// 子网掩码长度
+    pub prefix: u8,
+}
+
+impl Cidr {
+    /// 单个主机地址的网段，IPv4 为 /32，IPv6 为 /128
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - IP 地址
+    ///
+    pub fn host(ip: IpAddr) -> Cidr {
+        Cidr {
+            address: ip,
+            prefix: host_prefix(&ip),
+        }
+    }
+
+    /// 解析 `10.0.0.0/8` 或不带掩码的 IP 地址
+    ///
+    /// 检查掩码长度是否超出地址族范围，并按 `host_bits` 处理主机位
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - CIDR 或 IP 地址
+    /// * `prefix` - 单独指定的子网掩码，与 CIDR 中的掩码不一致时返回错误
+    /// * `host_bits` - 包含主机位时的处理方式
+    ///
+    pub fn parse(
+        input: &str,
+        prefix: Option<u8>,
+        host_bits: &HostBits,
+    ) -> Result<Cidr, NetRouteError> {
+        Cidr::parse_with_notice(input, prefix, host_bits).map(|(cidr, _)| cidr)
+    }
+
+    /// 解析 `10.0.0.0/8` 或不带掩码的 IP 地址，同 [`Cidr::parse`]，
+    /// 并在主机位被修正时返回修正说明，由调用方决定是否输出
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - CIDR 或 IP 地址
+    /// * `prefix` - 单独指定的子网掩码，与 CIDR 中的掩码不一致时返回错误
+    /// * `host_bits` - 包含主机位时的处理方式
+    ///
+    pub fn parse_with_notice(
+        input: &str,
+        prefix: Option<u8>,
+        host_bits: &HostBits,
+    ) -> Result<(Cidr, Option<String>), NetRouteError> {
+        let input = input.trim();
+        let (ip_str, cidr_prefix) = match input.split_once('/') {
+            Some((ip_str, prefix_str)) => {
+                let cidr_prefix = prefix_str
+                    .parse::<u8>()
+                    .map_err(|_| NetRouteError::new(format!("Invalid prefix length: {}", input)))?;
+                (ip_str, Some(cidr_prefix))
+            }
+            None => (input, None),
+        };
+        let ip: IpAddr = ip_str.parse().map_err(|_| {
+            NetRouteError::new(format!("Invalid destination IP address: {}", input))
+        })?;
+        let prefix = match (cidr_prefix, prefix) {
+            (Some(cidr_prefix), Some(prefix)) if cidr_prefix != prefix => {
+                return Err(NetRouteError::new(format!(
+                    "{} 的子网掩码与 --prefix {} 不一致",
+                    input, prefix
+                )));
+            }
+            (Some(prefix), _) | (None, Some(prefix)) => prefix,
+            (None, None) => host_prefix(&ip),
+        };
+        if prefix > host_prefix(&ip) {
+            return Err(NetRouteError::new(format!(
+                "子网掩码 /{} 超出 {} 地址的范围 (0-{})",
+                prefix,
+                if ip.is_ipv4() { "IPv4" } else { "IPv6" },
+                host_prefix(&ip)
+            )));
+        }
+        let network = network_address(&ip, prefix);
+        let mut notice = None;
+        if network != ip {
+            match host_bits {
+                HostBits::Reject => {
+                    return Err(NetRouteError::new(format!(
+                        "{}/{} 包含主机位，网络地址应为 {}/{}，可使用 --host-bits normalize 自动修正",
+                        ip, prefix, network, prefix
+                    )));
+                }
+                HostBits::Normalize => {
+                    notice = Some(format!(
+                        "已将 {}/{} 修正为 {}/{}",
+                        ip, prefix, network, prefix
+                    ));
+                }
+            }
+        }
+        Ok((
+            Cidr {
+                address: network,
+                prefix,
+            },
+            notice,
+        ))
+    }
+
+    /// 判断路由的目标网段是否与当前网段相同
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    ///
+    pub fn matches_route(&self, route: &Route) -> bool {
+        route.destination == self.address && route.prefix == self.prefix
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// 将网段列表聚合为覆盖完全相同地址范围的最少 CIDR 集合
+///
+/// 移除被其他网段包含的网段，并不断合并相邻的同级网段，
+/// IPv4 与 IPv6 网段分别聚合
+///
+/// # Arguments
+///
+/// * `cidrs` - 网段列表
+///
+pub fn aggregate(cidrs: Vec<Cidr>) -> Vec<Cidr> {
+    let mut sorted = cidrs
+        .into_iter()
+        .map(|cidr| Cidr {
+            address: network_address(&cidr.address, cidr.prefix),
+            prefix: cidr.prefix,
+        })
+        .collect::<Vec<Cidr>>();
+    // 按地址升序排列，地址相同时范围大的在前
+    sorted.sort_by_key(|cidr| (cidr.address, cidr.prefix));
+    let mut merged: Vec<Cidr> = vec![];
+    for cidr in sorted {
+        // 已被前一个网段包含
+        if let Some(last) = merged.last()
+            && last.prefix <= cidr.prefix
+            && contains(&last.address, last.prefix, &cidr.address)
+        {
+            continue;
+        }
+        merged.push(cidr);
+        // 与前一个网段互为同级网段时合并为上一级网段
+        while merged.len() >= 2 {
+            let right = merged[merged.len() - 1];
+            let left = merged[merged.len() - 2];
+            match sibling_parent(&left, &right) {
+                Some(parent) => {
+                    merged.truncate(merged.len() - 2);
+                    merged.push(parent);
+                }
+                None => break,
+            }
+        }
+    }
+    merged
+}
+
+/// 两个网段互为同级网段时返回它们的上一级网段
+fn sibling_parent(left: &Cidr, right: &Cidr) -> Option<Cidr> {
+    if left.prefix != right.prefix || left.prefix == 0 || left.address == right.address {
+        return None;
+    }
+    if left.address.is_ipv4() != right.address.is_ipv4() {
+        return None;
+    }
+    let parent = Cidr {
+        address: network_address(&left.address, left.prefix - 1),
+        prefix: left.prefix - 1,
+    };
+    if parent.address == left.address
+        && network_address(&right.address, parent.prefix) == parent.address
+    {
+        Some(parent)
+    } else {
+        None
+    }
+}
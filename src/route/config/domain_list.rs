@@ -0,0 +1,301 @@
+use crate::base::{NetRouteError, files};
+use crate::route::config::RouteConfig;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// 外部域名列表的格式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainListFormat {
+    /// 纯文本，每行一个域名，`#` 之后为注释
+    #[default]
+    Text,
+    /// dnsmasq 配置中的 `server=/域名/` 与 `ipset=/域名/` 行
+    Dnsmasq,
+    /// hosts 文件
+    Hosts,
+    /// Clash rule-provider YAML，支持 `DOMAIN`、`DOMAIN-SUFFIX` 与 `IP-CIDR` 规则
+    Clash,
+    /// base64 编码的 gfwlist
+    Gfwlist,
+}
+
+impl fmt::Display for DomainListFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainListFormat::Text => write!(f, "text"),
+            DomainListFormat::Dnsmasq => write!(f, "dnsmasq"),
+            DomainListFormat::Hosts => write!(f, "hosts"),
+            DomainListFormat::Clash => write!(f, "clash"),
+            DomainListFormat::Gfwlist => write!(f, "gfwlist"),
+        }
+    }
+}
+
+/// 路由条目引用的外部域名列表
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DomainList {
+    /// 列表文件路径，相对路径相对于配置文件所在目录
+    pub path: String,
+    /// 列表格式，默认为纯文本
+    #[serde(default)]
+    pub format: DomainListFormat,
+}
+
+/// 从域名列表中读取的域名与地址，均已去重
+#[derive(Debug, Default, PartialEq)]
+pub struct ListEntries {
+    pub domains: Vec<String>,
+    pub ips: Vec<String>,
+}
+
+impl ListEntries {
+    /// 添加域名，无效或重复的域名被忽略
+    fn push_domain(&mut self, domain: &str) {
+        let Some(domain) = normalize_domain(domain) else {
+            return;
+        };
+        if !self
+            .domains
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&domain))
+        {
+            self.domains.push(domain);
+        }
+    }
+
+    /// 添加 IP 地址或 CIDR，重复的地址被忽略
+    fn push_ip(&mut self, ip: &str) {
+        let ip = ip.trim();
+        if !ip.is_empty() && !self.ips.iter().any(|existing| existing == ip) {
+            self.ips.push(ip.to_string());
+        }
+    }
+}
+
+/// Clash rule-provider 文件
+#[derive(Deserialize)]
+struct ClashProvider {
+    #[serde(default)]
+    payload: Vec<String>,
+}
+
+impl DomainListFormat {
+    /// 解析域名列表内容，无法识别的行被忽略
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - 列表文件内容
+    ///
+    pub fn parse(&self, content: &str) -> Result<ListEntries, NetRouteError> {
+        let mut entries = ListEntries::default();
+        match self {
+            DomainListFormat::Text => {
+                for line in content.lines().map(strip_comment) {
+                    entries.push_domain(line);
+                }
+            }
+            DomainListFormat::Dnsmasq => {
+                for line in content.lines().map(strip_comment) {
+                    // server=/a.com/b.com/114.114.114.114 与 ipset=/a.com/setname，首尾之间为域名
+                    let Some(rest) = ["server=", "ipset="]
+                        .iter()
+                        .find_map(|prefix| line.strip_prefix(prefix))
+                    else {
+                        continue;
+                    };
+                    let Some(rest) = rest.strip_prefix('/') else {
+                        continue;
+                    };
+                    let Some((domains, _)) = rest.rsplit_once('/') else {
+                        continue;
+                    };
+                    for domain in domains.split('/') {
+                        entries.push_domain(domain);
+                    }
+                }
+            }
+            DomainListFormat::Hosts => {
+                for line in content.lines().map(strip_comment) {
+                    let mut fields = line.split_whitespace();
+                    if fields
+                        .next()
+                        .and_then(|ip| ip.parse::<IpAddr>().ok())
+                        .is_none()
+                    {
+                        continue;
+                    }
+                    // 跳过 localhost、broadcasthost 等本地主机名
+                    for name in fields.filter(|name| {
+                        name.contains('.') && name.split('.').next() != Some("localhost")
+                    }) {
+                        entries.push_domain(name);
+                    }
+                }
+            }
+            DomainListFormat::Clash => {
+                let provider: ClashProvider = serde_yaml::from_str(content)
+                    .map_err(|e| NetRouteError::new(format!("域名列表解析失败: {}", e)))?;
+                for rule in provider.payload.iter() {
+                    parse_clash_rule(rule.trim(), &mut entries);
+                }
+            }
+            DomainListFormat::Gfwlist => {
+                let encoded = content
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>();
+                let decoded = STANDARD
+                    .decode(encoded)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| {
+                        NetRouteError::new("gfwlist 不是有效的 base64 编码内容".to_string())
+                    })?;
+                for line in decoded.lines() {
+                    parse_gfwlist_rule(line.trim(), &mut entries);
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+impl DomainList {
+    /// 读取并解析域名列表
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - 相对路径的基准目录
+    ///
+    pub fn load(&self, base: &Path) -> Result<ListEntries, NetRouteError> {
+        let path = base.join(&self.path).to_string_lossy().to_string();
+        files::read_file_content(&path)
+            .and_then(|content| self.format.parse(&content))
+            .map_err(|e| {
+                NetRouteError::new(format!(
+                    "读取 {} 格式的域名列表 {} 失败: {}",
+                    self.format, path, e.message
+                ))
+            })
+    }
+}
+
+/// 读取路由条目引用的域名列表，将其中的域名与地址追加到条目的 `domains` 与 `ips` 中
+///
+/// # Arguments
+///
+/// * `route_config` - 路由条目
+/// * `base` - 相对路径的基准目录
+///
+pub fn expand_domain_lists(
+    route_config: &mut RouteConfig,
+    base: &Path,
+) -> Result<(), NetRouteError> {
+    for domain_list in std::mem::take(&mut route_config.domain_lists) {
+        let entries = domain_list
+            .load(base)
+            .map_err(|e| NetRouteError::new(format!("{}: {}", route_config.origin, e.message)))?;
+        let domains = route_config
+            .domains
+            .iter()
+            .map(|domain| domain.to_ascii_lowercase())
+            .collect::<HashSet<String>>();
+        route_config.domains.extend(
+            entries
+                .domains
+                .into_iter()
+                .filter(|domain| !domains.contains(&domain.to_ascii_lowercase())),
+        );
+        let ips = route_config
+            .ips
+            .iter()
+            .cloned()
+            .collect::<HashSet<String>>();
+        route_config
+            .ips
+            .extend(entries.ips.into_iter().filter(|ip| !ips.contains(ip)));
+    }
+    Ok(())
+}
+
+/// 去除行中 `#` 之后的注释与首尾空白
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+/// 规范化列表中的域名，去除通配前缀与末尾的 `.`，不是有效域名时返回空
+///
+/// # Arguments
+///
+/// * `domain` - 列表中的域名
+///
+fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim();
+    let domain = ["+.", "*.", "."]
+        .iter()
+        .find_map(|prefix| domain.strip_prefix(prefix))
+        .unwrap_or(domain)
+        .trim_end_matches('.');
+    let valid = !domain.is_empty()
+        && domain.parse::<IpAddr>().is_err()
+        && domain
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_'));
+    valid.then(|| domain.to_string())
+}
+
+/// 解析 Clash 规则，支持 classical 格式的 `DOMAIN`、`DOMAIN-SUFFIX`、`IP-CIDR`、`IP-CIDR6`
+/// 规则，以及 domain 与 ipcidr 格式中的域名与网段
+///
+/// # Arguments
+///
+/// * `rule` - 规则
+/// * `entries` - 读取结果
+///
+fn parse_clash_rule(rule: &str, entries: &mut ListEntries) {
+    if let Some((kind, rest)) = rule.split_once(',') {
+        let value = rest.split(',').next().unwrap_or_default();
+        match kind.trim().to_ascii_uppercase().as_str() {
+            "DOMAIN" | "DOMAIN-SUFFIX" => entries.push_domain(value),
+            "IP-CIDR" | "IP-CIDR6" => entries.push_ip(value),
+            _ => {}
+        }
+    } else if rule.contains('/') || rule.parse::<IpAddr>().is_ok() {
+        entries.push_ip(rule);
+    } else {
+        entries.push_domain(rule);
+    }
+}
+
+/// 解析 gfwlist 规则，跳过注释、白名单 `@@` 与正则规则，只保留规则中的主机名
+///
+/// # Arguments
+///
+/// * `rule` - 规则
+/// * `entries` - 读取结果
+///
+fn parse_gfwlist_rule(rule: &str, entries: &mut ListEntries) {
+    if rule.is_empty() || rule.starts_with(['!', '[', '/']) || rule.starts_with("@@") {
+        return;
+    }
+    let rule = rule
+        .strip_prefix("||")
+        .or_else(|| rule.strip_prefix('|'))
+        .unwrap_or(rule);
+    let rule = ["http://", "https://"]
+        .iter()
+        .find_map(|scheme| rule.strip_prefix(scheme))
+        .unwrap_or(rule);
+    let host = rule.split(['/', ':', '^', '?']).next().unwrap_or_default();
+    if host.parse::<IpAddr>().is_ok() {
+        entries.push_ip(host);
+    } else if host.contains('.') {
+        entries.push_domain(host);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::base::NetRouteError;
+use crate::route::config::RouteConfigData;
+use clap::ValueEnum;
+use json_comments::StripComments;
+use std::fmt;
+use std::path::Path;
+
+/// 配置文件格式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    /// JSON
+    #[default]
+    Json,
+    /// 支持 `//`、`/* */` 与 `#` 注释的 JSON
+    Jsonc,
+    /// TOML
+    Toml,
+    /// YAML
+    Yaml,
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => write!(f, "json"),
+            ConfigFormat::Jsonc => write!(f, "jsonc"),
+            ConfigFormat::Toml => write!(f, "toml"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// 根据文件扩展名判断配置文件格式，无法识别时为 JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 配置文件路径
+    ///
+    pub fn from_path(path: &str) -> ConfigFormat {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("jsonc") => ConfigFormat::Jsonc,
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// 解析配置文件内容，解析失败时返回包含行号与列号的错误
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - 配置文件内容
+    ///
+    pub fn parse(&self, content: &str) -> Result<RouteConfigData, NetRouteError> {
+        let mut config: RouteConfigData = match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(json_error),
+            // 注释被替换为空格，行号与列号保持不变
+            ConfigFormat::Jsonc => {
+                serde_json::from_reader(StripComments::new(content.as_bytes())).map_err(json_error)
+            }
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| match e.span() {
+                Some(span) => {
+                    let (line, column) = line_column(content, span.start);
+                    location_error(line, column, e.message())
+                }
+                None => NetRouteError::new(format!("配置文件解析失败: {}", e.message())),
+            }),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| match e.location() {
+                Some(location) => location_error(
+                    location.line(),
+                    location.column(),
+                    &strip_location(&e.to_string(), location.line(), location.column()),
+                ),
+                None => NetRouteError::new(format!("配置文件解析失败: {}", e)),
+            }),
+        }?;
+        config.set_origin(None);
+        Ok(config)
+    }
+
+    /// 将配置序列化为该格式，转换后原配置文件中的注释不会保留
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - 配置
+    ///
+    pub fn serialize(&self, config: &RouteConfigData) -> Result<String, NetRouteError> {
+        match self {
+            ConfigFormat::Json | ConfigFormat::Jsonc => {
+                serde_json::to_string_pretty(config).map_err(|e| e.to_string())
+            }
+            ConfigFormat::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::to_string(config).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| NetRouteError::new(format!("配置转换为 {} 格式失败: {}", self, e)))
+    }
+}
+
+/// 将 JSON 解析错误转换为包含行号与列号的错误
+fn json_error(e: serde_json::Error) -> NetRouteError {
+    location_error(
+        e.line(),
+        e.column(),
+        &strip_location(&e.to_string(), e.line(), e.column()),
+    )
+}
+
+/// 创建包含行号与列号的解析错误
+///
+/// # Arguments
+///
+/// * `line` - 行号，从 1 开始
+/// * `column` - 列号，从 1 开始
+/// * `message` - 错误信息
+///
+fn location_error(line: usize, column: usize, message: &str) -> NetRouteError {
+    NetRouteError::new(format!(
+        "配置文件解析失败: 第 {} 行第 {} 列: {}",
+        line, column, message
+    ))
+}
+
+/// 去除错误信息末尾的英文位置描述
+///
+/// # Arguments
+///
+/// * `message` - 错误信息
+/// * `line` - 行号
+/// * `column` - 列号
+///
+fn strip_location(message: &str, line: usize, column: usize) -> String {
+    let suffix = format!(" at line {} column {}", line, column);
+    message.strip_suffix(&suffix).unwrap_or(message).to_string()
+}
+
+/// 计算字节偏移量所在的行号与列号，均从 1 开始
+///
+/// # Arguments
+///
+/// * `content` - 文件内容
+/// * `offset` - 字节偏移量
+///
+pub fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = content.get(..offset).unwrap_or(content);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |last| last.chars().count())
+        + 1;
+    (line, column)
+}
@@ -0,0 +1,275 @@
+use crate::base::NetRouteError;
+use crate::interface::selector::glob_match;
+use crate::route::config::domain_list::expand_domain_lists;
+use crate::route::config::format::ConfigFormat;
+use crate::route::config::validate::ConfigIssue;
+use crate::route::config::{RouteConfig, RouteConfigData, RouteDefaults, RouteOrigin};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 配置目录中作为配置片段读取的文件扩展名
+const FRAGMENT_EXTENSIONS: [&str; 5] = ["json", "jsonc", "toml", "yaml", "yml"];
+
+/// 读取配置文件或配置目录，并合并 `include` 包含的配置文件
+///
+/// 目录中的配置片段按文件名顺序读取；`include` 包含的文件在当前文件的路由条目之后按声明顺序读取，
+/// 通配符匹配到的多个文件按路径顺序读取。每个文件的 `defaults` 只作用于该文件及其包含的文件。
+/// 不同文件中的同一域名或地址配置一致时只保留先读取的条目，不一致时返回错误
+///
+/// # Arguments
+///
+/// * `path` - 配置文件或配置目录路径
+/// * `format` - 配置文件格式，只作用于直接指定的配置文件，为空时根据扩展名判断
+///
+pub fn load_config(
+    path: &str,
+    format: &Option<ConfigFormat>,
+) -> Result<RouteConfigData, NetRouteError> {
+    let (config, conflicts) = load_config_with_conflicts(path, format)?;
+    if !conflicts.is_empty() {
+        let conflicts = conflicts
+            .iter()
+            .map(|conflict| conflict.to_string())
+            .collect::<Vec<_>>();
+        return Err(NetRouteError::new(format!(
+            "配置文件之间存在冲突:\n{}",
+            conflicts.join("\n")
+        )));
+    }
+    Ok(config)
+}
+
+/// 读取配置文件或配置目录，与 [`load_config`] 相同，但不同文件之间的冲突不作为错误返回
+///
+/// 冲突的域名或地址从合并后的配置中移除，冲突与合并后的配置一起返回，供 `config validate` 与其他问题一起报告
+///
+/// # Arguments
+///
+/// * `path` - 配置文件或配置目录路径
+/// * `format` - 配置文件格式，只作用于直接指定的配置文件，为空时根据扩展名判断
+///
+pub fn load_config_with_conflicts(
+    path: &str,
+    format: &Option<ConfigFormat>,
+) -> Result<(RouteConfigData, Vec<ConfigIssue>), NetRouteError> {
+    let mut loader = Loader::default();
+    let root = Path::new(path);
+    let mut routes = vec![];
+    if root.is_dir() {
+        for fragment in list_fragments(root)? {
+            routes.extend(loader.load(&fragment, &None)?);
+        }
+    } else {
+        routes.extend(loader.load(root, format)?);
+    }
+    // 只有一个配置文件时不需要标记条目所在的文件
+    if loader.loaded.len() == 1 {
+        for route_config in routes.iter_mut() {
+            route_config.origin.file = None;
+        }
+    }
+    let (routes, conflicts) = merge_routes(routes);
+    let config = RouteConfigData {
+        include: vec![],
+        defaults: RouteDefaults::default(),
+        routes,
+    };
+    Ok((config, conflicts))
+}
+
+/// 递归读取配置文件
+#[derive(Default)]
+struct Loader {
+    /// 已读取的配置文件，同一文件被多次包含时只读取一次
+    loaded: Vec<PathBuf>,
+    /// 正在读取的配置文件，用于检测循环包含
+    stack: Vec<PathBuf>,
+}
+
+impl Loader {
+    /// 读取配置文件及其包含的配置文件，返回使用该文件 `defaults` 补全后的路由条目
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 配置文件路径
+    /// * `format` - 配置文件格式，为空时根据扩展名判断
+    ///
+    fn load(
+        &mut self,
+        path: &Path,
+        format: &Option<ConfigFormat>,
+    ) -> Result<Vec<RouteConfig>, NetRouteError> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.contains(&canonical) {
+            let chain = self
+                .stack
+                .iter()
+                .chain([&canonical])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>();
+            return Err(NetRouteError::new(format!(
+                "配置文件循环包含: {}",
+                chain.join(" -> ")
+            )));
+        }
+        if self.loaded.contains(&canonical) {
+            return Ok(vec![]);
+        }
+        self.loaded.push(canonical.clone());
+        let file = path.display().to_string();
+        let mut config = super::load_config_file(&file, format)
+            .map_err(|e| NetRouteError::new(format!("{}: {}", file, e.message)))?;
+        config.set_origin(Some(&file));
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut routes = std::mem::take(&mut config.routes);
+        for route_config in routes.iter_mut() {
+            expand_domain_lists(route_config, base)?;
+        }
+        self.stack.push(canonical);
+        for pattern in config.include.iter() {
+            for included in expand_include(base, pattern)? {
+                routes.extend(self.load(&included, &None)?);
+            }
+        }
+        self.stack.pop();
+        Ok(routes
+            .into_iter()
+            .map(|route_config| route_config.inherit(&config.defaults))
+            .collect())
+    }
+}
+
+/// 列出配置目录中的配置片段，按文件名排序
+///
+/// # Arguments
+///
+/// * `dir` - 配置目录
+///
+fn list_fragments(dir: &Path) -> Result<Vec<PathBuf>, NetRouteError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| NetRouteError::new(format!("读取配置目录 {} 失败: {}", dir.display(), e)))?;
+    let mut fragments = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|extension| {
+                    FRAGMENT_EXTENSIONS
+                        .contains(&extension.to_string_lossy().to_ascii_lowercase().as_str())
+                })
+        })
+        .collect::<Vec<PathBuf>>();
+    fragments.sort();
+    Ok(fragments)
+}
+
+/// 展开 `include` 中的路径，路径中的每一级都可以使用 `*` 与 `?` 通配符
+///
+/// 不含通配符的路径原样返回，文件不存在时在读取时报错；通配符没有匹配到文件时返回空
+///
+/// # Arguments
+///
+/// * `base` - 相对路径的基准目录
+/// * `pattern` - 包含的路径
+///
+fn expand_include(base: &Path, pattern: &str) -> Result<Vec<PathBuf>, NetRouteError> {
+    let full = base.join(pattern);
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![full]);
+    }
+    let mut matched = vec![PathBuf::new()];
+    for component in full.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if !name.contains(['*', '?']) {
+            for path in matched.iter_mut() {
+                path.push(component);
+            }
+            continue;
+        }
+        let mut next = vec![];
+        for dir in matched {
+            let read_dir = if dir.as_os_str().is_empty() {
+                fs::read_dir(".")
+            } else {
+                fs::read_dir(&dir)
+            };
+            let Ok(entries) = read_dir else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if glob_match(&name, &file_name) {
+                    next.push(dir.join(file_name));
+                }
+            }
+        }
+        matched = next;
+    }
+    matched.retain(|path| path.is_file());
+    matched.sort();
+    Ok(matched)
+}
+
+/// 先读取的域名或地址所在的条目与配置
+struct FirstSeen {
+    origin: RouteOrigin,
+    settings: RouteConfig,
+}
+
+/// 合并来自不同配置文件的路由条目
+///
+/// 不同文件中的同一域名或地址配置一致时移除后出现的，不一致时同样移除并记录为冲突，
+/// 返回合并后的条目与所有冲突；同一文件中的重复由 `config validate` 报告
+///
+/// # Arguments
+///
+/// * `routes` - 按读取顺序排列的路由条目
+///
+fn merge_routes(routes: Vec<RouteConfig>) -> (Vec<RouteConfig>, Vec<ConfigIssue>) {
+    let mut seen = HashMap::new();
+    let mut conflicts = vec![];
+    let mut merged = vec![];
+    for mut route_config in routes {
+        if !route_config.is_enabled() {
+            merged.push(route_config);
+            continue;
+        }
+        // 除目标外的配置，用于判断不同文件中的同一目标是否一致
+        let settings = RouteConfig {
+            domains: vec![],
+            ips: vec![],
+            enabled: None,
+            origin: RouteOrigin::default(),
+            ..route_config.clone()
+        };
+        let origin = route_config.origin.clone();
+        let mut keep = |key: String, target: &str| match seen.get(&key) {
+            None => {
+                seen.insert(
+                    key,
+                    FirstSeen {
+                        origin: origin.clone(),
+                        settings: settings.clone(),
+                    },
+                );
+                true
+            }
+            Some(first) if first.origin.file == origin.file => true,
+            Some(first) if first.settings == settings => false,
+            Some(first) => {
+                conflicts.push(ConfigIssue {
+                    origin: origin.clone(),
+                    message: format!("{} 同时配置在{}中，且配置不一致", target, first.origin),
+                });
+                false
+            }
+        };
+        route_config
+            .domains
+            .retain(|domain| keep(format!("domain:{}", domain.to_ascii_lowercase()), domain));
+        route_config.ips.retain(|ip| keep(format!("ip:{}", ip), ip));
+        merged.push(route_config);
+    }
+    (merged, conflicts)
+}
@@ -0,0 +1,216 @@
+use crate::base::NetRouteError;
+use crate::base::files;
+use crate::interface::selector::InterfaceSelector;
+use crate::probe::check::Check;
+use crate::route::config::domain_list::DomainList;
+use crate::route::config::format::ConfigFormat;
+use crate::route::family::Family;
+use crate::route::select::{IfIndex, SelectScope};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+
+pub mod domain_list;
+pub mod format;
+pub mod include;
+pub mod validate;
+
+/// 路由条目所在的配置文件与序号
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteOrigin {
+    /// 配置文件路径，配置只由一个文件组成时为空
+    pub file: Option<String>,
+    /// 条目在配置文件中的序号，从 1 开始
+    pub entry: usize,
+}
+
+impl fmt::Display for RouteOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{} ", file)?;
+        }
+        write!(f, "第 {} 个路由条目", self.entry)
+    }
+}
+
+/// 配置文件中的一组路由
+///
+/// 除 `domains` 与 `ips` 外的字段未设置时继承 `defaults` 中的值
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RouteConfig {
+    /// 网卡索引或网卡选择器，如 `"WLAN"`、`"type:Ieee80211"`，`auto` 表示从候选网卡中按连通性测试结果自动选择
+    #[serde(default, alias = "interface", skip_serializing_if = "Option::is_none")]
+    pub ifindex: Option<IfIndex>,
+    /// 自动选择网卡时的候选网卡，支持网卡选择器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<InterfaceSelector>>,
+    /// 自动选择网卡的粒度，默认为每个 IP 地址分别选择
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub select_per: Option<SelectScope>,
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// 引用的外部域名列表，读取配置文件时追加到 `domains` 与 `ips` 中
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domain_lists: Vec<DomainList>,
+    #[serde(default)]
+    pub ips: Vec<String>,
+    /// 域名解析结果需要添加路由的地址族，默认仅 IPv4
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<Family>,
+    /// 添加路由前的连通性检查方式，如 `tcp:443`，为空时不检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<Check>,
+    /// 路由度量值，默认为 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+    /// 网关地址，默认使用网卡对应地址族的网关
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<IpAddr>,
+    /// 子网掩码长度，域名解析结果与未指定掩码的 IP 地址使用该掩码所在的网段，默认为单个主机地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<u8>,
+    /// 是否添加不经过网关的直连路由，默认为 false
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_link: Option<bool>,
+    /// 是否启用，停用的条目不添加路由，之前由该条目添加的路由会在应用配置时删除，默认为 true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// 条目所在的配置文件与序号，读取配置文件时设置
+    #[serde(skip)]
+    pub origin: RouteOrigin,
+}
+
+/// 所有路由条目继承的默认值
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RouteDefaults {
+    #[serde(default, alias = "interface", skip_serializing_if = "Option::is_none")]
+    pub ifindex: Option<IfIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<InterfaceSelector>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub select_per: Option<SelectScope>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<Family>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<Check>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_link: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+impl RouteConfig {
+    /// 使用默认值补全未设置的字段
+    ///
+    /// # Arguments
+    ///
+    /// * `defaults` - 默认值
+    ///
+    pub fn inherit(self, defaults: &RouteDefaults) -> RouteConfig {
+        RouteConfig {
+            ifindex: self.ifindex.or_else(|| defaults.ifindex.clone()),
+            candidates: self.candidates.or_else(|| defaults.candidates.clone()),
+            select_per: self.select_per.or(defaults.select_per),
+            domains: self.domains,
+            domain_lists: self.domain_lists,
+            ips: self.ips,
+            family: self.family.or(defaults.family),
+            check: self.check.or_else(|| defaults.check.clone()),
+            metric: self.metric.or(defaults.metric),
+            gateway: self.gateway.or(defaults.gateway),
+            prefix: self.prefix.or(defaults.prefix),
+            on_link: self.on_link.or(defaults.on_link),
+            enabled: self.enabled.or(defaults.enabled),
+            origin: self.origin,
+        }
+    }
+
+    /// 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+impl RouteDefaults {
+    /// 是否没有设置任何默认值
+    pub fn is_empty(&self) -> bool {
+        *self == RouteDefaults::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RouteConfigData {
+    /// 包含的其他配置文件，相对路径相对于当前配置文件所在目录，支持通配符
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// 所有路由条目继承的默认值
+    #[serde(default, skip_serializing_if = "RouteDefaults::is_empty")]
+    pub defaults: RouteDefaults,
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+}
+
+impl RouteConfigData {
+    /// 记录每个路由条目所在的配置文件与序号
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - 配置文件路径
+    ///
+    pub fn set_origin(&mut self, file: Option<&str>) {
+        for (idx, route_config) in self.routes.iter_mut().enumerate() {
+            route_config.origin = RouteOrigin {
+                file: file.map(str::to_string),
+                entry: idx + 1,
+            };
+        }
+    }
+
+    /// 使用默认值补全后的启用的路由条目，条目缺少网卡或网关与直连同时设置时返回错误
+    pub fn resolved_routes(&self) -> Result<Vec<RouteConfig>, NetRouteError> {
+        let mut routes = vec![];
+        for route_config in self.routes.iter() {
+            let route_config = route_config.clone().inherit(&self.defaults);
+            if !route_config.is_enabled() {
+                continue;
+            }
+            if route_config.ifindex.is_none() {
+                return Err(NetRouteError::new(format!(
+                    "{}缺少 ifindex，且 defaults 中没有设置",
+                    route_config.origin
+                )));
+            }
+            if route_config.gateway.is_some() && route_config.on_link == Some(true) {
+                return Err(NetRouteError::new(format!(
+                    "{}不能同时设置 gateway 与 on_link",
+                    route_config.origin
+                )));
+            }
+            routes.push(route_config);
+        }
+        Ok(routes)
+    }
+}
+
+/// 读取并解析单个配置文件，不处理 `include`
+///
+/// # Arguments
+///
+/// * `path` - 配置文件路径
+/// * `format` - 配置文件格式，为空时根据扩展名判断
+///
+pub fn load_config_file(
+    path: &str,
+    format: &Option<ConfigFormat>,
+) -> Result<RouteConfigData, NetRouteError> {
+    let content = files::read_file_content(path)?;
+    format
+        .unwrap_or_else(|| ConfigFormat::from_path(path))
+        .parse(&content)
+}
@@ -0,0 +1,268 @@
+use crate::interface::{AdapterInfo, Interface};
+use crate::route::cidr::{Cidr, HostBits};
+use crate::route::config::{RouteConfig, RouteConfigData, RouteOrigin};
+use crate::route::family::Family;
+use crate::route::parse_domain;
+use crate::route::select::IfIndex;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// 配置文件中的一个问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// 路由条目所在的配置文件与序号
+    pub origin: RouteOrigin,
+    /// 问题说明
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.origin, self.message)
+    }
+}
+
+/// 已出现过的目标所在的条目与网卡
+struct Seen {
+    origin: RouteOrigin,
+    adapter: Option<AdapterInfo>,
+}
+
+/// 检查配置中的所有启用的路由条目，返回发现的全部问题
+///
+/// # Arguments
+///
+/// * `config` - 配置
+/// * `adapters` - 系统网卡列表
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `resolve` - 是否解析域名，检查域名能否解析以及解析结果是否指向不同网卡
+///
+pub fn validate_config(
+    config: &RouteConfigData,
+    adapters: &[AdapterInfo],
+    host_bits: &HostBits,
+    resolve: bool,
+) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    let mut domains = HashMap::new();
+    let mut destinations = HashMap::new();
+    for route_config in config.routes.iter() {
+        let route_config = route_config.clone().inherit(&config.defaults);
+        let origin = &route_config.origin;
+        if !route_config.is_enabled() {
+            continue;
+        }
+        let mut messages = vec![];
+        let adapter = validate_adapter(&route_config, adapters, &mut messages);
+        let is_auto = route_config.ifindex == Some(IfIndex::Auto);
+        let on_link = route_config.on_link == Some(true);
+        if route_config.gateway.is_some() && on_link {
+            messages.push("不能同时设置 gateway 与 on_link".to_string());
+        }
+        // 未指定网关且不是直连路由时使用网卡的网关
+        let gateway_adapter = adapter
+            .as_ref()
+            .filter(|_| route_config.gateway.is_none() && !on_link);
+        let family = route_config.family.unwrap_or_default();
+        if let Some(adapter) = gateway_adapter
+            && !route_config.domains.is_empty()
+        {
+            messages.extend(missing_gateway(adapter, &family));
+        }
+        for domain in route_config.domains.iter() {
+            let key = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+            if key.is_empty() {
+                messages.push("domains 中存在空域名".to_string());
+                continue;
+            }
+            let name = format!("域名 {}", domain);
+            // 重复的域名只报告一次
+            if let Some(message) = record(&mut domains, key, origin, &adapter, &name, true) {
+                messages.push(message);
+                continue;
+            }
+            if !resolve {
+                continue;
+            }
+            match parse_domain(domain, &family) {
+                Err(_) => messages.push(format!("域名 {} 无法解析", domain)),
+                Ok(ip_list) if ip_list.is_empty() => {
+                    messages.push(format!("域名 {} 没有 {} 地址", domain, family))
+                }
+                Ok(ip_list) => {
+                    for ip in ip_list {
+                        let cidr = match route_config.prefix {
+                            Some(prefix) => {
+                                Cidr::parse(&ip.to_string(), Some(prefix), &HostBits::Normalize)
+                            }
+                            None => Ok(Cidr::host(ip)),
+                        };
+                        match cidr {
+                            // 不同域名解析到同一地址很常见，只报告指向不同网卡的冲突
+                            Ok(cidr) => {
+                                let name = format!("域名 {} 解析得到的 {}", domain, cidr);
+                                messages.extend(record(
+                                    &mut destinations,
+                                    cidr,
+                                    origin,
+                                    &adapter,
+                                    &name,
+                                    false,
+                                ));
+                            }
+                            Err(e) => messages.push(e.message),
+                        }
+                    }
+                }
+            }
+        }
+        for ip_str in route_config.ips.iter() {
+            let prefix = if ip_str.contains('/') {
+                None
+            } else {
+                route_config.prefix
+            };
+            let cidr = match Cidr::parse(ip_str, prefix, host_bits) {
+                Ok(cidr) => cidr,
+                Err(e) => {
+                    messages.push(e.message);
+                    continue;
+                }
+            };
+            if is_auto && cidr != Cidr::host(cidr.address) {
+                messages.push(format!(
+                    "网段 {} 没有确定的检查目标，不能自动选择网卡",
+                    cidr
+                ));
+            }
+            if let Some(gateway) = route_config.gateway
+                && gateway.is_ipv4() != cidr.address.is_ipv4()
+            {
+                messages.push(format!(
+                    "网关地址 {} 与目标地址 {} 的地址族不一致",
+                    gateway, cidr
+                ));
+            }
+            if let Some(adapter) = gateway_adapter
+                && let Err(e) = Interface::get_gateway_for(adapter, &cidr.address)
+            {
+                messages.push(e.message);
+            }
+            let name = format!("目标 {}", cidr);
+            messages.extend(record(
+                &mut destinations,
+                cidr,
+                origin,
+                &adapter,
+                &name,
+                true,
+            ));
+        }
+        issues.extend(messages.into_iter().map(|message| ConfigIssue {
+            origin: origin.clone(),
+            message,
+        }));
+    }
+    issues
+}
+
+/// 检查条目使用的网卡与候选网卡是否存在，返回指定的网卡
+///
+/// # Arguments
+///
+/// * `route_config` - 使用默认值补全后的路由条目
+/// * `adapters` - 系统网卡列表
+/// * `messages` - 发现的问题
+///
+fn validate_adapter(
+    route_config: &RouteConfig,
+    adapters: &[AdapterInfo],
+    messages: &mut Vec<String>,
+) -> Option<AdapterInfo> {
+    let candidates = route_config.candidates.as_deref().unwrap_or_default();
+    match &route_config.ifindex {
+        None => {
+            messages.push("缺少 ifindex，且 defaults 中没有设置".to_string());
+            None
+        }
+        Some(IfIndex::Adapter(selector)) => {
+            if !candidates.is_empty() {
+                messages.push("候选网卡只能在网卡索引为 auto 时使用".to_string());
+            }
+            selector
+                .select(adapters)
+                .map_err(|e| messages.push(e.message))
+                .ok()
+        }
+        Some(IfIndex::Auto) => {
+            if candidates.is_empty() {
+                messages.push("网卡索引为 auto 时需要指定候选网卡".to_string());
+            }
+            for candidate in candidates {
+                if let Err(e) = candidate.select(adapters) {
+                    messages.push(format!("候选网卡: {}", e.message));
+                }
+            }
+            None
+        }
+    }
+}
+
+/// 检查网卡是否有域名解析结果所需地址族的网关，地址族为 both 时只需要其中之一
+///
+/// # Arguments
+///
+/// * `adapter` - 网卡信息
+/// * `family` - 地址族
+///
+fn missing_gateway(adapter: &AdapterInfo, family: &Family) -> Option<String> {
+    let ipv4 = Interface::get_ipv4_gateway(adapter);
+    let ipv6 = Interface::get_ipv6_gateway(adapter);
+    match family {
+        Family::V4 => ipv4.err().map(|e| e.message),
+        Family::V6 => ipv6.err().map(|e| e.message),
+        Family::Both => (ipv4.is_err() && ipv6.is_err())
+            .then(|| format!("网卡 {} 没有可用的网关", adapter.name)),
+    }
+}
+
+/// 记录目标所在的条目与网卡，目标已出现过时返回重复或指向不同网卡的说明
+///
+/// # Arguments
+///
+/// * `seen` - 已出现过的目标
+/// * `key` - 目标
+/// * `origin` - 条目所在的配置文件与序号
+/// * `adapter` - 条目指定的网卡，自动选择时为空
+/// * `name` - 目标的描述
+/// * `duplicate` - 是否报告重复的目标
+///
+fn record<K: Eq + Hash>(
+    seen: &mut HashMap<K, Seen>,
+    key: K,
+    origin: &RouteOrigin,
+    adapter: &Option<AdapterInfo>,
+    name: &str,
+    duplicate: bool,
+) -> Option<String> {
+    let Some(previous) = seen.get(&key) else {
+        seen.insert(
+            key,
+            Seen {
+                origin: origin.clone(),
+                adapter: adapter.clone(),
+            },
+        );
+        return None;
+    };
+    match (&previous.adapter, adapter) {
+        (Some(a), Some(b)) if a.index != b.index => Some(format!(
+            "{} 同时指向网卡 {} ({}) 与网卡 {} ({})，前者位于{}",
+            name, a.name, a.index, b.name, b.index, previous.origin
+        )),
+        _ if duplicate && previous.origin == *origin => Some(format!("{} 重复", name)),
+        _ if duplicate => Some(format!("{} 与{}重复", name, previous.origin)),
+        _ => None,
+    }
+}
@@ -0,0 +1,368 @@
+use crate::base::NetRouteError;
+use crate::probe::check::Check;
+use crate::route::backend::{self, RouteBackend};
+use crate::route::cidr::HostBits;
+use crate::route::config::format::ConfigFormat;
+use crate::route::config::include;
+use crate::route::plan::{self, same_record};
+use crate::route::select::AdapterSelection;
+use crate::route::state::{self, RouteSource, RouteState};
+use crate::route::{
+    TargetOptions, build_config_plan, config_selection, dns, domain_targets, execute_plan,
+    record_plan_state,
+};
+use winroute::Route;
+
+/// 守护进程的运行参数
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    /// 重新解析域名的最短间隔，秒，TTL 小于该值或无法获取时使用
+    pub min_ttl: u64,
+    /// 重新解析域名的最长间隔，秒，TTL 大于该值时使用
+    pub max_ttl: u64,
+    /// IP 地址从解析结果中消失后保留路由的时间，秒
+    pub grace: u64,
+    /// 退出时是否删除由该配置文件添加的所有路由
+    pub cleanup: bool,
+}
+
+impl DaemonOptions {
+    /// 根据 TTL 计算下一次重新解析的间隔，TTL 无法获取时使用最短间隔
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` - 域名解析结果的 TTL，秒
+    ///
+    pub fn refresh_interval(&self, ttl: Option<u32>) -> u64 {
+        ttl.map_or(self.min_ttl, u64::from)
+            .clamp(self.min_ttl, self.max_ttl)
+    }
+}
+
+/// 守护进程跟踪的域名
+struct DomainWatch {
+    /// 域名
+    domain: String,
+    /// 使用的网卡
+    selection: AdapterSelection,
+    /// 连通性检查方式
+    check: Option<Check>,
+    /// 地址族、度量值、网关等路由参数
+    options: TargetOptions,
+    /// 由该域名添加的路由，以及最近一次出现在解析结果中的时间
+    routes: Vec<(Route, u64)>,
+    /// 下一次重新解析的时间，Unix 时间戳（秒）
+    next_refresh: u64,
+}
+
+/// 按域名解析结果的 TTL 持续更新配置文件路由的守护进程
+pub struct Daemon {
+    /// 路由表后端
+    backend: Box<dyn RouteBackend>,
+    /// 配置文件的绝对路径，用于在状态文件中标识配置文件
+    config_path: String,
+    /// 运行参数
+    options: DaemonOptions,
+    /// 跟踪的域名
+    watches: Vec<DomainWatch>,
+}
+
+impl Daemon {
+    /// 应用配置文件并开始跟踪其中的域名
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - 配置文件路径
+    /// * `config_format` - 配置文件格式，为空时根据扩展名判断
+    /// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+    /// * `options` - 运行参数
+    ///
+    pub fn start(
+        config_path: &str,
+        config_format: &Option<ConfigFormat>,
+        host_bits: &HostBits,
+        options: DaemonOptions,
+    ) -> Result<Daemon, NetRouteError> {
+        if options.min_ttl == 0 || options.min_ttl > options.max_ttl {
+            return Err(NetRouteError::new(format!(
+                "重新解析间隔的范围无效: 最短 {} 秒，最长 {} 秒",
+                options.min_ttl, options.max_ttl
+            )));
+        }
+        let backend = backend::new_backend()?;
+        let plan = build_config_plan(
+            backend.as_ref(),
+            &Some(config_path.to_string()),
+            config_format,
+            &false,
+            host_bits,
+            &false,
+        )?;
+        log(&format!("应用配置文件 {}", plan.config_path));
+        plan::show_plan(&plan);
+        if plan.has_changes() {
+            execute_plan(backend.as_ref(), &plan)?;
+            record_plan_state(&plan)?;
+        }
+
+        let now = state::now();
+        let managed = RouteState::load()?.by_config(&plan.config_path);
+        let mut watches = vec![];
+        for route_config in include::load_config(config_path, config_format)?.resolved_routes()? {
+            let (selection, check) = config_selection(&route_config)?;
+            let target_options = TargetOptions::from_config(&route_config);
+            for domain in route_config.domains.iter() {
+                let routes = managed
+                    .iter()
+                    .filter(|managed| managed.source.domain.as_deref() == Some(domain.as_str()))
+                    .map(|managed| (managed.route.clone(), now))
+                    .collect();
+                watches.push(DomainWatch {
+                    domain: domain.clone(),
+                    selection: selection.clone(),
+                    check: check.clone(),
+                    options: target_options.clone(),
+                    routes,
+                    // 应用配置时已解析过一次，但无法获取 TTL
+                    next_refresh: now + options.min_ttl,
+                });
+            }
+        }
+        log(&format!(
+            "守护进程已启动，跟踪 {} 个域名，重新解析间隔 {}~{} 秒，地址消失 {} 秒后删除路由",
+            watches.len(),
+            options.min_ttl,
+            options.max_ttl,
+            options.grace
+        ));
+        Ok(Daemon {
+            backend,
+            config_path: plan.config_path,
+            options,
+            watches,
+        })
+    }
+
+    /// 下一次需要重新解析的时间，没有跟踪的域名时为空
+    pub fn next_refresh(&self) -> Option<u64> {
+        self.watches.iter().map(|watch| watch.next_refresh).min()
+    }
+
+    /// 重新解析所有到期的域名，添加新地址的路由，删除消失超过保留时间的地址的路由
+    ///
+    /// 解析失败或解析结果为空时保留已添加的路由，并在最短间隔后重试
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - 当前时间，Unix 时间戳（秒）
+    ///
+    pub fn refresh_due(&mut self, now: u64) {
+        let mut watches = std::mem::take(&mut self.watches);
+        for watch in watches.iter_mut() {
+            if watch.next_refresh > now {
+                continue;
+            }
+            let interval = match self.refresh_watch(watch, now) {
+                Ok(ttl) => self.options.refresh_interval(ttl),
+                Err(err) => {
+                    log(&format!(
+                        "{} 更新失败，{} 秒后重试: {}",
+                        watch.domain, self.options.min_ttl, err
+                    ));
+                    self.options.min_ttl
+                }
+            };
+            watch.next_refresh = now + interval;
+        }
+        self.watches = watches;
+    }
+
+    /// 重新解析域名并更新路由，返回解析结果的 TTL
+    ///
+    /// # Arguments
+    ///
+    /// * `watch` - 跟踪的域名
+    /// * `now` - 当前时间，Unix 时间戳（秒）
+    ///
+    fn refresh_watch(
+        &self,
+        watch: &mut DomainWatch,
+        now: u64,
+    ) -> Result<Option<u32>, NetRouteError> {
+        let resolved = dns::resolve(&watch.domain, &watch.options.family)?;
+        // 没有地址记录的响应不能说明地址已失效，保留已添加的路由
+        if resolved.ip_list.is_empty() {
+            return Err(NetRouteError::new(format!(
+                "域名 {} 没有 {} 地址，保留已添加的路由",
+                watch.domain, watch.options.family
+            )));
+        }
+        let target_list = domain_targets(
+            &watch.domain,
+            &resolved.ip_list,
+            &watch.selection,
+            watch.check.as_ref(),
+            &watch.options,
+        )?;
+        let live_routes = self.backend.get_routes()?;
+        let source = RouteSource::config(&self.config_path, &watch.domain);
+        let mut desired_routes = vec![];
+        for target in target_list {
+            let route = Route::new(target.cidr.address, target.cidr.prefix)
+                .ifindex(target.if_index)
+                .gateway(target.gateway)
+                .metric(target.metric);
+            if let Some((_, last_seen)) = watch
+                .routes
+                .iter_mut()
+                .find(|(managed, _)| same_record(managed, &route))
+            {
+                *last_seen = now;
+            } else if live_routes.iter().any(|live| same_record(live, &route)) {
+                // 路由表中已存在的路由不是由该域名添加的，不接管
+            } else {
+                match self.backend.add_route(&route) {
+                    Ok(()) => {
+                        log(&format!("添加路由 {} ({})", describe(&route), watch.domain));
+                        if let Err(err) =
+                            state::record_routes(std::slice::from_ref(&route), &source)
+                        {
+                            log(&err.message);
+                        }
+                        watch.routes.push((route.clone(), now));
+                    }
+                    Err(err) => log(&format!(
+                        "添加路由 {} ({}) 失败: {}",
+                        describe(&route),
+                        watch.domain,
+                        err
+                    )),
+                }
+            }
+            desired_routes.push(route);
+        }
+        let mut kept = vec![];
+        for (route, last_seen) in std::mem::take(&mut watch.routes) {
+            let desired = desired_routes
+                .iter()
+                .any(|desired| same_record(desired, &route));
+            if desired || now < last_seen + self.options.grace {
+                kept.push((route, last_seen));
+                continue;
+            }
+            // 路由已不在路由表中时只清除记录
+            let result = if live_routes.iter().any(|live| same_record(live, &route)) {
+                self.backend.remove_route(&route)
+            } else {
+                Ok(())
+            };
+            match result {
+                Ok(()) => {
+                    log(&format!(
+                        "删除路由 {} ({})，该地址已有 {} 秒不在解析结果中",
+                        describe(&route),
+                        watch.domain,
+                        now - last_seen
+                    ));
+                    if let Err(err) = state::forget_routes(std::slice::from_ref(&route)) {
+                        log(&err.message);
+                    }
+                }
+                Err(err) => {
+                    log(&format!(
+                        "删除路由 {} ({}) 失败: {}",
+                        describe(&route),
+                        watch.domain,
+                        err
+                    ));
+                    kept.push((route, last_seen));
+                }
+            }
+        }
+        watch.routes = kept;
+        Ok(resolved.ttl)
+    }
+
+    /// 停止守护进程，按运行参数删除由该配置文件添加的所有路由
+    pub fn shutdown(&self) -> Result<(), NetRouteError> {
+        if !self.options.cleanup {
+            log("守护进程已退出，保留已添加的路由");
+            return Ok(());
+        }
+        let plan = build_config_plan(
+            self.backend.as_ref(),
+            &Some(self.config_path.clone()),
+            &None,
+            &true,
+            &HostBits::Reject,
+            &false,
+        )?;
+        if plan.has_changes() {
+            execute_plan(self.backend.as_ref(), &plan)?;
+            record_plan_state(&plan)?;
+        }
+        log(&format!(
+            "守护进程已退出，删除了由该配置文件添加的 {} 条路由",
+            plan.count(plan::PlanAction::Remove)
+        ));
+        Ok(())
+    }
+}
+
+/// 路由的简短描述，如 `1.1.1.1/32 经网卡 28 网关 10.0.0.1`
+fn describe(route: &Route) -> String {
+    format!(
+        "{}/{} 经网卡 {} 网关 {}",
+        route.destination,
+        route.prefix,
+        route
+            .ifindex
+            .map_or("NONE".to_string(), |index| index.to_string()),
+        route.gateway
+    )
+}
+
+/// 输出带时间的日志
+///
+/// # Arguments
+///
+/// * `message` - 日志内容
+///
+fn log(message: &str) {
+    println!("[{}] {}", format_time(state::now()), message);
+}
+
+/// 将 Unix 时间戳格式化为 UTC 时间，如 `2024-01-01 00:00:00 UTC`
+///
+/// # Arguments
+///
+/// * `timestamp` - Unix 时间戳（秒）
+///
+pub fn format_time(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    // 按公历将天数转换为年月日
+    let era_days = days + 719468;
+    let era = era_days.div_euclid(146097);
+    let day_of_era = era_days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,298 @@
+use crate::base::NetRouteError;
+use crate::route::family::Family;
+use crate::route::simulate;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 查询 DNS 服务器的超时时间
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A 记录类型
+const TYPE_A: u16 = 1;
+/// AAAA 记录类型
+const TYPE_AAAA: u16 = 28;
+
+/// 带 TTL 的域名解析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    /// 解析得到的 IP 地址
+    pub ip_list: Vec<IpAddr>,
+    /// 解析结果的有效期，秒，使用系统解析器时无法获取
+    pub ttl: Option<u32>,
+}
+
+/// 解析域名并获取解析结果的 TTL
+///
+/// 直接向系统配置的 DNS 服务器查询 A 与 AAAA 记录，查询失败时使用系统解析器，此时 TTL 未知；
+/// 模拟网络环境中使用夹具中的解析结果与 TTL
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+/// * `family` - 需要保留的地址族
+///
+pub fn resolve(domain: &str, family: &Family) -> Result<Resolved, NetRouteError> {
+    if let Some(result) = simulate::lookup_host(domain) {
+        return Ok(Resolved {
+            ip_list: result?
+                .into_iter()
+                .filter(|ip| family.matches(ip))
+                .collect(),
+            ttl: simulate::dns_ttl(domain),
+        });
+    }
+    for server in nameservers() {
+        if let Ok(resolved) = query_family(&server, domain, family) {
+            return Ok(resolved);
+        }
+    }
+    Ok(Resolved {
+        ip_list: super::parse_domain(domain, family)?,
+        ttl: None,
+    })
+}
+
+/// 向 DNS 服务器查询地址族对应的记录，地址族为 both 时合并 A 与 AAAA 记录并使用较小的 TTL
+///
+/// # Arguments
+///
+/// * `server` - DNS 服务器地址
+/// * `domain` - 域名
+/// * `family` - 地址族
+///
+fn query_family(server: &IpAddr, domain: &str, family: &Family) -> Result<Resolved, NetRouteError> {
+    let record_types: &[u16] = match family {
+        Family::V4 => &[TYPE_A],
+        Family::V6 => &[TYPE_AAAA],
+        Family::Both => &[TYPE_A, TYPE_AAAA],
+    };
+    let mut resolved = Resolved {
+        ip_list: vec![],
+        ttl: None,
+    };
+    for record_type in record_types {
+        let answer = query(SocketAddr::new(*server, 53), domain, *record_type)?;
+        resolved.ip_list.extend(answer.ip_list);
+        resolved.ttl = match (resolved.ttl, answer.ttl) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    Ok(resolved)
+}
+
+/// 向 DNS 服务器发送一次查询
+///
+/// 在超时前持续接收响应，丢弃来源地址或报文标识不一致的报文，避免伪造的响应污染路由表
+///
+/// # Arguments
+///
+/// * `server` - DNS 服务器地址与端口
+/// * `domain` - 域名
+/// * `record_type` - 记录类型
+///
+pub fn query(
+    server: SocketAddr,
+    domain: &str,
+    record_type: u16,
+) -> Result<Resolved, NetRouteError> {
+    let io_error =
+        |e: std::io::Error| NetRouteError::new(format!("查询 DNS 服务器 {} 失败: {}", server, e));
+    let bind: SocketAddr = match server {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).map_err(io_error)?;
+    let id = random_id();
+    socket
+        .send_to(&build_query(id, domain, record_type)?, server)
+        .map_err(io_error)?;
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut buffer = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(NetRouteError::new(format!(
+                "查询 DNS 服务器 {} 超时",
+                server
+            )));
+        }
+        socket.set_read_timeout(Some(remaining)).map_err(io_error)?;
+        let (size, from) = socket.recv_from(&mut buffer).map_err(io_error)?;
+        if from != server || buffer[..size.min(2)] != id.to_be_bytes() {
+            continue;
+        }
+        return parse_response(&buffer[..size], id, domain, record_type);
+    }
+}
+
+/// 生成随机的报文标识
+fn random_id() -> u16 {
+    RandomState::new().hash_one(SystemTime::now()) as u16
+}
+
+/// 构造 DNS 查询报文
+///
+/// # Arguments
+///
+/// * `id` - 报文标识
+/// * `domain` - 域名
+/// * `record_type` - 记录类型
+///
+pub fn build_query(id: u16, domain: &str, record_type: u16) -> Result<Vec<u8>, NetRouteError> {
+    let mut packet = vec![];
+    packet.extend(id.to_be_bytes());
+    // 期望递归查询，一个问题
+    packet.extend([0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in domain.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(NetRouteError::new(format!(
+                "Invalid domain name: {}",
+                domain
+            )));
+        }
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend(record_type.to_be_bytes());
+    packet.extend(1u16.to_be_bytes());
+    Ok(packet)
+}
+
+/// 解析 DNS 响应报文，返回其中的 A 与 AAAA 记录，TTL 为应答中所有记录（包括 CNAME）的最小值
+///
+/// # Arguments
+///
+/// * `packet` - 响应报文
+/// * `id` - 查询报文的标识
+/// * `domain` - 域名
+/// * `record_type` - 查询的记录类型
+///
+pub fn parse_response(
+    packet: &[u8],
+    id: u16,
+    domain: &str,
+    record_type: u16,
+) -> Result<Resolved, NetRouteError> {
+    let malformed = || NetRouteError::new(format!("域名 {} 的 DNS 响应格式错误", domain));
+    let read_u16 = |offset: usize| {
+        packet
+            .get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(malformed)
+    };
+    if read_u16(0)? != id {
+        return Err(malformed());
+    }
+    let flags = read_u16(2)?;
+    if flags & 0x0200 != 0 {
+        return Err(NetRouteError::new(format!(
+            "域名 {} 的 DNS 响应被截断",
+            domain
+        )));
+    }
+    match flags & 0x000f {
+        0 => {}
+        3 => {
+            return Err(NetRouteError::new(format!(
+                "Invalid domain name: {}",
+                domain
+            )));
+        }
+        rcode => {
+            return Err(NetRouteError::new(format!(
+                "域名 {} 的 DNS 查询失败，响应码 {}",
+                domain, rcode
+            )));
+        }
+    }
+    let questions = read_u16(4)?;
+    let answers = read_u16(6)?;
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(packet, offset).ok_or_else(malformed)? + 4;
+    }
+    let mut resolved = Resolved {
+        ip_list: vec![],
+        ttl: None,
+    };
+    for _ in 0..answers {
+        offset = skip_name(packet, offset).ok_or_else(malformed)?;
+        let kind = read_u16(offset)?;
+        let ttl = (read_u16(offset + 4)? as u32) << 16 | read_u16(offset + 6)? as u32;
+        let length = read_u16(offset + 8)? as usize;
+        let data = packet
+            .get(offset + 10..offset + 10 + length)
+            .ok_or_else(malformed)?;
+        offset += 10 + length;
+        resolved.ttl = Some(resolved.ttl.map_or(ttl, |min| min.min(ttl)));
+        // CNAME 等记录只参与 TTL 的计算
+        let ip = match kind {
+            _ if kind != record_type => continue,
+            TYPE_A => <[u8; 4]>::try_from(data).map(IpAddr::from),
+            TYPE_AAAA => <[u8; 16]>::try_from(data).map(IpAddr::from),
+            _ => continue,
+        }
+        .map_err(|_| malformed())?;
+        if !resolved.ip_list.contains(&ip) {
+            resolved.ip_list.push(ip);
+        }
+    }
+    Ok(resolved)
+}
+
+/// 跳过报文中的域名，返回域名之后的偏移量，域名格式错误时返回空
+///
+/// # Arguments
+///
+/// * `packet` - 报文
+/// * `offset` - 域名的起始偏移量
+///
+fn skip_name(packet: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *packet.get(offset)?;
+        match length {
+            0 => return Some(offset + 1),
+            // 压缩指针占两个字节，指向的域名不需要读取
+            _ if length & 0xc0 == 0xc0 => return Some(offset + 2),
+            _ => offset += 1 + length as usize,
+        }
+    }
+}
+
+/// 系统配置的 DNS 服务器，读取已连接网卡的 DNS 服务器
+#[cfg(windows)]
+fn nameservers() -> Vec<IpAddr> {
+    let mut servers = vec![];
+    for adapter in ipconfig::get_adapters().unwrap_or_default() {
+        if adapter.oper_status() != ipconfig::OperStatus::IfOperStatusUp {
+            continue;
+        }
+        for server in adapter.dns_servers() {
+            // 跳过未配置时出现的站点本地地址
+            if !servers.contains(server) && !server.to_string().starts_with("fec0:") {
+                servers.push(*server);
+            }
+        }
+    }
+    servers
+}
+
+/// 系统配置的 DNS 服务器，读取 `/etc/resolv.conf` 中的 `nameserver`
+#[cfg(not(windows))]
+fn nameservers() -> Vec<IpAddr> {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some("nameserver"))
+                .then(|| fields.next())
+                .flatten()
+                .and_then(|server| server.parse().ok())
+        })
+        .collect()
+}
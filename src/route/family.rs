@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+
+/// 地址族
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Family {
+    /// 仅 IPv4
+    #[default]
+    V4,
+    /// 仅 IPv6
+    V6,
+    /// IPv4 与 IPv6
+    Both,
+}
+
+impl Family {
+    /// 判断 IP 地址是否属于当前地址族
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - IP 地址
+    ///
+    pub fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            Family::V4 => ip.is_ipv4(),
+            Family::V6 => ip.is_ipv6(),
+            Family::Both => true,
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Family::V4 => write!(f, "v4"),
+            Family::V6 => write!(f, "v6"),
+            Family::Both => write!(f, "both"),
+        }
+    }
+}
+
+/// 获取单个主机地址的子网掩码长度，IPv4 为 32，IPv6 为 128
+///
+/// # Arguments
+///
+/// * `ip` - IP 地址
+///
+pub fn host_prefix(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
@@ -0,0 +1,120 @@
+use crate::route::cidr::{self, Cidr};
+use crate::route::family::Family;
+use crate::route::state::RouteState;
+use clap::ValueEnum;
+use std::net::IpAddr;
+use winroute::Route;
+
+/// 路由列表的排序方式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteSort {
+    /// 按目标地址排序
+    Dest,
+    /// 按子网掩码长度排序
+    Prefix,
+    /// 按度量值排序
+    Metric,
+    /// 按网卡索引排序
+    Ifindex,
+}
+
+/// 路由列表的筛选条件，为空的条件不参与筛选
+#[derive(Debug, Clone, Default)]
+pub struct RouteFilter {
+    /// 目标网卡
+    pub ifindex: Option<u32>,
+    /// 目标网段位于该网段之内
+    pub dest_within: Option<Cidr>,
+    /// 目标网段包含该 IP 地址
+    pub dest_contains: Option<IpAddr>,
+    /// 网关地址
+    pub gateway: Option<IpAddr>,
+    /// 地址族
+    pub family: Option<Family>,
+    /// 度量值不超过该值，未设置度量值的路由按 0 处理
+    pub metric_max: Option<u32>,
+    /// 只保留由本工具添加的路由
+    pub managed_only: bool,
+}
+
+impl RouteFilter {
+    /// 判断路由是否满足所有筛选条件
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    /// * `route_state` - 路由状态，`managed_only` 为 true 时需要
+    ///
+    pub fn matches(&self, route: &Route, route_state: Option<&RouteState>) -> bool {
+        if self
+            .ifindex
+            .is_some_and(|ifindex| route.ifindex != Some(ifindex))
+        {
+            return false;
+        }
+        if let Some(within) = &self.dest_within
+            && (route.prefix < within.prefix
+                || !cidr::contains(&within.address, within.prefix, &route.destination))
+        {
+            return false;
+        }
+        if let Some(ip) = &self.dest_contains
+            && !cidr::contains(&route.destination, route.prefix, ip)
+        {
+            return false;
+        }
+        if self.gateway.is_some_and(|gateway| route.gateway != gateway) {
+            return false;
+        }
+        if self
+            .family
+            .is_some_and(|family| !family.matches(&route.destination))
+        {
+            return false;
+        }
+        if self
+            .metric_max
+            .is_some_and(|metric_max| route.metric.unwrap_or(0) > metric_max)
+        {
+            return false;
+        }
+        if self.managed_only && route_state.is_none_or(|state| state.find(route).is_none()) {
+            return false;
+        }
+        true
+    }
+
+    /// 筛选路由列表
+    ///
+    /// # Arguments
+    ///
+    /// * `routes` - 路由列表
+    /// * `route_state` - 路由状态，`managed_only` 为 true 时需要
+    ///
+    pub fn apply(&self, routes: Vec<Route>, route_state: Option<&RouteState>) -> Vec<Route> {
+        routes
+            .into_iter()
+            .filter(|route| self.matches(route, route_state))
+            .collect()
+    }
+}
+
+/// 对路由列表排序，排序字段相同时按目标网段排序
+///
+/// # Arguments
+///
+/// * `routes` - 路由列表
+/// * `sort` - 排序方式
+///
+pub fn sort_routes(routes: &mut [Route], sort: &RouteSort) {
+    match sort {
+        RouteSort::Dest => routes.sort_by_key(|route| (route.destination, route.prefix)),
+        RouteSort::Prefix => routes.sort_by_key(|route| (route.prefix, route.destination)),
+        RouteSort::Metric => {
+            routes.sort_by_key(|route| (route.metric.unwrap_or(0), route.destination, route.prefix))
+        }
+        RouteSort::Ifindex => {
+            routes.sort_by_key(|route| (route.ifindex, route.destination, route.prefix))
+        }
+    }
+}
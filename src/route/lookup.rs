@@ -0,0 +1,27 @@
+use crate::route::cidr;
+use std::net::IpAddr;
+use winroute::Route;
+
+/// 最长前缀匹配
+///
+/// 返回所有包含目标地址的路由，按匹配优先级排序：子网掩码越长越优先，
+/// 掩码相同时度量值越小越优先。第一条即为实际承载流量的路由。
+///
+/// # Arguments
+///
+/// * `routes` - 路由表
+/// * `ip` - 目标 IP 地址
+///
+pub fn longest_prefix_match(routes: &[Route], ip: &IpAddr) -> Vec<Route> {
+    let mut candidates = routes
+        .iter()
+        .filter(|route| cidr::contains(&route.destination, route.prefix, ip))
+        .cloned()
+        .collect::<Vec<Route>>();
+    candidates.sort_by(|a, b| {
+        b.prefix
+            .cmp(&a.prefix)
+            .then(a.metric.unwrap_or(0).cmp(&b.metric.unwrap_or(0)))
+    });
+    candidates
+}
@@ -1,162 +1,69 @@
-use crate::base::{NetRouteError, files, user_input};
+use crate::base::{NetRouteError, files, output, user_input};
 use crate::interface::{AdapterInfo, Interface};
-use crate::route::config::RouteConfigData;
-use encoding_rs::GBK;
+use crate::probe::check::Check;
+use crate::probe::{self, CheckResult, PingOptions, PingStats};
+use crate::route::backend::RouteBackend;
+use crate::route::cidr::{Cidr, HostBits};
+use crate::route::config::format::ConfigFormat;
+use crate::route::config::{RouteConfig, RouteConfigData};
+use crate::route::config::{include, validate};
+use crate::route::daemon::{Daemon, DaemonOptions};
+use crate::route::family::Family;
+use crate::route::filter::{RouteFilter, RouteSort};
+use crate::route::plan::{DesiredRoute, PlanAction, RoutePlan};
+use crate::route::select::{AdapterSelection, IfIndex, SelectScope};
+use crate::route::snapshot::RouteSnapshot;
+use crate::route::state::{RouteSource, RouteState};
+use crate::route::transaction::Transaction;
 use prettytable::Table;
-use std::net::IpAddr;
-use std::process::Command;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 use winroute::*;
 
-struct WinRoute {
-    manager: RouteManager,
-}
-
-impl WinRoute {
-    pub fn new() -> Result<WinRoute, NetRouteError> {
-        match RouteManager::new() {
-            Ok(manager) => Ok(WinRoute { manager }),
-            Err(e) => Err(NetRouteError::new(e.to_string())),
-        }
-    }
-
-    pub fn get_routes(&self) -> Result<Vec<Route>, NetRouteError> {
-        match self.manager.routes() {
-            Ok(routes) => Ok(routes),
-            Err(e) => Err(NetRouteError::new(e.to_string())),
-        }
-    }
-
-    pub fn search_route_by_ip(
-        &self,
-        dest: &IpAddr,
-        prefix: &u8,
-        if_index: Option<&u32>,
-        gateway: Option<&IpAddr>,
-    ) -> Result<Vec<Route>, NetRouteError> {
-        Ok(self
-            .get_routes()?
-            .into_iter()
-            .filter(|route| {
-                // 根据提供的参数过滤路由
-                (route.destination == *dest)
-                    && (route.prefix == *prefix)
-                    && (if_index.is_none() || route.ifindex == Some(*if_index.unwrap()))
-                    && (gateway.is_none() || route.gateway == *gateway.unwrap())
-            })
-            .collect::<Vec<Route>>())
-    }
-
-    pub fn search_route_by_ip_vec(
-        &self,
-        ip_vec: Vec<IpAddr>,
-        prefix: &u8,
-        if_index: &Option<u32>,
-        gateway: Option<&IpAddr>,
-    ) -> Result<Vec<Route>, NetRouteError> {
-        Ok(self
-            .get_routes()?
-            .into_iter()
-            .filter(|route| {
-                // 根据提供的参数过滤路由
-                (ip_vec.contains(&route.destination))
-                    && (route.prefix == *prefix)
-                    && (if_index.is_none() || route.ifindex == Some(if_index.unwrap()))
-                    && (gateway.is_none() || route.gateway == *gateway.unwrap())
-            })
-            .collect::<Vec<Route>>())
-    }
-
-    pub fn add_route(&self, route: &Route) -> Result<(), NetRouteError> {
-        self.manager
-            .add_route(route)
-            .map_err(|err| NetRouteError::new(format!("添加路由错误: {}", err)))?;
-        Ok(())
-    }
-
-    pub fn add_ip_route(
-        &self,
-        destination: IpAddr,
-        prefix: &u8,
-        if_index: &u32,
-        gateway: IpAddr,
-        metric: &u32,
-    ) -> Result<Route, NetRouteError> {
-        // 创建路由
-        let mut route = Route::new(destination, *prefix);
-        route = route.ifindex(*if_index);
-        route = route.gateway(gateway);
-        route = route.metric(*metric);
-        // 查询路由表
-        let search_route_vec =
-            self.search_route_by_ip(&destination, prefix, Some(if_index), None)?;
-        if !search_route_vec.is_empty() {
-            println!("路由表中已存在匹配的路由！");
-            show_route_table(&search_route_vec);
-            return Err(NetRouteError::new("路由已存在".to_string()));
-        }
-        self.manager
-            .add_route(&route)
-            .map_err(|err| NetRouteError::new(format!("添加路由错误: {}", err)))?;
-        Ok(route)
-    }
-
-    pub fn remove_route(&self, route: &Route) -> Result<(), NetRouteError> {
-        self.manager
-            .delete_route(route)
-            .map_err(|err| NetRouteError::new(format!("删除路由错误: {}", err)))?;
-        Ok(())
-    }
-}
-
-/// 使用指定网卡的IP地址进行ping测试
+/// 使用指定网卡的IP地址并发检查多个目标地址的连通性，不可达的地址只提示不阻止添加路由
 ///
 /// # Arguments
 ///
-/// * `target_ip` - 目标IP地址
+/// * `target_ips` - 目标IP地址列表
 /// * `adapter_info` - 网卡信息
+/// * `check` - 检查方式
+/// * `server_name` - TLS 检查使用的服务器名称，为空时使用目标IP地址
 ///
-pub fn ping_from_interface(
-    target_ip: &String,
+fn check_from_interface(
+    target_ips: &[IpAddr],
     adapter_info: &AdapterInfo,
-) -> Result<bool, NetRouteError> {
-    // 获取网卡的IP地址作为源地址
-    let source_ip = &adapter_info.ip_address;
-
+    check: &Check,
+    server_name: Option<&str>,
+) -> Result<(), NetRouteError> {
     println!(
-        "使用网卡 {} (IP: {}) 测试连接到 {}",
-        adapter_info.name, source_ip, target_ip
+        "使用网卡 {} 以 {} 方式测试连接到 {} 个目标地址",
+        adapter_info.name,
+        check,
+        target_ips.len()
     );
-
-    // 在Windows上使用ping命令，通过-S参数指定源IP
-    let output = Command::new("ping")
-        .args(&[
-            "-n", "2", // 发送4个数据包
-            "-w", "1000", // 超时时间1秒
-            "-S", &source_ip, // 指定源IP地址
-            target_ip,  // 目标IP地址
-        ])
-        .output()
-        .map_err(|e| NetRouteError::new(format!("网络通路测试失败: {}", e)))?;
-
-    let output_str = GBK.decode(&output.stdout).0.into_owned();
-
-    // 检查是否收到回复
-    let success = output_str.contains("来自")
-        && output_str.contains("字节=")
-        && !output_str.contains("100% 丢失");
-
-    if success {
-        println!("连接测试成功，目标IP可达");
-    } else {
-        println!("连接测试失败，无法连接到目标IP");
-        println!("详细输出: \n{}", output_str);
+    let results = probe::check_all_from_adapter(
+        adapter_info,
+        target_ips,
+        check,
+        server_name,
+        &PingOptions::default(),
+    )
+    .into_iter()
+    .collect::<Result<Vec<CheckResult>, NetRouteError>>()?;
+    probe::show_check_table(&results);
+    let unreachable = results.iter().filter(|result| !result.success).count();
+    if unreachable > 0 {
+        println!("连接测试失败，{} 个目标地址不可达", unreachable);
     }
-
-    Ok(success)
+    Ok(())
 }
 
 /// 计算总页数和当前页码并打印信息
 ///
+/// 页码超出范围时使用最后一页，没有数据时为第 1 页
+///
 /// # Arguments
 ///
 /// * `total_size` - 总数据量
@@ -164,27 +71,76 @@ pub fn ping_from_interface(
 /// * `current_page` - 当前页码，从 1 开始
 ///
 fn parse_page_info(total_size: usize, page_size: usize, current_page: usize) -> usize {
-    let total_pages = (total_size + page_size - 1) / page_size;
+    let total_pages = total_size.div_ceil(page_size).max(1);
     // 计算当前页码
-    let current_page = if current_page > total_pages {
-        total_pages
-    } else {
-        current_page
-    };
-    println!(
-        "总数: {}, 总页数: {}, 当前页: {}",
-        total_size, total_pages, current_page
-    );
+    let current_page = current_page.clamp(1, total_pages);
+    // 机器可读格式只输出记录本身
+    if output::is_table() {
+        println!(
+            "总数: {}, 总页数: {}, 当前页: {}",
+            total_size, total_pages, current_page
+        );
+    }
     current_page
 }
 
+/// 路由列表中的一行，用于机器可读格式的输出
+#[derive(Serialize, Debug, Clone)]
+pub struct RouteRow {
+    /// 目标地址
+    pub destination: IpAddr,
+    /// 掩码长度
+    pub prefix: u8,
+    /// 网关地址
+    pub gateway: IpAddr,
+    /// 目标网卡
+    pub ifindex: Option<u32>,
+    /// 度量值
+    pub metric: Option<u32>,
+    /// 网卡 LUID
+    pub luid: Option<u64>,
+    /// 协议版本，4 或 6
+    pub version: u8,
+    /// 是否由本工具添加，仅在 `--managed` 时输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub managed: Option<bool>,
+    /// 路由的来源，仅在 `--managed` 时输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl RouteRow {
+    /// 生成路由列表中的一行
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    /// * `route_state` - 路由状态，为空时不输出托管信息
+    ///
+    pub fn new(route: &Route, route_state: Option<&RouteState>) -> RouteRow {
+        let managed = route_state.map(|route_state| route_state.find(route));
+        RouteRow {
+            destination: route.destination,
+            prefix: route.prefix,
+            gateway: route.gateway,
+            ifindex: route.ifindex,
+            metric: route.metric,
+            luid: route.luid,
+            version: route.version,
+            managed: managed.map(|managed| managed.is_some()),
+            source: managed
+                .map(|managed| managed.map_or(String::new(), |managed| managed.source.to_string())),
+        }
+    }
+}
+
 /// 展示路由列表表格
 ///
 /// # Arguments
 ///
 /// * `route_vec` - 路由列表
 ///
-pub fn show_route_table(route_vec: &Vec<Route>) {
+pub fn show_route_table(route_vec: &[Route]) {
     // 实现表格展示路由列表
     let mut table = Table::new();
     table.add_row(row![
@@ -214,17 +170,39 @@ pub fn show_route_table(route_vec: &Vec<Route>) {
 
 /// 展示路由列表
 ///
+/// 先筛选与排序，再对结果分页
+///
 /// # Arguments
 ///
 /// * `page_size` - 每页展示数量
 /// * `current_page` - 当前页码，从 1 开始
+/// * `all` - 是否不分页，展示全部路由
+/// * `managed` - 是否标记由本工具添加的路由
+/// * `filter` - 筛选条件
+/// * `sort` - 排序方式，为空时保持路由表中的顺序
 ///
-pub fn show_route_list(page_size: usize, current_page: usize) -> Result<(), NetRouteError> {
+pub fn show_route_list(
+    page_size: usize,
+    current_page: usize,
+    all: &bool,
+    managed: &bool,
+    filter: &RouteFilter,
+    sort: &Option<RouteSort>,
+) -> Result<(), NetRouteError> {
     // 获取路由列表
-    let win_route = WinRoute::new()?;
-    let routes = win_route.get_routes()?;
+    let backend = backend::new_backend()?;
+    let route_state = if *managed || filter.managed_only {
+        Some(RouteState::load()?)
+    } else {
+        None
+    };
+    let mut routes = filter.apply(backend.get_routes()?, route_state.as_ref());
+    if let Some(sort) = sort {
+        filter::sort_routes(&mut routes, sort);
+    }
 
     // 计算总页数
+    let page_size = if *all { routes.len().max(1) } else { page_size };
     let current_page = parse_page_info(routes.len(), page_size, current_page);
 
     let route_vec = routes
@@ -233,8 +211,49 @@ pub fn show_route_list(page_size: usize, current_page: usize) -> Result<(), NetR
         .take(page_size)
         .collect::<Vec<Route>>();
     // 打印路由列表
-    show_route_table(&route_vec);
-    Ok(())
+    let rows = route_vec
+        .iter()
+        .map(|route| RouteRow::new(route, route_state.as_ref()))
+        .collect::<Vec<RouteRow>>();
+    output::print_rows(&rows, || match &route_state {
+        Some(route_state) => show_managed_route_table(&route_vec, route_state),
+        None => show_route_table(&route_vec),
+    })
+}
+
+/// 展示路由列表表格，并标记由本工具添加的路由及其来源
+///
+/// # Arguments
+///
+/// * `route_vec` - 路由列表
+/// * `route_state` - 路由状态
+///
+fn show_managed_route_table(route_vec: &[Route], route_state: &RouteState) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "序号",
+        "目标地址",
+        "prefix",
+        "网关地址",
+        "目标网卡",
+        "metric",
+        "托管",
+        "来源"
+    ]);
+    for (idx, route) in route_vec.iter().enumerate() {
+        let managed = route_state.find(route);
+        table.add_row(row![
+            idx,
+            route.destination.to_string(),
+            route.prefix.to_string(),
+            route.gateway.to_string(),
+            route.ifindex.map_or("NONE".to_string(), |v| v.to_string()),
+            route.metric.map_or("NONE".to_string(), |v| v.to_string()),
+            if managed.is_some() { "是" } else { "" },
+            managed.map_or(String::new(), |managed| managed.source.to_string())
+        ]);
+    }
+    table.printstd();
 }
 
 pub fn get_adapter_by_if_index(if_index: &u32) -> Result<AdapterInfo, NetRouteError> {
@@ -243,54 +262,76 @@ pub fn get_adapter_by_if_index(if_index: &u32) -> Result<AdapterInfo, NetRouteEr
     Ok(adapter)
 }
 
-pub fn get_gateway_ip_by_if_index(if_index: &u32) -> Result<IpAddr, NetRouteError> {
-    let adapter = get_adapter_by_if_index(if_index)?;
-    let ipv4_gateway = Interface::get_ipv4_gateway(&adapter)?;
-    Ok(ipv4_gateway)
+/// 解析命令行或配置文件中的目标地址，主机位被修正时将修正说明输出到标准错误，不影响表格或 JSON 输出
+///
+/// # Arguments
+///
+/// * `destination` - 目标 IP 地址或 CIDR
+/// * `prefix` - 单独指定的子网掩码
+/// * `host_bits` - 包含主机位时的处理方式
+///
+fn parse_destination(
+    destination: &str,
+    prefix: &Option<u8>,
+    host_bits: &HostBits,
+) -> Result<Cidr, NetRouteError> {
+    let (cidr, notice) = Cidr::parse_with_notice(destination, *prefix, host_bits)?;
+    if let Some(notice) = notice {
+        eprintln!("{}", notice);
+    }
+    Ok(cidr)
 }
 
 /// 添加路由
 ///
 /// # Arguments
 ///
-/// * `destination` - 目标 IP 地址
-/// * `prefix` - 目标 IP 子网掩码
+/// * `destination` - 目标 IP 地址或 CIDR，如 `10.0.0.0/8`
+/// * `prefix` - 目标 IP 子网掩码，为空时使用 CIDR 中的掩码或单个主机地址
 /// * `if_index` - 网卡索引
 /// * `gateway` - 网关 IP 地址
 /// * `metric` - 路由度量值，值越小优先级越高
-/// * `no_check` - 是否检查目标地址是否可达
+/// * `check` - 连通性检查方式，为空时不检查目标地址是否可达
+/// * `host_bits` - 目标地址包含主机位时的处理方式
 ///
 pub fn add_route(
-    destination: &String,
-    prefix: &u8,
+    destination: &str,
+    prefix: &Option<u8>,
     if_index: &u32,
     gateway: &Option<String>,
     metric: &u32,
-    no_check: &bool,
+    check: Option<&Check>,
+    host_bits: &HostBits,
 ) -> Result<(), NetRouteError> {
     // 检查if_index网卡是否存在
-    let ipv4_gateway = get_gateway_ip_by_if_index(if_index)?;
+    let adapter = get_adapter_by_if_index(if_index)?;
     // 解析目标地址
-    let dest_ip: IpAddr = destination.parse().map_err(|_| {
-        NetRouteError::new(format!("Invalid destination IP address: {}", destination))
-    })?;
+    let cidr = parse_destination(destination, prefix, host_bits)?;
+    let dest_ip = cidr.address;
     // 检查目标地址和网卡是否可达
-    if !*no_check {
-        let adapter = get_adapter_by_if_index(if_index)?;
-        ping_from_interface(destination, &adapter)?;
+    if let Some(check) = check {
+        check_from_interface(&[dest_ip], &adapter, check, None)?;
     }
-    // 解析网关地址
+    // 解析网关地址，未指定时使用网卡对应地址族的网关
     let gateway: IpAddr = match gateway {
         Some(gateway) => gateway
             .parse()
             .map_err(|_| NetRouteError::new(format!("Invalid gateway IP address: {}", gateway)))?,
-        None => ipv4_gateway,
+        None => Interface::get_gateway_for(&adapter, &dest_ip)?,
     };
-    let win_route = WinRoute::new()?;
-    let route = win_route.add_ip_route(dest_ip, prefix, if_index, gateway, metric)?;
+    if gateway.is_ipv4() != dest_ip.is_ipv4() {
+        return Err(NetRouteError::new(format!(
+            "网关地址 {} 与目标地址 {} 的地址族不一致",
+            gateway, dest_ip
+        )));
+    }
+    let backend = backend::new_backend()?;
+    auto_snapshot(backend.as_ref())?;
+    let route = backend.add_ip_route(dest_ip, &cidr.prefix, if_index, gateway, metric)?;
+    state::record_routes(std::slice::from_ref(&route), &RouteSource::cli(None))?;
     // 显示路由表
     println!("路由添加成功！");
-    show_route_table(&vec![route]);
+    show_route_table(&[route]);
     Ok(())
 }
 
@@ -299,29 +340,134 @@ pub fn add_route(
 /// # Arguments
 ///
 /// * `domain` - 域名
-fn parse_domain(domain: &String) -> Result<Vec<IpAddr>, NetRouteError> {
-    // 解析域名的IP地址列表
-    let ip_list = dns_lookup::lookup_host(domain)
-        .map_err(|_| NetRouteError::new(format!("Invalid domain name: {}", domain)))?;
+/// * `family` - 需要保留的地址族
+fn parse_domain(domain: &str, family: &Family) -> Result<Vec<IpAddr>, NetRouteError> {
+    // 解析域名的IP地址列表，模拟网络环境中使用夹具中的解析结果
+    let ip_list = match simulate::lookup_host(domain) {
+        Some(result) => result?,
+        None => dns_lookup::lookup_host(domain)
+            .map_err(|_| NetRouteError::new(format!("Invalid domain name: {}", domain)))?,
+    };
     Ok(ip_list
         .into_iter()
-        .filter(|ip| ip.is_ipv4())
+        .filter(|ip| family.matches(ip))
         .collect::<Vec<IpAddr>>())
 }
 
+/// 域名解析结果中的一行，用于机器可读格式的输出
+#[derive(Serialize, Debug, Clone)]
+pub struct DnsRow {
+    /// 域名
+    pub domain: String,
+    /// 解析得到的 IP 地址
+    pub address: IpAddr,
+    /// 协议版本，4 或 6
+    pub version: u8,
+}
+
 /// 显示域名的IP地址列表
 ///
 /// # Arguments
 ///
 /// * `domain` - 域名
-pub fn show_domain_ips_info(domain: &String) -> Result<(), NetRouteError> {
-    let ip_list = parse_domain(domain)?;
-    let mut table = Table::new();
-    table.add_row(row!["序号", "IP地址"]);
-    for (idx, ip) in ip_list.iter().enumerate() {
-        table.add_row(row![idx, ip.to_string()]);
+/// * `family` - 需要展示的地址族
+pub fn show_domain_ips_info(domain: &str, family: &Family) -> Result<(), NetRouteError> {
+    let ip_list = parse_domain(domain, family)?;
+    let rows = ip_list
+        .iter()
+        .map(|ip| DnsRow {
+            domain: domain.to_string(),
+            address: *ip,
+            version: if ip.is_ipv4() { 4 } else { 6 },
+        })
+        .collect::<Vec<DnsRow>>();
+    output::print_rows(&rows, || {
+        let mut table = Table::new();
+        table.add_row(row!["序号", "IP地址"]);
+        for (idx, ip) in ip_list.iter().enumerate() {
+            table.add_row(row![idx, ip.to_string()]);
+        }
+        table.printstd();
+    })
+}
+
+/// 使用指定网卡测试到目标地址的连通性，目标为域名时并发测试所有解析结果
+///
+/// 所有目标地址都不可达时返回错误
+///
+/// # Arguments
+///
+/// * `if_index` - 网卡索引
+/// * `destination` - 目标 IP 地址或域名
+/// * `options` - 测试参数
+/// * `family` - 目标为域名时需要测试的地址族
+///
+pub fn ping_destination(
+    if_index: &u32,
+    destination: &str,
+    options: &PingOptions,
+    family: &Family,
+) -> Result<(), NetRouteError> {
+    let adapter = get_adapter_by_if_index(if_index)?;
+    let target_ips = match destination.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => parse_domain(destination, family)?,
+    };
+    let stats = probe::ping_all_from_adapter(&adapter, &target_ips, options)
+        .into_iter()
+        .collect::<Result<Vec<PingStats>, NetRouteError>>()?;
+    output::print_rows(&stats, || probe::show_ping_table(&stats))?;
+    if !stats.iter().any(PingStats::is_reachable) {
+        return Err(NetRouteError::new(format!(
+            "使用网卡 {} 无法连接到 {}",
+            adapter.name, destination
+        )));
+    }
+    Ok(())
+}
+
+/// 查询目标地址实际使用的路由
+///
+/// 对路由表进行最长前缀匹配，展示承载流量的路由、网关与网卡，以及被淘汰的其他候选路由
+///
+/// # Arguments
+///
+/// * `destination` - 目标 IP 地址或域名
+///
+pub fn show_route_lookup(destination: &str) -> Result<(), NetRouteError> {
+    let ip_list = match destination.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => parse_domain(destination, &Family::Both)?,
+    };
+    let backend = backend::new_backend()?;
+    let routes = backend.get_routes()?;
+    let interface = Interface::new();
+    for ip in ip_list {
+        let mut candidates = lookup::longest_prefix_match(&routes, &ip);
+        if candidates.is_empty() {
+            println!("目标地址 {} 没有匹配的路由", ip);
+            continue;
+        }
+        let best = candidates.remove(0);
+        let adapter_name = best
+            .ifindex
+            .and_then(|index| interface.get_interface_by_index(&index).ok())
+            .map_or("未知".to_string(), |adapter| adapter.name);
+        println!(
+            "目标地址 {} 使用路由 {}/{}，网关: {}，网卡: {} ({})",
+            ip,
+            best.destination,
+            best.prefix,
+            best.gateway,
+            adapter_name,
+            best.ifindex.map_or("NONE".to_string(), |v| v.to_string())
+        );
+        show_route_table(std::slice::from_ref(&best));
+        if !candidates.is_empty() {
+            println!("其他候选路由:");
+            show_route_table(&candidates);
+        }
     }
-    table.printstd();
     Ok(())
 }
 
@@ -330,58 +476,285 @@ pub fn show_domain_ips_info(domain: &String) -> Result<(), NetRouteError> {
 /// # Arguments
 ///
 /// * `domain` - 域名
-/// * `if_index` - 网卡索引
+/// * `selection` - 使用的网卡，自动选择时按连通性测试结果为每个 IP 地址选择网卡
 /// * `metric` - 路由度量值，值越小优先级越高
-/// * `no_check` - 是否检查目标地址是否可达
+/// * `check` - 连通性检查方式，为空时不检查，TLS 检查使用域名作为服务器名称
+/// * `family` - 需要添加路由的地址族
+/// * `aggregate` - 是否将解析结果聚合为最少的 CIDR 集合
 ///
 pub fn add_domain_route(
-    domain: &String,
-    if_index: &u32,
+    domain: &str,
+    selection: &AdapterSelection,
     metric: &u32,
-    no_check: &bool,
+    check: Option<&Check>,
+    family: &Family,
+    aggregate: &bool,
 ) -> Result<(), NetRouteError> {
-    // 解析域名的IP地址列表
-    let ip_list = parse_domain(&domain)?;
-    // 获取网卡信息
-    let interface = Interface::new();
-    let adapter = interface.get_interface_by_index(if_index)?;
-    let gateway = Interface::get_ipv4_gateway(&adapter)?;
-    // 逐个检查IP地址是否可达
-    if !*no_check {
-        for ip in ip_list.iter() {
-            ping_from_interface(&ip.to_string(), &adapter)?;
-        }
+    let options = TargetOptions {
+        family: *family,
+        metric: *metric,
+        ..TargetOptions::default()
+    };
+    let mut target_list = resolve_domain_targets(domain, selection, check, &options)?;
+    if *aggregate {
+        target_list = aggregate_targets(target_list);
     }
     // 逐个添加路由信息
+    let backend = backend::new_backend()?;
+    auto_snapshot(backend.as_ref())?;
     let mut added_routes = vec![];
-    for dest_ip in ip_list {
-        let win_route = WinRoute::new()?;
-        let added_route = win_route.add_ip_route(dest_ip, &32, if_index, gateway, metric)?;
-        added_routes.push(added_route);
+    let mut result = Ok(());
+    for target in target_list {
+        let cidr = target.cidr;
+        match backend.add_ip_route(
+            cidr.address,
+            &cidr.prefix,
+            &target.if_index,
+            target.gateway,
+            &target.metric,
+        ) {
+            Ok(added_route) => added_routes.push(added_route),
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        }
     }
+    // 添加失败前已添加的路由同样需要记录
+    state::record_routes(&added_routes, &RouteSource::cli(Some(domain)))?;
+    result?;
     // 显示路由表
     println!("路由添加成功！");
     show_route_table(&added_routes);
     Ok(())
 }
 
+/// 解析域名并为每个 IP 地址确定网卡与网关
+///
+/// 使用指定网卡时按 `check` 检查连通性，不可达的地址只提示；
+/// 自动选择网卡时以 `check` 的结果选择网卡，因此不能跳过检查
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+/// * `selection` - 使用的网卡
+/// * `check` - 连通性检查方式
+/// * `options` - 地址族、度量值、网关等路由参数
+///
+fn resolve_domain_targets(
+    domain: &str,
+    selection: &AdapterSelection,
+    check: Option<&Check>,
+    options: &TargetOptions,
+) -> Result<Vec<RouteTarget>, NetRouteError> {
+    // 解析域名的IP地址列表
+    let ip_list = parse_domain(domain, &options.family)?;
+    domain_targets(domain, &ip_list, selection, check, options)
+}
+
+/// 为域名解析得到的每个 IP 地址确定网卡与网关
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+/// * `ip_list` - 域名解析得到的 IP 地址列表
+/// * `selection` - 使用的网卡
+/// * `check` - 连通性检查方式
+/// * `options` - 地址族、度量值、网关等路由参数
+///
+fn domain_targets(
+    domain: &str,
+    ip_list: &[IpAddr],
+    selection: &AdapterSelection,
+    check: Option<&Check>,
+    options: &TargetOptions,
+) -> Result<Vec<RouteTarget>, NetRouteError> {
+    let mut target_list = vec![];
+    match selection {
+        AdapterSelection::Fixed(if_index) => {
+            let adapter = get_adapter_by_if_index(if_index)?;
+            // 逐个检查IP地址是否可达
+            if let Some(check) = check {
+                check_from_interface(ip_list, &adapter, check, Some(domain))?;
+            }
+            target_list.extend(options.targets_for(&adapter, ip_list, domain)?);
+        }
+        AdapterSelection::Auto { candidates, scope } => {
+            let check = check.ok_or_else(|| {
+                NetRouteError::new("自动选择网卡需要进行连通性测试，不能跳过检查".to_string())
+            })?;
+            let selected =
+                select::select_adapters(ip_list, candidates, scope, check, Some(domain))?;
+            for (ip, adapter) in selected {
+                target_list.extend(options.targets_for(&adapter, &[ip], domain)?);
+            }
+        }
+    }
+    Ok(target_list)
+}
+
+/// 修改已存在路由的度量值、网关或网卡
+///
+/// 替换过程中目标网段始终有路由可用，完成后展示修改前后的路由
+///
+/// # Arguments
+///
+/// * `destination` - 目标 IP 地址或 CIDR，如 `10.0.0.0/8`
+/// * `prefix` - 目标 IP 子网掩码，为空时使用 CIDR 中的掩码或单个主机地址
+/// * `if_index` - 需要修改的路由所在的网卡索引，为空时匹配所有网卡
+/// * `metric` - 新的路由度量值
+/// * `gateway` - 新的网关 IP 地址
+/// * `new_if_index` - 新的网卡索引
+/// * `host_bits` - 目标地址包含主机位时的处理方式
+///
+pub fn set_route(
+    destination: &str,
+    prefix: &Option<u8>,
+    if_index: &Option<u32>,
+    metric: &Option<u32>,
+    gateway: &Option<String>,
+    new_if_index: &Option<u32>,
+    host_bits: &HostBits,
+) -> Result<(), NetRouteError> {
+    if metric.is_none() && gateway.is_none() && new_if_index.is_none() {
+        return Err(NetRouteError::new(
+            "请至少指定 --metric、--gateway 或 --new-ifindex 中的一项".to_string(),
+        ));
+    }
+    // 解析目标地址并查询需要修改的路由
+    let cidr = parse_destination(destination, prefix, host_bits)?;
+    let backend = backend::new_backend()?;
+    let route_vec = backend.search_cidr_routes(&[cidr], if_index)?;
+    let current = match route_vec.as_slice() {
+        [] => {
+            return Err(NetRouteError::new(format!(
+                "路由表中没有找到匹配的路由: {}",
+                cidr
+            )));
+        }
+        [current] => current.clone(),
+        _ => {
+            println!("匹配到的路由:");
+            show_route_table(&route_vec);
+            return Err(NetRouteError::new(
+                "匹配到多条路由，请使用 --ifindex 指定需要修改的路由".to_string(),
+            ));
+        }
+    };
+    // 生成新路由
+    let mut route = current.clone();
+    if let Some(new_if_index) = new_if_index {
+        let adapter = get_adapter_by_if_index(new_if_index)?;
+        route.ifindex = Some(*new_if_index);
+        // LUID 与网卡对应，修改网卡后由系统重新确定
+        route.luid = None;
+        if gateway.is_none() {
+            route.gateway = Interface::get_gateway_for(&adapter, &cidr.address)?;
+        }
+    }
+    if let Some(gateway) = gateway {
+        let gateway: IpAddr = gateway
+            .parse()
+            .map_err(|_| NetRouteError::new(format!("Invalid gateway IP address: {}", gateway)))?;
+        if gateway.is_ipv4() != cidr.address.is_ipv4() {
+            return Err(NetRouteError::new(format!(
+                "网关地址 {} 与目标地址 {} 的地址族不一致",
+                gateway, cidr.address
+            )));
+        }
+        route.gateway = gateway;
+    }
+    if let Some(metric) = metric {
+        route.metric = Some(*metric);
+    }
+    if route.ifindex == current.ifindex
+        && route.gateway == current.gateway
+        && route.metric == current.metric
+    {
+        println!("路由没有变化，无需修改");
+        show_route_table(&[current]);
+        return Ok(());
+    }
+    // Windows 无法原地修改路由，只修改度量值时新旧路由无法同时存在
+    if cfg!(windows) && plan::same_record(&current, &route) {
+        println!(
+            "注意: Windows 不支持原地修改路由度量值，将先删除再添加，期间目标网段短暂没有路由"
+        );
+    }
+    auto_snapshot(backend.as_ref())?;
+    backend.replace_route(&current, &route)?;
+    state::replace_route(&current, &route)?;
+    // 从路由表中读取修改后的路由，展示系统实际生效的值
+    let after = backend
+        .search_route_by_ip(
+            &route.destination,
+            &route.prefix,
+            route.ifindex.as_ref(),
+            Some(&route.gateway),
+        )?
+        .into_iter()
+        .next()
+        .unwrap_or(route);
+    println!("路由修改成功！");
+    show_route_change(&current, &after);
+    Ok(())
+}
+
+/// 展示修改前后的路由
+///
+/// # Arguments
+///
+/// * `before` - 修改前的路由
+/// * `after` - 修改后的路由
+///
+fn show_route_change(before: &Route, after: &Route) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "",
+        "目标地址",
+        "prefix",
+        "网关地址",
+        "目标网卡",
+        "metric"
+    ]);
+    for (label, route) in [("修改前", before), ("修改后", after)] {
+        table.add_row(row![
+            label,
+            route.destination.to_string(),
+            route.prefix.to_string(),
+            route.gateway.to_string(),
+            route.ifindex.map_or("NONE".to_string(), |v| v.to_string()),
+            route.metric.map_or("NONE".to_string(), |v| v.to_string())
+        ]);
+    }
+    table.printstd();
+}
+
 /// 删除路由
 ///
 /// # Arguments
 ///
-/// * `destination` - 目标 IP 地址
-/// * `prefix` - 目标 IP 子网掩码
+/// * `destination` - 目标 IP 地址或 CIDR，如 `10.0.0.0/8`
+/// * `prefix` - 目标 IP 子网掩码，为空时使用 CIDR 中的掩码或单个主机地址
+/// * `if_index` - 网卡索引，为空时匹配所有网卡
+/// * `no_confirm` - 是否跳过确认
+/// * `host_bits` - 目标地址包含主机位时的处理方式
 ///
-pub fn remove_route(destination: &String, prefix: &u8) -> Result<(), NetRouteError> {
+pub fn remove_route(
+    destination: &str,
+    prefix: &Option<u8>,
+    if_index: &Option<u32>,
+    no_confirm: &bool,
+    host_bits: &HostBits,
+) -> Result<(), NetRouteError> {
     // 解析目标地址
-    let dest_ip: IpAddr = destination.parse().map_err(|_| {
-        NetRouteError::new(format!("Invalid destination IP address: {}", destination))
-    })?;
+    let cidr = parse_destination(destination, prefix, host_bits)?;
+    let dest_ip = cidr.address;
     // 查询路由表
-    let win_route = WinRoute::new()?;
-    let route_vec = win_route.search_route_by_ip(&dest_ip, prefix, None, None)?;
+    let backend = backend::new_backend()?;
+    let route_vec = backend.search_cidr_routes(&[cidr], if_index)?;
     if route_vec.is_empty() {
-        println!("路由表中没有找到匹配的路由: {}/{}", dest_ip, prefix);
+        println!("路由表中没有找到匹配的路由: {}", cidr);
         return Ok(());
     } else {
         println!("匹配到的路由:");
@@ -390,10 +763,17 @@ pub fn remove_route(destination: &String, prefix: &u8) -> Result<(), NetRouteErr
     // 如果匹配的路由不止一条，提示用户选择
     let mut num = 0;
     if route_vec.len() > 1 {
+        if *no_confirm {
+            return Err(NetRouteError::new(
+                "匹配到多条路由，请使用 --ifindex 指定需要删除的路由".to_string(),
+            ));
+        }
         num = user_input::user_select_num("请选择需要删除的路由序号", 0, route_vec.len())?;
     }
     // 如果找到匹配的路由，需要用户确认删除
-    user_input::user_check("是否删除匹配的路由？")?;
+    if !*no_confirm {
+        user_input::user_check("是否删除匹配的路由？")?;
+    }
     // 创建路由
     let route = match route_vec.get(num) {
         Some(route) => route,
@@ -404,92 +784,561 @@ pub fn remove_route(destination: &String, prefix: &u8) -> Result<(), NetRouteErr
             )));
         }
     };
-    win_route.remove_route(route)?;
+    auto_snapshot(backend.as_ref())?;
+    backend.remove_route(route)?;
+    state::forget_routes(std::slice::from_ref(route))?;
     // 显示路由表
     println!("路由移除成功！");
-    show_route_table(&vec![(*route).clone()]);
+    show_route_table(std::slice::from_ref(route));
     Ok(())
 }
 
 /// 删除域名路由
 ///
+/// 只删除状态文件中记录的由本工具为该域名添加的路由，不重新解析域名
+///
 /// # Arguments
 ///
 /// * `domain` - 域名
 /// * `if_index` - 网卡索引
+/// * `no_confirm` - 是否跳过确认
+/// * `family` - 需要删除路由的地址族，为空时删除所有地址族
 ///
-pub fn remove_domain_route(domain: &String, if_index: &Option<u32>) -> Result<(), NetRouteError> {
-    // 解析域名的IP地址列表
-    let ip_list = parse_domain(&domain)?;
-    // 获取路由信息
-    let win_route = WinRoute::new()?;
+pub fn remove_domain_route(
+    domain: &str,
+    if_index: &Option<u32>,
+    no_confirm: &bool,
+    family: &Option<Family>,
+) -> Result<(), NetRouteError> {
+    // 获取该域名添加的路由记录
+    let route_state = RouteState::load()?;
+    let managed_routes = route_state
+        .by_domain(domain)
+        .into_iter()
+        .map(|managed| managed.route)
+        .filter(|route| {
+            family.is_none_or(|family| family.matches(&route.destination))
+                && (if_index.is_none() || route.ifindex == *if_index)
+        })
+        .collect::<Vec<Route>>();
     // 查询路由表
-    let route_list = win_route.search_route_by_ip_vec(ip_list, &32, if_index, None)?;
+    let backend = backend::new_backend()?;
+    let (route_list, missing_list) = split_live_routes(backend.as_ref(), managed_routes)?;
+    if !missing_list.is_empty() {
+        println!("以下记录的路由已不在路由表中，清除记录:");
+        show_route_table(&missing_list);
+        state::forget_routes(&missing_list)?;
+    }
     if route_list.is_empty() {
-        println!("路由表中没有找到匹配的路由: {}", domain);
+        println!("没有找到由本工具为 {} 添加的路由", domain);
         return Ok(());
     } else {
         println!("匹配到的路由:");
         show_route_table(&route_list);
     }
-    user_input::user_check("是否删除所有匹配的路由？")?;
+    if !*no_confirm {
+        user_input::user_check("是否删除所有匹配的路由？")?;
+    }
+    auto_snapshot(backend.as_ref())?;
     // 删除路由
+    let mut removed_routes = vec![];
+    let mut result = Ok(());
     for route in route_list.iter() {
-        win_route.remove_route(route)?;
+        match backend.remove_route(route) {
+            Ok(()) => removed_routes.push(route.clone()),
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        }
     }
+    state::forget_routes(&removed_routes)?;
+    result?;
     // 显示路由表
     println!("路由移除成功！");
     show_route_table(&route_list);
     Ok(())
 }
 
-fn parse_config_to_repeat_and_add_routes(
-    win_route: &WinRoute,
-    route_config_data: RouteConfigData,
+/// 将记录的路由分为仍在路由表中的路由与已不存在的路由
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+/// * `managed_routes` - 记录的路由
+///
+fn split_live_routes(
+    backend: &dyn RouteBackend,
+    managed_routes: Vec<Route>,
 ) -> Result<(Vec<Route>, Vec<Route>), NetRouteError> {
-    let mut repeat_route_vec = vec![];
-    let mut add_route_list = vec![];
-    for route_config in route_config_data.routes {
-        let mut add_ip_addr_list = vec![];
+    let live_routes = backend.get_routes()?;
+    let mut route_list = vec![];
+    let mut missing_list = vec![];
+    for route in managed_routes {
+        match live_routes
+            .iter()
+            .find(|live| plan::same_record(live, &route))
+        {
+            Some(live) => route_list.push(live.clone()),
+            None => missing_list.push(route),
+        }
+    }
+    Ok((route_list, missing_list))
+}
+
+/// 需要添加路由的目标网段
+struct RouteTarget {
+    /// 网卡索引
+    if_index: u32,
+    /// 目标网段
+    cidr: Cidr,
+    /// 网关地址，直连路由为未指定地址
+    gateway: IpAddr,
+    /// 路由度量值
+    metric: u32,
+    /// 产生该网段的域名或配置项
+    source: String,
+    /// 产生该网段的配置文件，配置只由一个文件组成时为空
+    file: Option<String>,
+}
+
+/// 由目标地址生成路由时使用的参数
+#[derive(Debug, Clone, Default)]
+struct TargetOptions {
+    /// 域名解析结果需要添加路由的地址族
+    family: Family,
+    /// 路由度量值
+    metric: u32,
+    /// 网关地址，为空时使用网卡对应地址族的网关
+    gateway: Option<IpAddr>,
+    /// 目标地址所在网段的子网掩码长度，为空时为单个主机地址
+    prefix: Option<u8>,
+    /// 是否添加不经过网关的直连路由
+    on_link: bool,
+}
+
+impl TargetOptions {
+    /// 从补全默认值后的配置条目中读取路由参数
+    ///
+    /// # Arguments
+    ///
+    /// * `route_config` - 配置条目
+    ///
+    fn from_config(route_config: &RouteConfig) -> TargetOptions {
+        TargetOptions {
+            family: route_config.family.unwrap_or_default(),
+            metric: route_config.metric.unwrap_or(0),
+            gateway: route_config.gateway,
+            prefix: route_config.prefix,
+            on_link: route_config.on_link.unwrap_or(false),
+        }
+    }
+
+    /// 为目标地址生成经由网卡的路由目标
+    ///
+    /// 地址族为 both 时跳过网关地址族不一致或网卡缺少对应网关的地址，否则返回错误
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - 网卡信息
+    /// * `ip_list` - 目标 IP 地址列表
+    /// * `source` - 产生这些地址的域名
+    ///
+    fn targets_for(
+        &self,
+        adapter: &AdapterInfo,
+        ip_list: &[IpAddr],
+        source: &str,
+    ) -> Result<Vec<RouteTarget>, NetRouteError> {
+        let mut target_list = vec![];
+        for ip in ip_list {
+            let cidr = match self.prefix {
+                Some(prefix) => Cidr::parse(&ip.to_string(), Some(prefix), &HostBits::Normalize)?,
+                None => Cidr::host(*ip),
+            };
+            match self.gateway_for(adapter, &cidr) {
+                Ok(gateway) => target_list.push(RouteTarget {
+                    if_index: adapter.index,
+                    cidr,
+                    gateway,
+                    metric: self.metric,
+                    source: source.to_string(),
+                    file: None,
+                }),
+                Err(err) if self.family == Family::Both => {
+                    println!("跳过 {}: {}", cidr, err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(target_list)
+    }
+
+    /// 获取目标网段使用的网关，直连路由使用未指定地址
+    ///
+    /// # Arguments
+    ///
+    /// * `adapter` - 网卡信息
+    /// * `cidr` - 目标网段
+    ///
+    fn gateway_for(&self, adapter: &AdapterInfo, cidr: &Cidr) -> Result<IpAddr, NetRouteError> {
+        if self.on_link {
+            return Ok(match cidr.address {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            });
+        }
+        match self.gateway {
+            Some(gateway) if gateway.is_ipv4() != cidr.address.is_ipv4() => {
+                Err(NetRouteError::new(format!(
+                    "网关地址 {} 与目标地址 {} 的地址族不一致",
+                    gateway, cidr
+                )))
+            }
+            Some(gateway) => Ok(gateway),
+            None => Interface::get_gateway_for(adapter, &cidr.address),
+        }
+    }
+}
+
+/// 获取配置条目使用的网卡与连通性检查方式，自动选择网卡时未配置检查方式则使用 ICMP
+///
+/// # Arguments
+///
+/// * `route_config` - 补全默认值后的配置条目
+///
+fn config_selection(
+    route_config: &RouteConfig,
+) -> Result<(AdapterSelection, Option<Check>), NetRouteError> {
+    let selection = AdapterSelection::new(
+        route_config.ifindex.as_ref().unwrap_or(&IfIndex::Auto),
+        route_config.candidates.as_deref().unwrap_or_default(),
+        route_config.select_per.unwrap_or_default(),
+    )?;
+    let check = match (&selection, &route_config.check) {
+        (AdapterSelection::Auto { .. }, None) => Some(Check::Icmp),
+        (_, check) => check.clone(),
+    };
+    Ok((selection, check))
+}
+
+/// 解析配置文件中每个网卡需要添加路由的目标网段
+///
+/// # Arguments
+///
+/// * `route_config_data` - 配置文件内容
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+///
+fn collect_config_targets(
+    route_config_data: RouteConfigData,
+    host_bits: &HostBits,
+) -> Result<Vec<RouteTarget>, NetRouteError> {
+    let mut target_list = vec![];
+    for route_config in route_config_data.resolved_routes()? {
+        let start = target_list.len();
+        let (selection, check) = config_selection(&route_config)?;
+        let options = TargetOptions::from_config(&route_config);
         // 解析域名的IP地址列表
-        for domain in route_config.domains {
-            // 解析域名的IP地址列表
-            let parsed_ip_list = parse_domain(&domain)?;
-            add_ip_addr_list.extend(parsed_ip_list.clone());
-            // 查询路由表是否存在重复的路由
-            repeat_route_vec.extend(win_route.search_route_by_ip_vec(
-                parsed_ip_list,
-                &32,
-                &None,
-                None,
+        for domain in route_config.domains.iter() {
+            target_list.extend(resolve_domain_targets(
+                domain,
+                &selection,
+                check.as_ref(),
+                &options,
             )?);
         }
-        let ip_addr_vec = route_config
-            .ips
-            .iter()
-            .map(|ip_str| {
-                ip_str.parse::<IpAddr>().map_err(|_| {
-                    NetRouteError::new(format!("Invalid destination IP address: {}", ip_str))
-                })
-            })
-            .collect::<Result<Vec<IpAddr>, NetRouteError>>()?;
-        add_ip_addr_list.extend(ip_addr_vec.clone());
-        let route_vec = win_route.search_route_by_ip_vec(ip_addr_vec, &32, &None, None)?;
-        repeat_route_vec.extend(route_vec);
-
-        // 生成路由
-        let if_index = route_config.ifindex;
-        // 获取ifindex
-        let gateway_ip = get_gateway_ip_by_if_index(&if_index)?;
-        for add_ip_addr in add_ip_addr_list {
-            let mut route = Route::new(add_ip_addr, 32);
-            route = route.ifindex(if_index);
-            route = route.gateway(gateway_ip);
-            route = route.metric(0);
-            add_route_list.push(route);
-        }
-    }
-    Ok((repeat_route_vec, add_route_list))
+        // 直接配置的 IP 地址或 CIDR，未指定掩码时使用配置的掩码或单个主机地址
+        for ip_str in route_config.ips.iter() {
+            let prefix = if ip_str.contains('/') {
+                None
+            } else {
+                route_config.prefix
+            };
+            let cidr = parse_destination(ip_str, &prefix, host_bits)?;
+            let is_host = cidr == Cidr::host(cidr.address);
+            let adapter = match &selection {
+                AdapterSelection::Fixed(if_index) => {
+                    let adapter = get_adapter_by_if_index(if_index)?;
+                    // 只检查单个主机地址，网段没有确定的检查目标
+                    if let Some(check) = &check
+                        && is_host
+                    {
+                        check_from_interface(&[cidr.address], &adapter, check, None)?;
+                    }
+                    adapter
+                }
+                AdapterSelection::Auto { candidates, .. } => {
+                    if !is_host {
+                        return Err(NetRouteError::new(format!(
+                            "网段 {} 没有确定的检查目标，不能自动选择网卡",
+                            cidr
+                        )));
+                    }
+                    let check = check.as_ref().unwrap_or(&Check::Icmp);
+                    select::select_adapters(
+                        &[cidr.address],
+                        candidates,
+                        &SelectScope::Ip,
+                        check,
+                        None,
+                    )?
+                    .remove(0)
+                    .1
+                }
+            };
+            // 直接配置的 IP 地址不受地址族过滤
+            target_list.push(RouteTarget {
+                if_index: adapter.index,
+                cidr,
+                gateway: options.gateway_for(&adapter, &cidr)?,
+                metric: options.metric,
+                source: ip_str.clone(),
+                file: None,
+            });
+        }
+        for target in target_list[start..].iter_mut() {
+            target.file = route_config.origin.file.clone();
+        }
+    }
+    Ok(target_list)
+}
+
+/// 将绑定到相同网卡与网关的网段聚合为最少的 CIDR 集合，并输出减少的路由数量
+///
+/// 聚合后网段的来源为其包含的所有网段的来源
+///
+/// # Arguments
+///
+/// * `target_list` - 目标网段列表
+///
+fn aggregate_targets(target_list: Vec<RouteTarget>) -> Vec<RouteTarget> {
+    let before = target_list.len();
+    let mut groups: BTreeMap<(u32, IpAddr, u32), Vec<RouteTarget>> = BTreeMap::new();
+    for target in target_list {
+        groups
+            .entry((target.if_index, target.gateway, target.metric))
+            .or_default()
+            .push(target);
+    }
+    let mut aggregated = vec![];
+    for ((if_index, gateway, metric), members) in groups {
+        let cidr_vec = members.iter().map(|member| member.cidr).collect();
+        for cidr in cidr::aggregate(cidr_vec) {
+            let mut sources = vec![];
+            let mut files = vec![];
+            for member in members.iter() {
+                if !cidr::contains(&cidr.address, cidr.prefix, &member.cidr.address) {
+                    continue;
+                }
+                if !sources.contains(&member.source) {
+                    sources.push(member.source.clone());
+                }
+                if let Some(file) = &member.file
+                    && !files.contains(file)
+                {
+                    files.push(file.clone());
+                }
+            }
+            aggregated.push(RouteTarget {
+                if_index,
+                cidr,
+                gateway,
+                metric,
+                source: sources.join(", "),
+                file: (!files.is_empty()).then(|| files.join(", ")),
+            });
+        }
+    }
+    println!(
+        "路由聚合: {} 条路由合并为 {} 条，减少 {} 条",
+        before,
+        aggregated.len(),
+        before - aggregated.len()
+    );
+    aggregated
+}
+
+/// 读取配置文件并对比路由表生成执行计划
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+/// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
+/// * `cancel` - 是否只移除配置文件对应的路由
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `aggregate` - 是否将配置的路由聚合为最少的 CIDR 集合
+///
+fn build_config_plan(
+    backend: &dyn RouteBackend,
+    config_path: &Option<String>,
+    config_format: &Option<ConfigFormat>,
+    cancel: &bool,
+    host_bits: &HostBits,
+    aggregate: &bool,
+) -> Result<RoutePlan, NetRouteError> {
+    let path = config_path
+        .as_ref()
+        .ok_or_else(|| NetRouteError::new("配置文件路径不能为空".to_string()))?;
+    let canonical_path = state::canonical_config_path(path);
+    let live_routes = backend.get_routes()?;
+    let managed_routes = RouteState::load()?.by_config(&canonical_path);
+    if *cancel {
+        // 取消时只删除状态文件中记录的由该配置文件添加的路由，不重新解析域名
+        let mut plan = RoutePlan::new(
+            &canonical_path,
+            &live_routes,
+            Some(&managed_routes),
+            &[],
+            &[],
+        );
+        plan.retire_managed(&live_routes, &managed_routes);
+        return Ok(plan);
+    }
+    let config = include::load_config(path, config_format)?;
+
+    let target_list = collect_config_targets(config, host_bits)?;
+    // 聚合前的网段，只删除不添加
+    let retired = target_list
+        .iter()
+        .map(|target| (target.cidr, target.source.clone()))
+        .collect::<Vec<(Cidr, String)>>();
+    let target_list = if *aggregate {
+        aggregate_targets(target_list)
+    } else {
+        target_list
+    };
+    let desired_routes = target_list
+        .into_iter()
+        .map(|target| DesiredRoute {
+            route: Route::new(target.cidr.address, target.cidr.prefix)
+                .ifindex(target.if_index)
+                .gateway(target.gateway)
+                .metric(target.metric),
+            source: target.source,
+            file: target.file,
+        })
+        .collect::<Vec<DesiredRoute>>();
+    let mut plan = RoutePlan::new(
+        &canonical_path,
+        &live_routes,
+        Some(&managed_routes),
+        &desired_routes,
+        &retired,
+    );
+    // 由该配置文件添加但已不在配置中的路由，如域名解析结果发生了变化
+    plan.retire_managed(&live_routes, &managed_routes);
+    if backend.exclusive_metric() {
+        plan.mark_conflicts(&live_routes);
+    }
+    Ok(plan)
+}
+
+/// 按计划修改路由表
+///
+/// 所有修改在同一个事务中执行，任意一步失败或用户按下 Ctrl-C 时，
+/// 撤销已执行的修改，使路由表恢复到执行前的状态
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+/// * `plan` - 执行计划
+///
+fn execute_plan(backend: &dyn RouteBackend, plan: &RoutePlan) -> Result<(), NetRouteError> {
+    auto_snapshot(backend)?;
+    let mut transaction = Transaction::begin(backend);
+    // 最后一步操作执行期间收到的中断由提交时检查，同样回滚
+    match execute_plan_entries(&mut transaction, plan).and_then(|()| transaction.commit()) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            println!("执行计划失败: {}，开始回滚已执行的修改", err);
+            transaction.rollback()?;
+            Err(NetRouteError::new(format!(
+                "执行计划失败，路由表已恢复到执行前的状态: {}",
+                err
+            )))
+        }
+    }
+}
+
+/// 计划执行成功后更新路由状态，记录添加的路由并清除删除的路由
+///
+/// # Arguments
+///
+/// * `plan` - 执行计划
+///
+fn record_plan_state(plan: &RoutePlan) -> Result<(), NetRouteError> {
+    let mut route_state = RouteState::load()?;
+    for entry in plan.entries.iter() {
+        if matches!(entry.action, PlanAction::Remove | PlanAction::Modify)
+            && let Some(route) = &entry.current
+        {
+            route_state.forget(route);
+        }
+        if matches!(entry.action, PlanAction::Add | PlanAction::Modify)
+            && let Some(route) = &entry.desired
+        {
+            route_state.record(route, RouteSource::config(&plan.config_path, &entry.source));
+        }
+    }
+    route_state
+        .save()
+        .map_err(|e| NetRouteError::new(format!("路由已修改，但保存路由状态失败: {}", e.message)))
+}
+
+/// 在事务中依次执行计划中的路由变更
+///
+/// # Arguments
+///
+/// * `transaction` - 路由表修改事务
+/// * `plan` - 执行计划
+///
+fn execute_plan_entries(
+    transaction: &mut Transaction,
+    plan: &RoutePlan,
+) -> Result<(), NetRouteError> {
+    for entry in plan.entries.iter() {
+        if matches!(entry.action, PlanAction::Remove | PlanAction::Modify)
+            && let Some(route) = &entry.current
+        {
+            println!("移除路由: {}/{}", route.destination, route.prefix);
+            transaction.remove_route(route)?;
+        }
+        if matches!(entry.action, PlanAction::Add | PlanAction::Modify)
+            && let Some(route) = &entry.desired
+        {
+            println!("添加路由: {}/{}", route.destination, route.prefix);
+            transaction.add_route(route)?;
+        }
+    }
+    Ok(())
+}
+
+/// 展示并确认执行计划后修改路由表
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+/// * `plan` - 执行计划
+/// * `no_confirm` - 是否跳过确认
+///
+fn confirm_and_execute_plan(
+    backend: &dyn RouteBackend,
+    plan: &RoutePlan,
+    no_confirm: &bool,
+) -> Result<(), NetRouteError> {
+    plan::show_plan(plan);
+    if !plan.has_changes() {
+        println!("路由表已与配置文件一致，无需修改！");
+        return Ok(());
+    }
+    if !*no_confirm {
+        user_input::user_check("是否按以上计划修改路由表？")?;
+    }
+    execute_plan(backend, plan)?;
+    record_plan_state(plan)?;
+    println!("路由表已按计划修改！");
+    Ok(())
 }
 
 /// 应用配置文件
@@ -497,53 +1346,336 @@ fn parse_config_to_repeat_and_add_routes(
 /// # Arguments
 ///
 /// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
 /// * `no_confirm` - 是否跳过确认
+/// * `cancel` - 是否只移除配置文件对应的路由
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `aggregate` - 是否将配置的路由聚合为最少的 CIDR 集合
 ///
 pub fn apply_config_file(
     config_path: &Option<String>,
+    config_format: &Option<ConfigFormat>,
     no_confirm: &bool,
     cancel: &bool,
+    host_bits: &HostBits,
+    aggregate: &bool,
+) -> Result<(), NetRouteError> {
+    let backend = backend::new_backend()?;
+    let plan = build_config_plan(
+        backend.as_ref(),
+        config_path,
+        config_format,
+        cancel,
+        host_bits,
+        aggregate,
+    )?;
+    confirm_and_execute_plan(backend.as_ref(), &plan, no_confirm)?;
+    if *cancel {
+        println!("已取消应用此配置文件！");
+    }
+    Ok(())
+}
+
+/// 以守护进程方式运行配置文件
+///
+/// 应用配置文件后按域名解析结果的 TTL 重新解析每个域名，为新地址添加路由，
+/// 地址从解析结果中消失超过保留时间后删除其路由，直到收到中断或终止信号
+///
+/// # Arguments
+///
+/// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `options` - 重新解析间隔、保留时间等运行参数
+///
+pub fn run_daemon(
+    config_path: &str,
+    config_format: &Option<ConfigFormat>,
+    host_bits: &HostBits,
+    options: &DaemonOptions,
+) -> Result<(), NetRouteError> {
+    transaction::watch_shutdown();
+    let mut daemon = Daemon::start(config_path, config_format, host_bits, options.clone())?;
+    while !transaction::shutdown_requested() {
+        daemon.refresh_due(state::now());
+        // 每秒检查一次是否收到退出请求
+        if daemon
+            .next_refresh()
+            .is_none_or(|next_refresh| next_refresh > state::now())
+        {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    }
+    daemon.shutdown()
+}
+
+/// 生成配置文件的执行计划，不修改路由表
+///
+/// # Arguments
+///
+/// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
+/// * `cancel` - 是否只移除配置文件对应的路由
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `aggregate` - 是否将配置的路由聚合为最少的 CIDR 集合
+/// * `out` - 计划文件保存路径，为空时只展示计划
+///
+pub fn plan_config_file(
+    config_path: &Option<String>,
+    config_format: &Option<ConfigFormat>,
+    cancel: &bool,
+    host_bits: &HostBits,
+    aggregate: &bool,
+    out: &Option<String>,
+) -> Result<(), NetRouteError> {
+    let backend = backend::new_backend()?;
+    let plan = build_config_plan(
+        backend.as_ref(),
+        config_path,
+        config_format,
+        cancel,
+        host_bits,
+        aggregate,
+    )?;
+    plan::show_plan(&plan);
+    if let Some(out) = out {
+        plan.save(out)?;
+        println!(
+            "计划已保存到 {}，使用 config apply --plan {} 执行",
+            out, out
+        );
+    }
+    Ok(())
+}
+
+/// 将配置文件转换为其他格式
+///
+/// # Arguments
+///
+/// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
+/// * `to` - 目标格式
+/// * `out` - 转换结果保存路径，为空时输出到标准输出
+///
+pub fn convert_config_file(
+    config_path: &Option<String>,
+    config_format: &Option<ConfigFormat>,
+    to: &ConfigFormat,
+    out: &Option<String>,
 ) -> Result<(), NetRouteError> {
     let path = config_path
         .as_ref()
         .ok_or_else(|| NetRouteError::new("配置文件路径不能为空".to_string()))?;
-    let file_content = files::read_file_content(path)?;
-    let config = config::parse_config_file(&file_content)?;
-
-    let win_route = WinRoute::new()?;
-    let (repeat_route_vec, add_route_list) =
-        parse_config_to_repeat_and_add_routes(&win_route, config)?;
-    if !repeat_route_vec.is_empty() {
-        println!("路由表中已存在匹配的路由！");
-        show_route_table(&repeat_route_vec);
-        if !*no_confirm {
-            user_input::user_check("是否移除已存在的路由？")?;
+    let content = to.serialize(&config::load_config_file(path, config_format)?)?;
+    match out {
+        Some(out) => {
+            files::write_file_content(out, &content)?;
+            println!("已将配置文件 {} 转换为 {} 格式并保存到 {}", path, to, out);
         }
-        // 移除重复的路由
-        for route in repeat_route_vec.iter() {
-            println!("移除路由: {}", route.destination);
-            win_route.remove_route(route)?;
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// 检查配置文件，一次报告所有问题，存在问题时返回错误
+///
+/// # Arguments
+///
+/// * `config_path` - 配置文件路径
+/// * `config_format` - 配置文件格式，为空时根据扩展名判断
+/// * `host_bits` - 配置的 CIDR 包含主机位时的处理方式
+/// * `resolve` - 是否解析域名
+///
+pub fn validate_config_file(
+    config_path: &Option<String>,
+    config_format: &Option<ConfigFormat>,
+    host_bits: &HostBits,
+    resolve: &bool,
+) -> Result<(), NetRouteError> {
+    let path = config_path
+        .as_ref()
+        .ok_or_else(|| NetRouteError::new("配置文件路径不能为空".to_string()))?;
+    // 不同文件之间的冲突与其他问题一起报告，不中断检查
+    let (config, mut issues) = include::load_config_with_conflicts(path, config_format)?;
+    let adapters = Interface::new().get_interfaces()?;
+    issues.extend(validate::validate_config(
+        &config, &adapters, host_bits, *resolve,
+    ));
+    if issues.is_empty() {
+        println!("配置文件 {} 检查通过", path);
+        return Ok(());
+    }
+    for issue in issues.iter() {
+        println!("{}", issue);
+    }
+    Err(NetRouteError::new(format!(
+        "配置文件 {} 存在 {} 个问题",
+        path,
+        issues.len()
+    )))
+}
+
+/// 执行已保存的计划，路由表在生成计划后发生变化时拒绝执行
+///
+/// # Arguments
+///
+/// * `plan_path` - 计划文件路径
+/// * `no_confirm` - 是否跳过确认
+///
+pub fn apply_plan_file(plan_path: &str, no_confirm: &bool) -> Result<(), NetRouteError> {
+    let plan = RoutePlan::load(plan_path)?;
+    let backend = backend::new_backend()?;
+    let drift = plan.detect_drift(&backend.get_routes()?);
+    if !drift.is_empty() {
+        println!("路由表在生成计划后发生了变化:");
+        for line in drift.iter() {
+            println!("  {}", line);
         }
-        println!("已移除重复的路由！");
+        return Err(NetRouteError::new(format!(
+            "计划 {} 已过期，请重新执行 config plan",
+            plan_path
+        )));
     }
-    if *cancel {
-        println!("已取消应用此配置文件！");
+    confirm_and_execute_plan(backend.as_ref(), &plan, no_confirm)
+}
+
+/// 修改路由表前保存自动快照
+///
+/// 模拟模式或保留数量为 0 时不保存
+///
+/// # Arguments
+///
+/// * `backend` - 路由表后端
+///
+fn auto_snapshot(backend: &dyn RouteBackend) -> Result<(), NetRouteError> {
+    let keep = snapshot::keep();
+    if keep == 0 || simulate::is_enabled() {
         return Ok(());
     }
-    println!("需要添加的路由:");
-    show_route_table(&add_route_list);
-    if !*no_confirm {
-        user_input::user_check("是否继续添加路由？")?;
+    let path =
+        snapshot::save_auto_snapshot(&snapshot::auto_snapshot_dir(), backend.get_routes()?, keep)
+            .map_err(|e| {
+            NetRouteError::new(format!(
+                "修改路由表前保存快照失败: {}，可使用 --snapshot-keep 0 跳过",
+                e.message
+            ))
+        })?;
+    println!(
+        "已保存路由表快照，可使用 route restore {} 撤销本次修改",
+        path.display()
+    );
+    Ok(())
+}
+
+/// 备份路由表
+///
+/// # Arguments
+///
+/// * `out` - 快照文件保存路径
+///
+pub fn backup_route_table(out: &str) -> Result<(), NetRouteError> {
+    let backend = backend::new_backend()?;
+    let routes = backend.get_routes()?;
+    let count = routes.len();
+    RouteSnapshot::new(routes).save(out)?;
+    println!("已备份 {} 条路由到 {}", count, out);
+    Ok(())
+}
+
+/// 将路由表恢复到快照时的状态
+///
+/// 只删除快照中不存在的路由，只添加路由表中缺少的路由
+///
+/// # Arguments
+///
+/// * `path` - 快照文件路径
+/// * `latest` - 是否使用最近一次自动快照
+/// * `no_confirm` - 是否跳过确认
+///
+pub fn restore_route_table(
+    path: &Option<String>,
+    latest: &bool,
+    no_confirm: &bool,
+) -> Result<(), NetRouteError> {
+    let path = match (path, latest) {
+        (Some(path), _) => path.clone(),
+        (None, true) => snapshot::list_auto_snapshots(&snapshot::auto_snapshot_dir())
+            .pop()
+            .map(|path| path.to_string_lossy().to_string())
+            .ok_or_else(|| NetRouteError::new("没有可用的自动快照".to_string()))?,
+        (None, false) => {
+            return Err(NetRouteError::new(
+                "请指定快照文件路径或使用 --latest".to_string(),
+            ));
+        }
+    };
+    let route_snapshot = RouteSnapshot::load(&path)?;
+    let backend = backend::new_backend()?;
+    let live_routes = backend.get_routes()?;
+    let desired_routes = route_snapshot
+        .routes
+        .iter()
+        .map(|route| DesiredRoute {
+            route: route.clone(),
+            source: "快照".to_string(),
+            file: None,
+        })
+        .collect::<Vec<DesiredRoute>>();
+    // 快照中不存在的网段只删除不添加
+    let retired = live_routes
+        .iter()
+        .map(|route| {
+            (
+                Cidr {
+                    address: route.destination,
+                    prefix: route.prefix,
+                },
+                "快照中不存在".to_string(),
+            )
+        })
+        .collect::<Vec<(Cidr, String)>>();
+    // 恢复快照时路由表中的所有路由都以快照为准
+    let plan = RoutePlan::new(&path, &live_routes, None, &desired_routes, &retired);
+    plan::show_plan(&plan);
+    if !plan.has_changes() {
+        println!("路由表已与快照一致，无需恢复！");
+        return Ok(());
     }
-    // 添加路由
-    for route in add_route_list.iter() {
-        println!("添加路由: {}", route.destination);
-        win_route.add_route(route)?;
+    if !*no_confirm {
+        user_input::user_check("是否按以上计划恢复路由表？")?;
     }
-    println!("路由添加成功！");
+    execute_plan(backend.as_ref(), &plan)?;
+    // 删除的路由不再由本工具管理
+    let removed_routes = plan
+        .entries
+        .iter()
+        .filter(|entry| matches!(entry.action, PlanAction::Remove | PlanAction::Modify))
+        .filter_map(|entry| entry.current.clone())
+        .collect::<Vec<Route>>();
+    state::forget_routes(&removed_routes)?;
+    println!("路由表已恢复到快照 {} 时的状态！", path);
     Ok(())
 }
 
-mod config;
+pub mod backend;
+pub mod cidr;
+pub mod config;
+pub mod daemon;
+pub mod dns;
+pub mod family;
+pub mod filter;
+mod lookup;
+#[cfg(target_os = "linux")]
+pub mod netlink;
+pub mod plan;
+#[cfg(target_os = "linux")]
+pub mod procfs;
+pub mod select;
+pub mod simulate;
+pub mod snapshot;
+pub mod state;
 #[cfg(test)]
 mod tests;
+pub mod transaction;
+#[cfg(windows)]
+pub mod windows;
@@ -0,0 +1,420 @@
+use crate::base::NetRouteError;
+use crate::route::backend::RouteBackend;
+use crate::route::plan::same_record;
+use std::cell::Cell;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use winroute::Route;
+
+/// netlink 消息头长度
+const NLMSG_HDR_LEN: usize = 16;
+/// rtmsg 结构体长度
+const RTMSG_LEN: usize = 12;
+/// rtattr 头长度
+const RTA_HDR_LEN: usize = 4;
+/// 接收缓冲区大小
+const RECV_BUF_LEN: usize = 64 * 1024;
+
+/// 基于 rtnetlink 的 Linux 路由表后端
+///
+/// 直接通过 NETLINK_ROUTE 套接字发送 RTM_GETROUTE / RTM_NEWROUTE / RTM_DELROUTE 消息，
+/// 只读写 main 路由表。
+pub struct NetlinkRoute {
+    fd: OwnedFd,
+    seq: Cell<u32>,
+}
+
+impl NetlinkRoute {
+    pub fn new() -> Result<NetlinkRoute, NetRouteError> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(NetRouteError::new(format!(
+                "创建 netlink 套接字失败: {}",
+                io::Error::last_os_error()
+            )));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(NetRouteError::new(format!(
+                "绑定 netlink 套接字失败: {}",
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(NetlinkRoute {
+            fd,
+            seq: Cell::new(1),
+        })
+    }
+
+    /// 发送一条 netlink 请求并读取全部响应消息
+    ///
+    /// 返回值为所有数据消息的 (消息类型, 消息体)，ACK 与 DONE 消息不会出现在结果中。
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_type` - 消息类型
+    /// * `flags` - 消息标志位，NLM_F_REQUEST 会自动加上
+    /// * `payload` - 消息体
+    ///
+    fn request(
+        &self,
+        msg_type: u16,
+        flags: u16,
+        payload: &[u8],
+    ) -> io::Result<Vec<(u16, Vec<u8>)>> {
+        let seq = self.seq.get();
+        self.seq.set(seq.wrapping_add(1));
+
+        let mut message = Vec::with_capacity(NLMSG_HDR_LEN + payload.len());
+        message.extend_from_slice(&((NLMSG_HDR_LEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&msg_type.to_ne_bytes());
+        message.extend_from_slice(&(flags | libc::NLM_F_REQUEST as u16).to_ne_bytes());
+        message.extend_from_slice(&seq.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(payload);
+
+        let sent = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let is_dump = flags & libc::NLM_F_DUMP as u16 != 0;
+        let mut responses = vec![];
+        let mut buf = vec![0u8; RECV_BUF_LEN];
+        loop {
+            let received = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            let mut data = &buf[..received as usize];
+            while data.len() >= NLMSG_HDR_LEN {
+                let len = read_u32(data, 0) as usize;
+                if len < NLMSG_HDR_LEN || len > data.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "netlink 消息长度错误",
+                    ));
+                }
+                let kind = read_u16(data, 4);
+                let msg_seq = read_u32(data, 8);
+                let body = &data[NLMSG_HDR_LEN..len];
+                data = &data[align(len).min(data.len())..];
+                // 忽略其他请求的消息
+                if msg_seq != seq {
+                    continue;
+                }
+                match kind as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(responses),
+                    libc::NLMSG_ERROR => {
+                        let code = if body.len() >= 4 {
+                            i32::from_ne_bytes([body[0], body[1], body[2], body[3]])
+                        } else {
+                            -libc::EIO
+                        };
+                        if code == 0 {
+                            return Ok(responses);
+                        }
+                        return Err(io::Error::from_raw_os_error(-code));
+                    }
+                    libc::NLMSG_NOOP => {}
+                    _ => {
+                        responses.push((kind, body.to_vec()));
+                        if !is_dump {
+                            return Ok(responses);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 先添加新路由再删除旧路由，使目标网段在替换过程中始终有路由可用，删除失败时撤销新路由
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - 已存在的路由
+    /// * `route` - 新路由
+    /// * `flag` - 添加新路由时使用的 NLM_F_EXCL 或 NLM_F_APPEND
+    ///
+    fn add_then_remove(
+        &self,
+        current: &Route,
+        route: &Route,
+        flag: i32,
+    ) -> Result<(), NetRouteError> {
+        self.request(
+            libc::RTM_NEWROUTE,
+            (libc::NLM_F_ACK | libc::NLM_F_CREATE | flag) as u16,
+            &build_route_message(route, false),
+        )
+        .map_err(|err| NetRouteError::new(format!("替换路由错误: {}", err)))?;
+        if let Err(err) = self.remove_route(current) {
+            self.remove_route(route).map_err(|restore_err| {
+                NetRouteError::new(format!(
+                    "{}，且撤销新路由失败: {}",
+                    err.message, restore_err.message
+                ))
+            })?;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl RouteBackend for NetlinkRoute {
+    fn exclusive_metric(&self) -> bool {
+        true
+    }
+
+    fn get_routes(&self) -> Result<Vec<Route>, NetRouteError> {
+        let mut payload = vec![0u8; RTMSG_LEN];
+        payload[0] = libc::AF_UNSPEC as u8;
+        let responses = self
+            .request(libc::RTM_GETROUTE, libc::NLM_F_DUMP as u16, &payload)
+            .map_err(|e| NetRouteError::new(format!("获取路由表错误: {}", e)))?;
+        Ok(responses
+            .into_iter()
+            .filter(|(kind, _)| *kind == libc::RTM_NEWROUTE)
+            .filter_map(|(_, body)| parse_route_message(&body))
+            .collect())
+    }
+
+    fn add_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        let payload = build_route_message(route, false);
+        self.request(
+            libc::RTM_NEWROUTE,
+            (libc::NLM_F_ACK | libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16,
+            &payload,
+        )
+        .map_err(|err| NetRouteError::new(format!("添加路由错误: {}", err)))?;
+        Ok(())
+    }
+
+    /// 内核按目标网段与度量值定位路由，度量值不变且 `current` 是唯一匹配的路由时
+    /// 使用 NLM_F_REPLACE 原子替换；度量值变化时新旧路由可以同时存在，先添加新路由再删除旧路由，
+    /// 存在多条目标网段与度量值都相同的路由时追加新路由后再删除旧路由
+    fn replace_route(&self, current: &Route, route: &Route) -> Result<(), NetRouteError> {
+        if current.metric != route.metric {
+            return self.add_then_remove(current, route, libc::NLM_F_EXCL);
+        }
+        let shared = self
+            .get_routes()?
+            .iter()
+            .filter(|live| {
+                live.destination == current.destination
+                    && live.prefix == current.prefix
+                    && live.metric == current.metric
+            })
+            .count()
+            > 1;
+        // NLM_F_REPLACE 可能替换掉其他网卡上的路由，而 NLM_F_EXCL 不允许添加
+        // 目标网段与度量值相同的路由，只能以 NLM_F_APPEND 追加新路由
+        if shared {
+            // 新旧路由的网卡、网关与度量值都相同，无需替换
+            if same_record(current, route) {
+                return Ok(());
+            }
+            return self.add_then_remove(current, route, libc::NLM_F_APPEND);
+        }
+        let payload = build_route_message(route, false);
+        self.request(
+            libc::RTM_NEWROUTE,
+            (libc::NLM_F_ACK | libc::NLM_F_CREATE | libc::NLM_F_REPLACE) as u16,
+            &payload,
+        )
+        .map_err(|err| NetRouteError::new(format!("替换路由错误: {}", err)))?;
+        Ok(())
+    }
+
+    fn remove_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        let payload = build_route_message(route, true);
+        self.request(libc::RTM_DELROUTE, libc::NLM_F_ACK as u16, &payload)
+            .map_err(|err| NetRouteError::new(format!("删除路由错误: {}", err)))?;
+        Ok(())
+    }
+}
+
+/// 构造 RTM_NEWROUTE / RTM_DELROUTE 消息体
+///
+/// # Arguments
+///
+/// * `route` - 路由
+/// * `delete` - 是否为删除消息，删除时不限定协议、作用域与类型
+///
+fn build_route_message(route: &Route, delete: bool) -> Vec<u8> {
+    let family = match route.destination {
+        IpAddr::V4(_) => libc::AF_INET,
+        IpAddr::V6(_) => libc::AF_INET6,
+    };
+    let has_gateway = !route.gateway.is_unspecified();
+    let mut payload = vec![0u8; RTMSG_LEN];
+    payload[0] = family as u8;
+    payload[1] = route.prefix;
+    payload[4] = libc::RT_TABLE_MAIN;
+    if delete {
+        payload[6] = libc::RT_SCOPE_NOWHERE;
+    } else {
+        payload[5] = libc::RTPROT_STATIC;
+        // 没有网关的 IPv4 路由为直连路由
+        payload[6] = if has_gateway || family == libc::AF_INET6 {
+            libc::RT_SCOPE_UNIVERSE
+        } else {
+            libc::RT_SCOPE_LINK
+        };
+        payload[7] = libc::RTN_UNICAST;
+    }
+    push_attr(&mut payload, libc::RTA_DST, &ip_octets(&route.destination));
+    if has_gateway {
+        push_attr(&mut payload, libc::RTA_GATEWAY, &ip_octets(&route.gateway));
+    }
+    if let Some(ifindex) = route.ifindex {
+        push_attr(&mut payload, libc::RTA_OIF, &ifindex.to_ne_bytes());
+    }
+    if let Some(metric) = route.metric {
+        push_attr(&mut payload, libc::RTA_PRIORITY, &metric.to_ne_bytes());
+    }
+    payload
+}
+
+/// 解析 RTM_NEWROUTE 消息体
+///
+/// 只保留 main 路由表中的非缓存路由，其他消息返回 None
+///
+/// # Arguments
+///
+/// * `body` - 消息体
+///
+fn parse_route_message(body: &[u8]) -> Option<Route> {
+    if body.len() < RTMSG_LEN {
+        return None;
+    }
+    let family = body[0] as libc::c_int;
+    let prefix = body[1];
+    let mut table = body[4] as u32;
+    let flags = read_u32(body, 8);
+    if flags & libc::RTM_F_CLONED != 0 {
+        return None;
+    }
+    let mut destination = None;
+    let mut gateway = None;
+    let mut ifindex = None;
+    let mut metric = None;
+    let mut attrs = &body[RTMSG_LEN..];
+    while attrs.len() >= RTA_HDR_LEN {
+        let len = read_u16(attrs, 0) as usize;
+        if len < RTA_HDR_LEN || len > attrs.len() {
+            break;
+        }
+        let kind = read_u16(attrs, 2);
+        let data = &attrs[RTA_HDR_LEN..len];
+        match kind {
+            libc::RTA_DST => destination = parse_ip(family, data),
+            libc::RTA_GATEWAY => gateway = parse_ip(family, data),
+            libc::RTA_OIF if data.len() >= 4 => ifindex = Some(read_u32(data, 0)),
+            libc::RTA_PRIORITY if data.len() >= 4 => metric = Some(read_u32(data, 0)),
+            libc::RTA_TABLE if data.len() >= 4 => table = read_u32(data, 0),
+            _ => {}
+        }
+        attrs = &attrs[align(len).min(attrs.len())..];
+    }
+    if table != libc::RT_TABLE_MAIN as u32 {
+        return None;
+    }
+    let unspecified = match family {
+        libc::AF_INET => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        libc::AF_INET6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        _ => return None,
+    };
+    let mut route = Route::new(destination.unwrap_or(unspecified), prefix);
+    if let Some(gateway) = gateway {
+        route = route.gateway(gateway);
+    }
+    if let Some(ifindex) = ifindex {
+        route = route.ifindex(ifindex);
+    }
+    // 内核中未设置 priority 的路由度量值为 0
+    route = route.metric(metric.unwrap_or(0));
+    Some(route)
+}
+
+/// 追加一个 rtattr 属性
+fn push_attr(buf: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    let len = RTA_HDR_LEN + data.len();
+    buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    buf.extend_from_slice(&kind.to_ne_bytes());
+    buf.extend_from_slice(data);
+    buf.resize(buf.len() + align(len) - len, 0);
+}
+
+fn parse_ip(family: libc::c_int, data: &[u8]) -> Option<IpAddr> {
+    match family {
+        libc::AF_INET if data.len() >= 4 => Some(IpAddr::V4(Ipv4Addr::new(
+            data[0], data[1], data[2], data[3],
+        ))),
+        libc::AF_INET6 if data.len() >= 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&data[..16]);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+fn ip_octets(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    }
+}
+
+/// netlink 消息与属性均按 4 字节对齐
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
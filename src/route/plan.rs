@@ -0,0 +1,508 @@
+use crate::base::{NetRouteError, files};
+use crate::route::cidr::Cidr;
+use crate::route::state::ManagedRoute;
+use prettytable::Table;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use winroute::Route;
+
+/// 配置文件期望的路由
+#[derive(Debug, Clone)]
+pub struct DesiredRoute {
+    /// 路由
+    pub route: Route,
+    /// 产生该路由的域名或配置项
+    pub source: String,
+    /// 产生该路由的配置文件，配置只由一个文件组成时为空
+    pub file: Option<String>,
+}
+
+/// 计划中的操作
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanAction {
+    /// 添加路由
+    Add,
+    /// 删除路由
+    Remove,
+    /// 路由已存在且一致
+    Unchanged,
+    /// 删除已存在的路由后添加期望的路由
+    Modify,
+    /// 路由表中已有目标网段与度量值相同、不由本工具管理的路由，无法添加期望的路由
+    Conflict,
+}
+
+impl fmt::Display for PlanAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanAction::Add => write!(f, "添加"),
+            PlanAction::Remove => write!(f, "删除"),
+            PlanAction::Unchanged => write!(f, "不变"),
+            PlanAction::Modify => write!(f, "修改"),
+            PlanAction::Conflict => write!(f, "冲突"),
+        }
+    }
+}
+
+/// 计划中的一条路由变更
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlanEntry {
+    /// 操作
+    pub action: PlanAction,
+    /// 路由表中已存在的路由
+    pub current: Option<Route>,
+    /// 配置文件期望的路由
+    pub desired: Option<Route>,
+    /// 产生该路由的域名或配置项
+    pub source: String,
+    /// 产生该路由的配置文件，配置只由一个文件组成时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// 配置文件的执行计划
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoutePlan {
+    /// 生成计划的配置文件路径
+    pub config_path: String,
+    /// 生成计划的时间，Unix 时间戳（秒）
+    pub created_at: u64,
+    /// 生成计划时与计划相关的路由表快照
+    pub snapshot: Vec<Route>,
+    /// 路由变更列表
+    pub entries: Vec<PlanEntry>,
+}
+
+/// 路由在路由表中的目标网段
+type RouteKey = (IpAddr, u8);
+
+/// 用于比较路由是否一致的字段
+type RouteIdentity = (IpAddr, u8, IpAddr, Option<u32>, u32);
+
+fn route_key(route: &Route) -> RouteKey {
+    (route.destination, route.prefix)
+}
+
+fn route_identity(route: &Route) -> RouteIdentity {
+    (
+        route.destination,
+        route.prefix,
+        route.gateway,
+        route.ifindex,
+        route.metric.unwrap_or(0),
+    )
+}
+
+/// 判断两条路由是否指向同一条路由表记录，不比较度量值
+///
+/// 状态文件、执行计划、守护进程与路由表后端都以此判断路由是否由本工具添加
+///
+/// # Arguments
+///
+/// * `a` - 路由
+/// * `b` - 路由
+///
+pub(crate) fn same_record(a: &Route, b: &Route) -> bool {
+    a.destination == b.destination
+        && a.prefix == b.prefix
+        && a.ifindex == b.ifindex
+        && a.gateway == b.gateway
+}
+
+/// 判断已存在的路由与期望的路由是否一致
+fn same_route(current: &Route, desired: &Route) -> bool {
+    route_identity(current) == route_identity(desired)
+}
+
+impl RoutePlan {
+    /// 对比期望的路由与路由表，生成执行计划
+    ///
+    /// 同一目标网段上与期望一致的路由保持不变，不一致的路由被修改，
+    /// 多余的路由被删除；`retired` 中的网段只删除不添加。
+    /// 只有 `managed_routes` 中记录的路由会被修改或删除，用户或其他程序添加的路由保持不变
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - 配置文件路径
+    /// * `live_routes` - 当前路由表
+    /// * `managed_routes` - 由本工具添加的路由，为空时路由表中的所有路由都可以修改或删除，如恢复快照
+    /// * `desired_routes` - 配置文件期望的路由
+    /// * `retired` - 需要删除的网段及其来源
+    ///
+    pub fn new(
+        config_path: &str,
+        live_routes: &[Route],
+        managed_routes: Option<&[ManagedRoute]>,
+        desired_routes: &[DesiredRoute],
+        retired: &[(Cidr, String)],
+    ) -> RoutePlan {
+        // 按目标网段分组，保持配置文件中的顺序
+        let mut keys: Vec<RouteKey> = vec![];
+        let mut desired_by_key: BTreeMap<RouteKey, Vec<&DesiredRoute>> = BTreeMap::new();
+        let mut source_by_key: BTreeMap<RouteKey, String> = BTreeMap::new();
+        for desired in desired_routes {
+            let key = route_key(&desired.route);
+            if let Entry::Vacant(entry) = source_by_key.entry(key) {
+                keys.push(key);
+                entry.insert(desired.source.clone());
+            }
+            desired_by_key.entry(key).or_default().push(desired);
+        }
+        for (cidr, source) in retired {
+            let key = (cidr.address, cidr.prefix);
+            if let Entry::Vacant(entry) = source_by_key.entry(key) {
+                keys.push(key);
+                entry.insert(source.clone());
+            }
+        }
+
+        let mut entries = vec![];
+        for key in keys.iter() {
+            let mut live = live_routes
+                .iter()
+                .filter(|route| route_key(route) == *key)
+                .collect::<Vec<&Route>>();
+            let mut pending = vec![];
+            for desired in desired_by_key.get(key).into_iter().flatten() {
+                match live
+                    .iter()
+                    .position(|current| same_route(current, &desired.route))
+                {
+                    Some(idx) => {
+                        let current = live.remove(idx);
+                        entries.push(PlanEntry {
+                            action: PlanAction::Unchanged,
+                            current: Some(current.clone()),
+                            desired: Some(desired.route.clone()),
+                            source: desired.source.clone(),
+                            file: desired.file.clone(),
+                        });
+                    }
+                    None => pending.push(*desired),
+                }
+            }
+            // 不一致的路由依次修改，剩余的期望路由添加，剩余的已存在路由删除
+            let mut live = live.into_iter().filter(|current| {
+                managed_routes.is_none_or(|managed_routes| {
+                    managed_routes
+                        .iter()
+                        .any(|managed| same_record(&managed.route, current))
+                })
+            });
+            for desired in pending {
+                let (action, current) = match live.next() {
+                    Some(current) => (PlanAction::Modify, Some(current.clone())),
+                    None => (PlanAction::Add, None),
+                };
+                entries.push(PlanEntry {
+                    action,
+                    current,
+                    desired: Some(desired.route.clone()),
+                    source: desired.source.clone(),
+                    file: desired.file.clone(),
+                });
+            }
+            for current in live {
+                entries.push(PlanEntry {
+                    action: PlanAction::Remove,
+                    current: Some(current.clone()),
+                    desired: None,
+                    source: source_by_key[key].clone(),
+                    file: None,
+                });
+            }
+        }
+
+        let key_set = keys.into_iter().collect::<BTreeSet<RouteKey>>();
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        RoutePlan {
+            config_path: config_path.to_string(),
+            created_at,
+            snapshot: snapshot_of(live_routes, &key_set),
+            entries,
+        }
+    }
+
+    /// 删除由本工具添加、但计划中没有涉及的路由
+    ///
+    /// # Arguments
+    ///
+    /// * `live_routes` - 当前路由表
+    /// * `managed_routes` - 状态文件中记录的路由
+    ///
+    pub fn retire_managed(&mut self, live_routes: &[Route], managed_routes: &[ManagedRoute]) {
+        for managed in managed_routes {
+            let Some(current) = live_routes
+                .iter()
+                .find(|live| same_record(live, &managed.route))
+            else {
+                continue;
+            };
+            let planned = self.entries.iter().any(|entry| {
+                entry
+                    .current
+                    .as_ref()
+                    .is_some_and(|planned| same_record(planned, current))
+            });
+            if planned {
+                continue;
+            }
+            self.entries.push(PlanEntry {
+                action: PlanAction::Remove,
+                current: Some(current.clone()),
+                desired: None,
+                source: managed.source.domain.clone().unwrap_or_default(),
+                file: None,
+            });
+            if !self
+                .snapshot
+                .iter()
+                .any(|snapshot| same_route(snapshot, current))
+            {
+                self.snapshot.push(current.clone());
+            }
+        }
+    }
+
+    /// 将无法添加的期望路由标记为冲突，执行计划时跳过
+    ///
+    /// 路由表不允许目标网段与度量值都相同的多条路由同时存在时，期望的路由与计划中
+    /// 不会删除的路由（通常由用户或其他程序添加）冲突，添加会失败并使整个计划回滚；
+    /// 需要修改的路由发生冲突时只删除原路由
+    ///
+    /// # Arguments
+    ///
+    /// * `live_routes` - 当前路由表
+    ///
+    pub fn mark_conflicts(&mut self, live_routes: &[Route]) {
+        let removed = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry.action, PlanAction::Remove | PlanAction::Modify))
+            .filter_map(|entry| entry.current.clone())
+            .collect::<Vec<Route>>();
+        let mut occupied = live_routes
+            .iter()
+            .filter(|live| !removed.iter().any(|route| same_route(route, live)))
+            .cloned()
+            .collect::<Vec<Route>>();
+        let mut entries = vec![];
+        for mut entry in std::mem::take(&mut self.entries) {
+            let conflict = entry
+                .desired
+                .as_ref()
+                .filter(|_| matches!(entry.action, PlanAction::Add | PlanAction::Modify))
+                .and_then(|desired| {
+                    occupied
+                        .iter()
+                        .find(|route| {
+                            route_key(route) == route_key(desired)
+                                && route.metric.unwrap_or(0) == desired.metric.unwrap_or(0)
+                                && !same_record(route, desired)
+                        })
+                        .cloned()
+                });
+            match conflict {
+                Some(conflict) => {
+                    if entry.action == PlanAction::Modify {
+                        entries.push(PlanEntry {
+                            action: PlanAction::Remove,
+                            desired: None,
+                            ..entry.clone()
+                        });
+                    }
+                    entry.action = PlanAction::Conflict;
+                    entry.current = Some(conflict);
+                }
+                None => {
+                    if let Some(desired) = &entry.desired {
+                        occupied.push(desired.clone());
+                    }
+                }
+            }
+            entries.push(entry);
+        }
+        self.entries = entries;
+    }
+
+    /// 计划涉及的目标网段
+    fn keys(&self) -> BTreeSet<RouteKey> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.current.iter().chain(entry.desired.iter()))
+            .map(route_key)
+            .chain(self.snapshot.iter().map(route_key))
+            .collect()
+    }
+
+    /// 计划中是否存在需要修改路由表的操作
+    pub fn has_changes(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| !matches!(entry.action, PlanAction::Unchanged | PlanAction::Conflict))
+    }
+
+    /// 统计各个操作的数量
+    pub fn count(&self, action: PlanAction) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.action == action)
+            .count()
+    }
+
+    /// 检查路由表在生成计划后是否发生变化，返回变化的路由描述
+    ///
+    /// # Arguments
+    ///
+    /// * `live_routes` - 当前路由表
+    ///
+    pub fn detect_drift(&self, live_routes: &[Route]) -> Vec<String> {
+        let planned = self
+            .snapshot
+            .iter()
+            .map(route_identity)
+            .collect::<BTreeSet<RouteIdentity>>();
+        let current = snapshot_of(live_routes, &self.keys())
+            .iter()
+            .map(route_identity)
+            .collect::<BTreeSet<RouteIdentity>>();
+        let describe = |prefix: &str, identity: &RouteIdentity| {
+            let (destination, prefix_len, gateway, ifindex, metric) = identity;
+            format!(
+                "{} {}/{} via {} 网卡 {} metric {}",
+                prefix,
+                destination,
+                prefix_len,
+                gateway,
+                ifindex.map_or("NONE".to_string(), |index| index.to_string()),
+                metric
+            )
+        };
+        planned
+            .difference(&current)
+            .map(|identity| describe("已删除", identity))
+            .chain(
+                current
+                    .difference(&planned)
+                    .map(|identity| describe("新增", identity)),
+            )
+            .collect()
+    }
+
+    /// 将计划保存为 JSON 文件
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 计划文件路径
+    ///
+    pub fn save(&self, path: &str) -> Result<(), NetRouteError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| NetRouteError::new(format!("计划文件序列化失败: {}", e)))?;
+        files::write_file_content(path, &content)
+    }
+
+    /// 从 JSON 文件加载计划
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 计划文件路径
+    ///
+    pub fn load(path: &str) -> Result<RoutePlan, NetRouteError> {
+        let content = files::read_file_content(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| NetRouteError::new(format!("计划文件解析失败: {}", e)))
+    }
+}
+
+/// 获取路由表中属于指定目标网段的路由
+fn snapshot_of(live_routes: &[Route], keys: &BTreeSet<RouteKey>) -> Vec<Route> {
+    live_routes
+        .iter()
+        .filter(|route| keys.contains(&route_key(route)))
+        .cloned()
+        .collect()
+}
+
+/// 打印执行计划
+///
+/// # Arguments
+///
+/// * `plan` - 执行计划
+///
+pub fn show_plan(plan: &RoutePlan) {
+    // 配置由多个文件组成时显示每条路由所在的文件
+    let show_file = plan.entries.iter().any(|entry| entry.file.is_some());
+    let mut table = Table::new();
+    let mut header = [
+        "序号",
+        "操作",
+        "目标地址",
+        "prefix",
+        "当前网关",
+        "当前网卡",
+        "期望网关",
+        "期望网卡",
+        "来源",
+    ]
+    .map(str::to_string)
+    .to_vec();
+    if show_file {
+        header.push("文件".to_string());
+    }
+    table.add_row(header.into());
+    let gateway = |route: &Option<Route>| {
+        route
+            .as_ref()
+            .map_or("-".to_string(), |route| route.gateway.to_string())
+    };
+    let ifindex = |route: &Option<Route>| {
+        route.as_ref().map_or("-".to_string(), |route| {
+            route
+                .ifindex
+                .map_or("NONE".to_string(), |index| index.to_string())
+        })
+    };
+    for (idx, entry) in plan.entries.iter().enumerate() {
+        let Some(route) = entry.desired.as_ref().or(entry.current.as_ref()) else {
+            continue;
+        };
+        let mut row = vec![
+            idx.to_string(),
+            entry.action.to_string(),
+            route.destination.to_string(),
+            route.prefix.to_string(),
+            gateway(&entry.current),
+            ifindex(&entry.current),
+            gateway(&entry.desired),
+            ifindex(&entry.desired),
+            entry.source.clone(),
+        ];
+        if show_file {
+            row.push(entry.file.clone().unwrap_or("-".to_string()));
+        }
+        table.add_row(row.into());
+    }
+    table.printstd();
+    println!(
+        "计划: 添加 {} 条，删除 {} 条，修改 {} 条，不变 {} 条",
+        plan.count(PlanAction::Add),
+        plan.count(PlanAction::Remove),
+        plan.count(PlanAction::Modify),
+        plan.count(PlanAction::Unchanged)
+    );
+    let conflicts = plan.count(PlanAction::Conflict);
+    if conflicts > 0 {
+        println!(
+            "有 {} 条路由与路由表中不由本工具管理的路由目标网段与度量值相同，将跳过，可修改配置中的 metric 后重试",
+            conflicts
+        );
+    }
+}
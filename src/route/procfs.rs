@@ -0,0 +1,200 @@
+use crate::base::{NetRouteError, files};
+use crate::route::backend::RouteBackend;
+use std::ffi::CString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use winroute::Route;
+
+/// IPv4 路由表文件
+const PROC_NET_ROUTE: &str = "/proc/net/route";
+/// IPv6 路由表文件
+const PROC_NET_IPV6_ROUTE: &str = "/proc/net/ipv6_route";
+
+/// 拒绝路由，如 unreachable / prohibit
+const RTF_REJECT: u32 = 0x0200;
+/// 路由缓存
+const RTF_CACHE: u32 = 0x0100_0000;
+/// 本机地址路由，属于 local 路由表
+const RTF_LOCAL: u32 = 0x8000_0000;
+
+/// 基于 /proc/net/route 与 /proc/net/ipv6_route 的只读路由表后端
+///
+/// 不需要任何权限即可读取，适用于 netlink 不可用的最小化容器，
+/// 添加与删除路由时返回错误。
+pub struct ProcRoute {
+    route_path: String,
+    ipv6_route_path: String,
+}
+
+impl ProcRoute {
+    pub fn new() -> ProcRoute {
+        ProcRoute {
+            route_path: PROC_NET_ROUTE.to_string(),
+            ipv6_route_path: PROC_NET_IPV6_ROUTE.to_string(),
+        }
+    }
+}
+
+impl RouteBackend for ProcRoute {
+    fn exclusive_metric(&self) -> bool {
+        true
+    }
+
+    fn get_routes(&self) -> Result<Vec<Route>, NetRouteError> {
+        let mut routes = parse_proc_route(
+            &files::read_file_content(&self.route_path)?,
+            &if_name_to_index,
+        )?;
+        // 内核未启用 IPv6 时文件不存在
+        if let Ok(content) = files::read_file_content(&self.ipv6_route_path) {
+            routes.extend(parse_proc_ipv6_route(&content, &if_name_to_index)?);
+        }
+        Ok(routes)
+    }
+
+    fn add_route(&self, _route: &Route) -> Result<(), NetRouteError> {
+        Err(NetRouteError::new(
+            "添加路由错误: 当前使用 /proc/net 只读路由表，无法修改路由".to_string(),
+        ))
+    }
+
+    fn remove_route(&self, _route: &Route) -> Result<(), NetRouteError> {
+        Err(NetRouteError::new(
+            "删除路由错误: 当前使用 /proc/net 只读路由表，无法修改路由".to_string(),
+        ))
+    }
+}
+
+/// 根据网卡名称获取网卡索引
+///
+/// # Arguments
+///
+/// * `name` - 网卡名称
+///
+fn if_name_to_index(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+/// 解析 /proc/net/route 的内容
+///
+/// 地址与掩码为内存字节序的十六进制数，度量值为十进制数
+///
+/// # Arguments
+///
+/// * `content` - 文件内容
+/// * `resolve_index` - 根据网卡名称获取网卡索引
+///
+pub fn parse_proc_route(
+    content: &str,
+    resolve_index: &dyn Fn(&str) -> Option<u32>,
+) -> Result<Vec<Route>, NetRouteError> {
+    let mut routes = vec![];
+    // 跳过表头
+    for (line_no, line) in content.lines().enumerate().skip(1) {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() < 8 {
+            return Err(NetRouteError::new(format!(
+                "{} 第 {} 行格式错误: {}",
+                PROC_NET_ROUTE,
+                line_no + 1,
+                line
+            )));
+        }
+        let parse_err = |field: &str| {
+            NetRouteError::new(format!(
+                "{} 第 {} 行字段解析失败: {}",
+                PROC_NET_ROUTE,
+                line_no + 1,
+                field
+            ))
+        };
+        let parse_hex_v4 = |field: &str| {
+            u32::from_str_radix(field, 16)
+                .map(|value| Ipv4Addr::from(value.to_ne_bytes()))
+                .map_err(|_| parse_err(field))
+        };
+        let destination = parse_hex_v4(fields[1])?;
+        let gateway = parse_hex_v4(fields[2])?;
+        let flags = u32::from_str_radix(fields[3], 16).map_err(|_| parse_err(fields[3]))?;
+        let metric = fields[6].parse::<u32>().map_err(|_| parse_err(fields[6]))?;
+        let mask = parse_hex_v4(fields[7])?;
+        if flags & RTF_REJECT != 0 {
+            continue;
+        }
+        let prefix = u32::from(mask).count_ones() as u8;
+        let mut route = Route::new(IpAddr::V4(destination), prefix)
+            .gateway(IpAddr::V4(gateway))
+            .metric(metric);
+        if let Some(index) = resolve_index(fields[0]) {
+            route = route.ifindex(index);
+        }
+        routes.push(route);
+    }
+    Ok(routes)
+}
+
+/// 解析 /proc/net/ipv6_route 的内容
+///
+/// 该文件包含所有路由表，这里跳过本机地址、拒绝路由、缓存与组播路由，
+/// 使结果与 main 路由表保持一致
+///
+/// # Arguments
+///
+/// * `content` - 文件内容
+/// * `resolve_index` - 根据网卡名称获取网卡索引
+///
+pub fn parse_proc_ipv6_route(
+    content: &str,
+    resolve_index: &dyn Fn(&str) -> Option<u32>,
+) -> Result<Vec<Route>, NetRouteError> {
+    let mut routes = vec![];
+    for (line_no, line) in content.lines().enumerate() {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() < 10 {
+            return Err(NetRouteError::new(format!(
+                "{} 第 {} 行格式错误: {}",
+                PROC_NET_IPV6_ROUTE,
+                line_no + 1,
+                line
+            )));
+        }
+        let parse_err = |field: &str| {
+            NetRouteError::new(format!(
+                "{} 第 {} 行字段解析失败: {}",
+                PROC_NET_IPV6_ROUTE,
+                line_no + 1,
+                field
+            ))
+        };
+        let parse_hex_v6 = |field: &str| {
+            u128::from_str_radix(field, 16)
+                .map(Ipv6Addr::from)
+                .map_err(|_| parse_err(field))
+        };
+        let destination = parse_hex_v6(fields[0])?;
+        let prefix = u8::from_str_radix(fields[1], 16).map_err(|_| parse_err(fields[1]))?;
+        let gateway = parse_hex_v6(fields[4])?;
+        let metric = u32::from_str_radix(fields[5], 16).map_err(|_| parse_err(fields[5]))?;
+        let flags = u32::from_str_radix(fields[8], 16).map_err(|_| parse_err(fields[8]))?;
+        if flags & (RTF_REJECT | RTF_CACHE | RTF_LOCAL) != 0 || destination.is_multicast() {
+            continue;
+        }
+        let mut route = Route::new(IpAddr::V6(destination), prefix)
+            .gateway(IpAddr::V6(gateway))
+            .metric(metric);
+        if let Some(index) = resolve_index(fields[9]) {
+            route = route.ifindex(index);
+        }
+        routes.push(route);
+    }
+    Ok(routes)
+}
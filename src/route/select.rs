@@ -0,0 +1,309 @@
+use crate::base::NetRouteError;
+use crate::interface::selector::InterfaceSelector;
+use crate::interface::{AdapterInfo, Interface};
+use crate::probe::check::Check;
+use crate::probe::{self, CheckResult, PingOptions};
+use clap::ValueEnum;
+use prettytable::Table;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// 添加路由的网卡，`auto` 表示从候选网卡中按连通性测试结果自动选择
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfIndex {
+    /// 通过选择器指定的网卡
+    Adapter(InterfaceSelector),
+    /// 自动选择
+    Auto,
+}
+
+impl FromStr for IfIndex {
+    type Err = NetRouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(IfIndex::Auto);
+        }
+        s.parse().map(IfIndex::Adapter)
+    }
+}
+
+impl fmt::Display for IfIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IfIndex::Adapter(selector) => write!(f, "{}", selector),
+            IfIndex::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl Serialize for IfIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            IfIndex::Adapter(selector) => selector.serialize(serializer),
+            IfIndex::Auto => serializer.serialize_str("auto"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for IfIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// 配置文件中的网卡索引可以是数字或字符串
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Index(u32),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Index(index) => Ok(IfIndex::Adapter(InterfaceSelector::Index(index))),
+            Raw::Text(text) => text
+                .parse()
+                .map_err(|e: NetRouteError| serde::de::Error::custom(e.message)),
+        }
+    }
+}
+
+/// 自动选择网卡的粒度
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectScope {
+    /// 为每个 IP 地址分别选择网卡
+    #[default]
+    Ip,
+    /// 整个域名的所有 IP 地址使用同一个网卡
+    Domain,
+}
+
+/// 添加路由使用的网卡
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelection {
+    /// 使用指定的网卡
+    Fixed(u32),
+    /// 从候选网卡中自动选择
+    Auto {
+        /// 候选网卡索引
+        candidates: Vec<u32>,
+        /// 选择粒度
+        scope: SelectScope,
+    },
+}
+
+impl AdapterSelection {
+    /// 根据网卡与候选网卡创建网卡选择方式，选择器在创建时解析为网卡索引
+    ///
+    /// # Arguments
+    ///
+    /// * `if_index` - 网卡选择器或 `auto`
+    /// * `candidates` - 候选网卡选择器，只能与 `auto` 同时使用
+    /// * `scope` - 自动选择的粒度
+    ///
+    pub fn new(
+        if_index: &IfIndex,
+        candidates: &[InterfaceSelector],
+        scope: SelectScope,
+    ) -> Result<AdapterSelection, NetRouteError> {
+        match if_index {
+            IfIndex::Adapter(selector) if candidates.is_empty() => {
+                Ok(AdapterSelection::Fixed(selector.resolve_index()?))
+            }
+            IfIndex::Adapter(_) => Err(NetRouteError::new(
+                "候选网卡只能在网卡索引为 auto 时使用".to_string(),
+            )),
+            IfIndex::Auto if candidates.is_empty() => Err(NetRouteError::new(
+                "网卡索引为 auto 时需要指定候选网卡".to_string(),
+            )),
+            IfIndex::Auto => {
+                let mut unique = vec![];
+                for candidate in candidates {
+                    let index = candidate.resolve_index()?;
+                    if !unique.contains(&index) {
+                        unique.push(index);
+                    }
+                }
+                Ok(AdapterSelection::Auto {
+                    candidates: unique,
+                    scope,
+                })
+            }
+        }
+    }
+}
+
+/// 经由某个候选网卡到各目标地址的检查结果
+struct CandidateResults {
+    adapter: AdapterInfo,
+    /// 与目标地址顺序一致，无法测试时为空
+    results: Vec<Option<CheckResult>>,
+}
+
+/// 比较两个检查结果，丢包率低的优先，丢包率相同时耗时短的优先
+///
+/// # Arguments
+///
+/// * `a` - 检查结果
+/// * `b` - 检查结果
+///
+fn compare_results(a: &Option<CheckResult>, b: &Option<CheckResult>) -> Ordering {
+    let key = |result: &Option<CheckResult>| match result {
+        Some(result) if result.success => (result.loss, result.latency_ms.unwrap_or(f64::MAX)),
+        _ => (f64::MAX, f64::MAX),
+    };
+    let (a, b) = (key(a), key(b));
+    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+}
+
+/// 汇总多个检查结果，返回平均丢包率与可达结果的平均耗时
+///
+/// # Arguments
+///
+/// * `results` - 检查结果
+///
+fn summarize(results: &[Option<CheckResult>]) -> Option<CheckResult> {
+    let first = results.iter().flatten().next()?;
+    let count = results.len() as f64;
+    let loss = results
+        .iter()
+        .map(|result| result.as_ref().map_or(100.0, |result| result.loss))
+        .sum::<f64>()
+        / count;
+    let latencies = results
+        .iter()
+        .flatten()
+        .filter_map(|result| result.latency_ms)
+        .collect::<Vec<f64>>();
+    let success = !latencies.is_empty();
+    Some(CheckResult {
+        success,
+        loss,
+        latency_ms: success.then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+        detail: String::new(),
+        ..first.clone()
+    })
+}
+
+/// 从候选网卡中为每个目标地址选择丢包率最低、耗时最短的网卡，并展示对比表格
+///
+/// 所有候选网卡都不可达时使用第一个候选网卡；目标地址的地址族在某个候选网卡上没有源地址时，
+/// 该网卡不参与该地址的选择
+///
+/// # Arguments
+///
+/// * `target_ips` - 目标 IP 地址列表
+/// * `candidates` - 候选网卡索引
+/// * `scope` - 选择粒度
+/// * `check` - 连通性检查方式
+/// * `server_name` - TLS 检查使用的服务器名称
+///
+pub fn select_adapters(
+    target_ips: &[IpAddr],
+    candidates: &[u32],
+    scope: &SelectScope,
+    check: &Check,
+    server_name: Option<&str>,
+) -> Result<Vec<(IpAddr, AdapterInfo)>, NetRouteError> {
+    let interface = Interface::new();
+    let mut candidate_results = vec![];
+    for if_index in candidates {
+        let adapter = interface.get_interface_by_index(if_index)?;
+        let results = probe::check_all_from_adapter(
+            &adapter,
+            target_ips,
+            check,
+            server_name,
+            &PingOptions::default(),
+        )
+        .into_iter()
+        .map(Result::ok)
+        .collect();
+        candidate_results.push(CandidateResults { adapter, results });
+    }
+    let best_for = |results: &dyn Fn(&CandidateResults) -> Option<CheckResult>| {
+        // 结果相同时保留靠前的候选网卡
+        candidate_results
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| compare_results(&results(a), &results(b)))
+            .map(|(idx, _)| idx)
+            .unwrap_or_default()
+    };
+    let choices = match scope {
+        SelectScope::Ip => (0..target_ips.len())
+            .map(|ip_idx| best_for(&|candidate| candidate.results[ip_idx].clone()))
+            .collect::<Vec<usize>>(),
+        SelectScope::Domain => {
+            let best = best_for(&|candidate| summarize(&candidate.results));
+            vec![best; target_ips.len()]
+        }
+    };
+    show_selection_table(target_ips, &candidate_results, scope, &choices);
+    Ok(target_ips
+        .iter()
+        .zip(choices)
+        .map(|(ip, idx)| (*ip, candidate_results[idx].adapter.clone()))
+        .collect())
+}
+
+/// 格式化检查结果的耗时与丢包率
+fn format_result(result: &Option<CheckResult>) -> String {
+    match result {
+        Some(result) if result.success => format!(
+            "{:.2} ms / {:.0}%",
+            result.latency_ms.unwrap_or_default(),
+            result.loss
+        ),
+        Some(_) => "不可达".to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// 展示各候选网卡的检查结果对比表格
+///
+/// # Arguments
+///
+/// * `target_ips` - 目标 IP 地址列表
+/// * `candidate_results` - 各候选网卡的检查结果
+/// * `scope` - 选择粒度
+/// * `choices` - 每个目标地址选择的候选网卡序号
+///
+fn show_selection_table(
+    target_ips: &[IpAddr],
+    candidate_results: &[CandidateResults],
+    scope: &SelectScope,
+    choices: &[usize],
+) {
+    let adapter_name = |idx: usize| {
+        let adapter = &candidate_results[idx].adapter;
+        format!("{} ({})", adapter.name, adapter.index)
+    };
+    let mut table = Table::new();
+    let mut header = vec!["目标地址".to_string()];
+    header.extend((0..candidate_results.len()).map(adapter_name));
+    header.push("选择".to_string());
+    table.add_row(header.into());
+    for (ip_idx, ip) in target_ips.iter().enumerate() {
+        let mut row = vec![ip.to_string()];
+        row.extend(
+            candidate_results
+                .iter()
+                .map(|candidate| format_result(&candidate.results[ip_idx])),
+        );
+        row.push(adapter_name(choices[ip_idx]));
+        table.add_row(row.into());
+    }
+    if *scope == SelectScope::Domain && !target_ips.is_empty() {
+        let mut row = vec!["平均".to_string()];
+        row.extend(
+            candidate_results
+                .iter()
+                .map(|candidate| format_result(&summarize(&candidate.results))),
+        );
+        row.push(adapter_name(choices[0]));
+        table.add_row(row.into());
+    }
+    println!("候选网卡连通性对比:");
+    table.printstd();
+}
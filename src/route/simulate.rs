@@ -0,0 +1,281 @@
+use crate::base::{NetRouteError, files};
+use crate::interface::AdapterInfo;
+use crate::route::backend::{RouteBackend, replace_by_add_remove};
+use crate::route::plan::same_record;
+use crate::route::state::ManagedRoute;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::time::Duration;
+use winroute::Route;
+
+/// 模拟网络环境
+///
+/// 包含路由表、网卡列表以及域名解析结果，从 JSON 夹具文件加载，
+/// 启用后所有命令都只读写内存中的数据，不会修改系统路由表。
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SimNetwork {
+    /// 网卡列表
+    #[serde(default)]
+    pub adapters: Vec<AdapterInfo>,
+    /// 路由表
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// 域名解析结果
+    #[serde(default)]
+    pub dns: BTreeMap<String, Vec<IpAddr>>,
+    /// 域名解析结果的 TTL，秒，未配置时视为无法获取
+    #[serde(default)]
+    pub ttl: BTreeMap<String, u32>,
+    /// 连通性测试时不可达的 IP 地址
+    #[serde(default)]
+    pub unreachable: Vec<IpAddr>,
+    /// 经由各网卡到目标 IP 地址的往返时间，毫秒，未配置时为 1
+    #[serde(default)]
+    pub latency: BTreeMap<u32, BTreeMap<IpAddr, u64>>,
+    /// 由本工具添加的路由记录
+    #[serde(default)]
+    pub managed: Vec<ManagedRoute>,
+}
+
+thread_local! {
+    /// 当前线程启用的模拟网络环境
+    static SIMULATION: RefCell<Option<Rc<RefCell<SimNetwork>>>> = const { RefCell::new(None) };
+}
+
+/// 从 JSON 字符串解析模拟网络环境
+///
+/// # Arguments
+///
+/// * `json_str` - JSON 字符串
+///
+pub fn parse_fixture(json_str: &str) -> Result<SimNetwork, NetRouteError> {
+    serde_json::from_str(json_str)
+        .map_err(|e| NetRouteError::new(format!("模拟环境文件解析失败: {}", e)))
+}
+
+/// 从夹具文件加载模拟网络环境
+///
+/// # Arguments
+///
+/// * `path` - 夹具文件路径
+///
+pub fn load_fixture(path: &str) -> Result<SimNetwork, NetRouteError> {
+    let content = files::read_file_content(path)?;
+    parse_fixture(&content)
+}
+
+/// 在当前线程启用模拟网络环境
+///
+/// # Arguments
+///
+/// * `network` - 模拟网络环境
+///
+pub fn enable(network: SimNetwork) {
+    SIMULATION.with(|sim| *sim.borrow_mut() = Some(Rc::new(RefCell::new(network))));
+}
+
+/// 获取当前线程启用的模拟网络环境
+fn current() -> Option<Rc<RefCell<SimNetwork>>> {
+    SIMULATION.with(|sim| sim.borrow().clone())
+}
+
+/// 当前线程是否启用了模拟网络环境
+pub fn is_enabled() -> bool {
+    current().is_some()
+}
+
+/// 获取模拟的网卡列表，未启用时返回 None
+pub fn adapters() -> Option<Vec<AdapterInfo>> {
+    current().map(|network| network.borrow().adapters.clone())
+}
+
+/// 查询模拟的域名解析结果，未启用时返回 None
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+///
+pub fn lookup_host(domain: &str) -> Option<Result<Vec<IpAddr>, NetRouteError>> {
+    current().map(|network| {
+        network
+            .borrow()
+            .dns
+            .get(domain)
+            .cloned()
+            .ok_or_else(|| NetRouteError::new(format!("Invalid domain name: {}", domain)))
+    })
+}
+
+/// 查询模拟的域名解析结果的 TTL，未启用或未配置时返回 None
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+///
+pub fn dns_ttl(domain: &str) -> Option<u32> {
+    current().and_then(|network| network.borrow().ttl.get(domain).copied())
+}
+
+/// 修改模拟的域名解析结果，用于模拟域名的 IP 地址发生变化
+///
+/// # Arguments
+///
+/// * `domain` - 域名
+/// * `ip_list` - 新的解析结果
+///
+#[cfg(test)]
+pub fn set_dns(domain: &str, ip_list: Vec<IpAddr>) {
+    if let Some(network) = current() {
+        network.borrow_mut().dns.insert(domain.to_string(), ip_list);
+    }
+}
+
+/// 查询模拟的连通性测试结果，未启用时返回 None
+///
+/// # Arguments
+///
+/// * `ip` - 目标 IP 地址
+///
+pub fn is_reachable(ip: &IpAddr) -> Option<bool> {
+    current().map(|network| !network.borrow().unreachable.contains(ip))
+}
+
+/// 查询模拟的经由网卡到目标 IP 地址的往返时间，未启用时返回 None
+///
+/// # Arguments
+///
+/// * `if_index` - 网卡索引
+/// * `ip` - 目标 IP 地址
+///
+pub fn latency(if_index: u32, ip: &IpAddr) -> Option<Duration> {
+    current().map(|network| {
+        let millis = network
+            .borrow()
+            .latency
+            .get(&if_index)
+            .and_then(|latency| latency.get(ip).copied())
+            .unwrap_or(1);
+        Duration::from_millis(millis)
+    })
+}
+
+/// 获取模拟的路由状态记录，未启用时返回 None
+pub fn managed_routes() -> Option<Vec<ManagedRoute>> {
+    current().map(|network| network.borrow().managed.clone())
+}
+
+/// 保存模拟的路由状态记录，未启用时返回 false
+///
+/// # Arguments
+///
+/// * `routes` - 由本工具添加的路由记录
+///
+pub fn set_managed_routes(routes: Vec<ManagedRoute>) -> bool {
+    match current() {
+        Some(network) => {
+            network.borrow_mut().managed = routes;
+            true
+        }
+        None => false,
+    }
+}
+
+/// 基于模拟网络环境的路由表后端
+pub struct SimRoute {
+    network: Rc<RefCell<SimNetwork>>,
+}
+
+impl SimRoute {
+    pub fn new() -> Result<SimRoute, NetRouteError> {
+        current()
+            .map(|network| SimRoute { network })
+            .ok_or_else(|| NetRouteError::new("未启用模拟网络环境".to_string()))
+    }
+}
+
+/// 模拟路由表与 Linux 内核一致，不允许目标网段与度量值都相同的多条路由同时存在
+impl RouteBackend for SimRoute {
+    fn exclusive_metric(&self) -> bool {
+        true
+    }
+
+    fn get_routes(&self) -> Result<Vec<Route>, NetRouteError> {
+        Ok(self.network.borrow().routes.clone())
+    }
+
+    fn add_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        let mut network = self.network.borrow_mut();
+        if let Some(ifindex) = route.ifindex
+            && !network
+                .adapters
+                .iter()
+                .any(|adapter| adapter.index == ifindex)
+        {
+            return Err(NetRouteError::new(format!(
+                "添加路由错误: 网卡 {} 不存在",
+                ifindex
+            )));
+        }
+        if network.routes.iter().any(|exist| same_record(exist, route)) {
+            return Err(NetRouteError::new(format!(
+                "添加路由错误: 路由已存在 {}",
+                route
+            )));
+        }
+        if network.routes.iter().any(|exist| {
+            exist.destination == route.destination
+                && exist.prefix == route.prefix
+                && exist.metric.unwrap_or(0) == route.metric.unwrap_or(0)
+        }) {
+            return Err(NetRouteError::new(format!(
+                "添加路由错误: 已存在目标网段与度量值相同的路由 {}",
+                route
+            )));
+        }
+        network.routes.push(route.clone());
+        Ok(())
+    }
+
+    fn remove_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        let mut network = self.network.borrow_mut();
+        match network
+            .routes
+            .iter()
+            .position(|exist| same_record(exist, route))
+        {
+            Some(idx) => {
+                network.routes.remove(idx);
+                Ok(())
+            }
+            None => Err(NetRouteError::new(format!(
+                "删除路由错误: 路由不存在 {}",
+                route
+            ))),
+        }
+    }
+
+    fn replace_route(&self, current: &Route, route: &Route) -> Result<(), NetRouteError> {
+        if current.metric.unwrap_or(0) != route.metric.unwrap_or(0) {
+            return replace_by_add_remove(self, current, route);
+        }
+        // 与 NLM_F_REPLACE 一致，度量值不变时原地替换
+        let mut network = self.network.borrow_mut();
+        match network
+            .routes
+            .iter()
+            .position(|exist| same_record(exist, current))
+        {
+            Some(idx) => {
+                network.routes[idx] = route.clone();
+                Ok(())
+            }
+            None => Err(NetRouteError::new(format!(
+                "替换路由错误: 路由不存在 {}",
+                current
+            ))),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::base::{NetRouteError, files};
+use crate::route::state;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use winroute::Route;
+
+/// 默认保留的自动快照数量
+pub const DEFAULT_KEEP: usize = 10;
+
+/// 自动快照文件名前缀
+const AUTO_PREFIX: &str = "snapshot-";
+
+/// 通过 `--snapshot-keep` 指定的自动快照保留数量
+static KEEP: OnceLock<usize> = OnceLock::new();
+
+/// 路由表快照
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RouteSnapshot {
+    /// 生成快照的时间，Unix 时间戳（秒）
+    pub created_at: u64,
+    /// 路由表中的全部路由
+    pub routes: Vec<Route>,
+}
+
+impl RouteSnapshot {
+    /// 生成路由表快照
+    ///
+    /// # Arguments
+    ///
+    /// * `routes` - 路由表中的全部路由
+    ///
+    pub fn new(routes: Vec<Route>) -> RouteSnapshot {
+        RouteSnapshot {
+            created_at: state::now(),
+            routes,
+        }
+    }
+
+    /// 将快照保存为 JSON 文件
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 快照文件路径
+    ///
+    pub fn save(&self, path: &str) -> Result<(), NetRouteError> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| NetRouteError::new(format!("快照文件序列化失败: {}", e)))?;
+        files::write_file_content(path, &content)
+    }
+
+    /// 从 JSON 文件加载快照
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 快照文件路径
+    ///
+    pub fn load(path: &str) -> Result<RouteSnapshot, NetRouteError> {
+        let content = files::read_file_content(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| NetRouteError::new(format!("快照文件 {} 解析失败: {}", path, e)))
+    }
+}
+
+/// 设置自动快照保留数量，只在程序启动时调用一次
+///
+/// # Arguments
+///
+/// * `keep` - 保留数量，为 0 时不保存自动快照
+///
+pub fn set_keep(keep: usize) {
+    let _ = KEEP.set(keep);
+}
+
+/// 获取自动快照保留数量
+pub fn keep() -> usize {
+    KEEP.get().copied().unwrap_or(DEFAULT_KEEP)
+}
+
+/// 获取自动快照目录，位于状态文件所在目录下的 `snapshots` 目录
+pub fn auto_snapshot_dir() -> PathBuf {
+    state::state_path()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+        .join("snapshots")
+}
+
+/// 获取目录中的自动快照文件，按生成时间从旧到新排列
+///
+/// # Arguments
+///
+/// * `dir` - 自动快照目录
+///
+pub fn list_auto_snapshots(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut snapshots = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(AUTO_PREFIX) && name.ends_with(".json"))
+        })
+        .collect::<Vec<PathBuf>>();
+    // 文件名中的时间戳位数固定，按文件名排序即按时间排序
+    snapshots.sort();
+    snapshots
+}
+
+/// 保存自动快照，并删除超出保留数量的旧快照
+///
+/// # Arguments
+///
+/// * `dir` - 自动快照目录
+/// * `routes` - 路由表中的全部路由
+/// * `keep` - 保留数量
+///
+pub fn save_auto_snapshot(
+    dir: &Path,
+    routes: Vec<Route>,
+    keep: usize,
+) -> Result<PathBuf, NetRouteError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| NetRouteError::new(format!("创建快照目录 {} 失败: {}", dir.display(), e)))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    // 同一毫秒内多次保存时顺延到最新快照之后，避免覆盖或排在已删除的旧快照位置
+    let latest = list_auto_snapshots(dir)
+        .last()
+        .and_then(|path| {
+            path.file_stem()?
+                .to_str()?
+                .strip_prefix(AUTO_PREFIX)?
+                .parse::<u128>()
+                .ok()
+        })
+        .map_or(0, |millis| millis + 1);
+    let path = dir.join(format!("{}{:013}.json", AUTO_PREFIX, now.max(latest)));
+    RouteSnapshot::new(routes).save(&path.to_string_lossy())?;
+    let snapshots = list_auto_snapshots(dir);
+    if snapshots.len() > keep {
+        for old in snapshots.iter().take(snapshots.len() - keep) {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+    Ok(path)
+}
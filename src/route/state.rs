@@ -0,0 +1,328 @@
+use crate::base::{NetRouteError, files};
+use crate::route::plan::same_record;
+use crate::route::simulate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use winroute::Route;
+
+/// 指定状态文件路径的环境变量
+const STATE_PATH_ENV: &str = "NET_ROUTE_RS_STATE";
+
+/// 通过 `--state` 指定的状态文件路径
+static STATE_PATH: OnceLock<String> = OnceLock::new();
+
+/// 添加路由的方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// 命令行添加
+    Cli,
+    /// 应用配置文件添加
+    Config,
+}
+
+/// 路由的来源
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteSource {
+    /// 添加路由的方式
+    pub kind: SourceKind,
+    /// 配置文件路径，命令行添加时为空
+    #[serde(default)]
+    pub config_path: Option<String>,
+    /// 产生该路由的域名或配置项，直接添加 IP 路由时为空
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl RouteSource {
+    /// 命令行添加的路由
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - 域名，直接添加 IP 路由时为空
+    ///
+    pub fn cli(domain: Option<&str>) -> RouteSource {
+        RouteSource {
+            kind: SourceKind::Cli,
+            config_path: None,
+            domain: domain.map(str::to_string),
+        }
+    }
+
+    /// 应用配置文件添加的路由
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - 配置文件路径
+    /// * `domain` - 产生该路由的域名或配置项
+    ///
+    pub fn config(config_path: &str, domain: &str) -> RouteSource {
+        RouteSource {
+            kind: SourceKind::Config,
+            config_path: Some(config_path.to_string()),
+            domain: Some(domain.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for RouteSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, &self.config_path) {
+            (SourceKind::Config, Some(path)) => write!(f, "配置 {}", path)?,
+            (SourceKind::Config, None) => write!(f, "配置")?,
+            (SourceKind::Cli, _) => write!(f, "命令行")?,
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, " ({})", domain)?;
+        }
+        Ok(())
+    }
+}
+
+/// 由本工具添加的路由
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManagedRoute {
+    /// 路由
+    pub route: Route,
+    /// 路由的来源
+    pub source: RouteSource,
+    /// 添加路由的时间，Unix 时间戳（秒）
+    pub created_at: u64,
+}
+
+/// 路由状态，记录本工具添加的所有路由
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RouteState {
+    /// 由本工具添加的路由
+    #[serde(default)]
+    pub routes: Vec<ManagedRoute>,
+}
+
+/// 设置状态文件路径，只在程序启动时调用一次
+///
+/// # Arguments
+///
+/// * `path` - 状态文件路径
+///
+pub fn set_path(path: &str) {
+    let _ = STATE_PATH.set(path.to_string());
+}
+
+/// 获取状态文件路径
+///
+/// 优先使用 `--state` 参数，其次是环境变量 `NET_ROUTE_RS_STATE`，
+/// 否则 Windows 使用 `%ProgramData%\net-route-rs\state.json`，
+/// 其他系统使用 `/var/lib/net-route-rs/state.json`
+pub fn state_path() -> PathBuf {
+    if let Some(path) = STATE_PATH.get() {
+        return PathBuf::from(path);
+    }
+    if let Ok(path) = std::env::var(STATE_PATH_ENV)
+        && !path.is_empty()
+    {
+        return PathBuf::from(path);
+    }
+    default_state_dir().join("state.json")
+}
+
+#[cfg(windows)]
+fn default_state_dir() -> PathBuf {
+    std::env::var("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(r"C:\ProgramData"))
+        .join("net-route-rs")
+}
+
+#[cfg(not(windows))]
+fn default_state_dir() -> PathBuf {
+    PathBuf::from("/var/lib/net-route-rs")
+}
+
+/// 获取配置文件的绝对路径，用于在状态文件中标识配置文件
+///
+/// # Arguments
+///
+/// * `path` - 配置文件路径
+///
+pub fn canonical_config_path(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// 当前 Unix 时间戳（秒）
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl RouteState {
+    /// 加载路由状态，状态文件不存在时返回空状态
+    ///
+    /// 启用模拟网络环境时从模拟环境中读取
+    pub fn load() -> Result<RouteState, NetRouteError> {
+        if let Some(routes) = simulate::managed_routes() {
+            return Ok(RouteState { routes });
+        }
+        let path = state_path();
+        if !path.exists() {
+            return Ok(RouteState::default());
+        }
+        let content = files::read_file_content(&path.to_string_lossy())?;
+        serde_json::from_str(&content)
+            .map_err(|e| NetRouteError::new(format!("状态文件 {} 解析失败: {}", path.display(), e)))
+    }
+
+    /// 保存路由状态
+    ///
+    /// 启用模拟网络环境时只写入模拟环境
+    pub fn save(&self) -> Result<(), NetRouteError> {
+        if simulate::set_managed_routes(self.routes.clone()) {
+            return Ok(());
+        }
+        let path = state_path();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                NetRouteError::new(format!("创建状态文件目录 {} 失败: {}", dir.display(), e))
+            })?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| NetRouteError::new(format!("状态文件序列化失败: {}", e)))?;
+        files::write_file_content(&path.to_string_lossy(), &content)
+    }
+
+    /// 查询路由的记录
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    ///
+    pub fn find(&self, route: &Route) -> Option<&ManagedRoute> {
+        self.routes
+            .iter()
+            .find(|managed| same_record(&managed.route, route))
+    }
+
+    /// 记录新添加的路由，已存在相同路由的记录时覆盖
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    /// * `source` - 路由的来源
+    ///
+    pub fn record(&mut self, route: &Route, source: RouteSource) {
+        self.forget(route);
+        self.routes.push(ManagedRoute {
+            route: route.clone(),
+            source,
+            created_at: now(),
+        });
+    }
+
+    /// 移除路由的记录，返回是否存在该记录
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    ///
+    pub fn forget(&mut self, route: &Route) -> bool {
+        let before = self.routes.len();
+        self.routes
+            .retain(|managed| !same_record(&managed.route, route));
+        self.routes.len() != before
+    }
+
+    /// 获取某个配置文件添加的路由
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - 配置文件路径
+    ///
+    pub fn by_config(&self, config_path: &str) -> Vec<ManagedRoute> {
+        self.routes
+            .iter()
+            .filter(|managed| {
+                managed.source.kind == SourceKind::Config
+                    && managed.source.config_path.as_deref() == Some(config_path)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// 获取某个域名添加的路由
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - 域名
+    ///
+    pub fn by_domain(&self, domain: &str) -> Vec<ManagedRoute> {
+        self.routes
+            .iter()
+            .filter(|managed| managed.source.domain.as_deref() == Some(domain))
+            .cloned()
+            .collect()
+    }
+}
+
+/// 记录新添加的路由并保存状态
+///
+/// # Arguments
+///
+/// * `routes` - 新添加的路由
+/// * `source` - 路由的来源
+///
+pub fn record_routes(routes: &[Route], source: &RouteSource) -> Result<(), NetRouteError> {
+    let mut state = RouteState::load()?;
+    for route in routes {
+        state.record(route, source.clone());
+    }
+    state
+        .save()
+        .map_err(|e| NetRouteError::new(format!("路由已修改，但保存路由状态失败: {}", e.message)))
+}
+
+/// 移除已删除路由的记录并保存状态
+///
+/// # Arguments
+///
+/// * `routes` - 已删除的路由
+///
+pub fn forget_routes(routes: &[Route]) -> Result<(), NetRouteError> {
+    let mut state = RouteState::load()?;
+    let mut changed = false;
+    for route in routes {
+        changed |= state.forget(route);
+    }
+    if !changed {
+        return Ok(());
+    }
+    state
+        .save()
+        .map_err(|e| NetRouteError::new(format!("路由已修改，但保存路由状态失败: {}", e.message)))
+}
+
+/// 路由被替换后，将旧路由的记录转移到新路由并保存状态
+///
+/// 旧路由不是由本工具添加时不做任何修改
+///
+/// # Arguments
+///
+/// * `current` - 被替换的路由
+/// * `route` - 新路由
+///
+pub fn replace_route(current: &Route, route: &Route) -> Result<(), NetRouteError> {
+    let mut state = RouteState::load()?;
+    let Some(managed) = state.find(current).cloned() else {
+        return Ok(());
+    };
+    state.forget(current);
+    state.record(route, managed.source);
+    state
+        .save()
+        .map_err(|e| NetRouteError::new(format!("路由已修改，但保存路由状态失败: {}", e.message)))
+}
@@ -1,12 +1,28 @@
-use crate::route::WinRoute;
+use crate::interface::selector::InterfaceSelector;
+use crate::probe::check::Check;
+use crate::route::backend::RouteBackend;
+use crate::route::cidr::{Cidr, HostBits};
+use crate::route::config::domain_list::DomainListFormat;
+use crate::route::config::format::ConfigFormat;
+use crate::route::config::validate;
+use crate::route::daemon::{self, DaemonOptions};
+use crate::route::dns;
+use crate::route::family::Family;
+use crate::route::filter::{RouteFilter, RouteSort};
+use crate::route::plan::PlanAction;
+use crate::route::select::{AdapterSelection, IfIndex, SelectScope};
+#[cfg(windows)]
+use crate::route::windows::WinRoute;
 use test_case::test_case;
 
+#[cfg(windows)]
 #[test_case(true ; "测试创建路由类")]
 fn win_route_new_success(expected: bool) {
     let result = WinRoute::new();
     assert_eq!(result.is_ok(), expected);
 }
 
+#[cfg(windows)]
 #[test_case(true ; "测试获取路由信息")]
 fn win_route_get_routes_success(expected: bool) {
     let win_route = WinRoute::new();
@@ -15,3 +31,1769 @@ fn win_route_get_routes_success(expected: bool) {
     let result = win_route.get_routes();
     assert_eq!(result.is_ok(), expected);
 }
+
+/// 在独立的用户与网络命名空间中执行 netlink 路由表读写测试
+///
+/// 外层测试通过 `unshare` 重新执行当前测试，系统不支持命名空间时跳过
+#[cfg(target_os = "linux")]
+#[test]
+fn netlink_route_in_namespace() {
+    use crate::route::netlink::NetlinkRoute;
+    use winroute::Route;
+
+    if std::env::var_os("NET_ROUTE_RS_NETNS").is_none() {
+        let exe = std::env::current_exe().unwrap();
+        let status = std::process::Command::new("unshare")
+            .args(["--user", "--map-root-user", "--net"])
+            .arg(exe)
+            .args(["--exact", "route::tests::netlink_route_in_namespace"])
+            .env("NET_ROUTE_RS_NETNS", "1")
+            .status();
+        match status {
+            // 测试框架在测试失败时以 101 退出
+            Ok(status) if status.code() == Some(101) => panic!("命名空间内的 netlink 测试失败"),
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("无法创建网络命名空间，跳过测试: {}", status),
+            Err(e) => eprintln!("无法执行 unshare，跳过测试: {}", e),
+        }
+        return;
+    }
+
+    set_loopback_up();
+    let backend = NetlinkRoute::new().unwrap();
+    let v4_route = Route::new("10.1.2.0".parse().unwrap(), 24)
+        .ifindex(1)
+        .metric(5);
+    let v6_route = Route::new("fd00:1::".parse().unwrap(), 64)
+        .ifindex(1)
+        .metric(5);
+    backend.add_route(&v4_route).unwrap();
+    backend.add_route(&v6_route).unwrap();
+    // 重复添加应返回错误
+    assert!(backend.add_route(&v4_route).is_err());
+
+    let routes = backend.get_routes().unwrap();
+    assert!(routes.contains(&v4_route));
+    assert!(routes.contains(&v6_route));
+    let found = backend
+        .search_route_by_ip(&v4_route.destination, &24, Some(&1), None)
+        .unwrap();
+    assert_eq!(found, vec![v4_route.clone()]);
+
+    // 度量值不变时使用 NLM_F_REPLACE，度量值变化时先添加再删除
+    backend.replace_route(&v4_route, &v4_route).unwrap();
+    let v4_replaced = v4_route.clone().metric(7);
+    let events = monitor_routes(|| backend.replace_route(&v4_route, &v4_replaced).unwrap());
+    if let Some(events) = events {
+        let added = events
+            .iter()
+            .position(|line| !line.starts_with("Deleted") && line.contains("metric 7"));
+        let removed = events
+            .iter()
+            .position(|line| line.starts_with("Deleted") && line.contains("metric 5"));
+        assert!(added.is_some() && removed.is_some(), "{:?}", events);
+        assert!(added < removed, "{:?}", events);
+    } else {
+        eprintln!("无法执行 ip monitor，跳过路由替换顺序检查");
+    }
+    let routes = backend.get_routes().unwrap();
+    assert!(routes.contains(&v4_replaced));
+    assert!(!routes.contains(&v4_route));
+    backend.replace_route(&v4_replaced, &v4_route).unwrap();
+
+    // 两条路由的目标网段与度量值相同时，只替换指定的路由
+    if run_ip(&[
+        "link", "add", "nr-v0", "type", "veth", "peer", "name", "nr-v1",
+    ]) && run_ip(&["link", "set", "nr-v0", "up"])
+        && run_ip(&["link", "set", "nr-v1", "up"])
+    {
+        let if_index = |name: &str| {
+            let name = std::ffi::CString::new(name).unwrap();
+            unsafe { libc::if_nametoindex(name.as_ptr()) }
+        };
+        let shared = |ifindex: u32| {
+            Route::new("10.1.3.0".parse().unwrap(), 24)
+                .ifindex(ifindex)
+                .metric(5)
+        };
+        let (on_lo, on_v0, on_v1) = (
+            shared(1),
+            shared(if_index("nr-v0")),
+            shared(if_index("nr-v1")),
+        );
+        backend.add_route(&on_lo).unwrap();
+        // NLM_F_EXCL 不允许添加度量值相同的路由，使用 ip route append 添加
+        assert!(run_ip(&[
+            "route",
+            "append",
+            "10.1.3.0/24",
+            "dev",
+            "nr-v0",
+            "metric",
+            "5"
+        ]));
+        backend.replace_route(&on_v0, &on_v1).unwrap();
+        let routes = backend.get_routes().unwrap();
+        assert!(routes.contains(&on_lo));
+        assert!(routes.contains(&on_v1));
+        assert!(!routes.contains(&on_v0));
+        backend.remove_route(&on_lo).unwrap();
+        backend.remove_route(&on_v1).unwrap();
+    } else {
+        eprintln!("无法创建 veth 网卡，跳过度量值相同的路由替换测试");
+    }
+
+    backend.remove_route(&v4_route).unwrap();
+    backend.remove_route(&v6_route).unwrap();
+    let routes = backend.get_routes().unwrap();
+    assert!(!routes.contains(&v4_route));
+    assert!(!routes.contains(&v6_route));
+}
+
+/// 执行操作并记录期间的路由变更事件，无法执行 ip monitor 时返回空
+#[cfg(target_os = "linux")]
+fn monitor_routes(action: impl FnOnce()) -> Option<Vec<String>> {
+    use std::io::Read;
+    let mut monitor = std::process::Command::new("ip")
+        .args(["-o", "monitor", "route"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .ok()?;
+    // 等待 ip monitor 订阅路由变更
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    action();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let _ = monitor.kill();
+    let mut output = String::new();
+    monitor.stdout.take()?.read_to_string(&mut output).ok()?;
+    let _ = monitor.wait();
+    Some(output.lines().map(str::to_string).collect())
+}
+
+/// 在命名空间内执行 ip 命令，返回是否执行成功
+#[cfg(target_os = "linux")]
+fn run_ip(args: &[&str]) -> bool {
+    std::process::Command::new("ip")
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// 启用命名空间内的回环网卡
+#[cfg(target_os = "linux")]
+fn set_loopback_up() {
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        assert!(fd >= 0);
+        let mut req: libc::ifreq = std::mem::zeroed();
+        for (dst, src) in req.ifr_name.iter_mut().zip(b"lo") {
+            *dst = *src as libc::c_char;
+        }
+        assert!(libc::ioctl(fd, libc::SIOCGIFFLAGS, &mut req) >= 0);
+        req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        assert!(libc::ioctl(fd, libc::SIOCSIFFLAGS, &req) >= 0);
+        libc::close(fd);
+    }
+}
+
+/// 模拟网络环境测试使用的夹具
+const SIM_FIXTURE: &str = r#"{
+  "adapters": [
+    {"name": "以太网", "index": 12, "mac_address": "00:11:22:33:44:55",
+     "ip_address": "192.168.1.10", "gateway": "192.168.1.1", "if_type": "EthernetCsmacd"},
+    {"name": "WLAN", "index": 28, "mac_address": "66:77:88:99:aa:bb",
+     "ip_address": "10.0.0.20", "gateway": "10.0.0.1",
+     "ipv6_address": "2001:db8::20", "ipv6_gateway": "2001:db8::1", "if_type": "Ieee80211"}
+  ],
+  "routes": [
+    {"destination": "0.0.0.0", "prefix": 0, "gateway": "192.168.1.1", "ifindex": 12, "metric": 25},
+    {"destination": "1.1.1.1", "prefix": 32, "gateway": "192.168.1.1", "ifindex": 12, "metric": 0},
+    {"destination": "8.8.8.8", "prefix": 32, "gateway": "192.168.1.1", "ifindex": 12, "metric": 0},
+    {"destination": "8.8.8.8", "prefix": 32, "gateway": "10.0.0.1", "ifindex": 28, "metric": 5}
+  ],
+  "dns": {
+    "baidu.com": ["110.242.68.66", "39.156.66.10", "2400:da00::6666"]
+  },
+  "ttl": {"baidu.com": 300},
+  "unreachable": ["39.156.66.10"]
+}"#;
+
+/// 启用模拟网络环境
+fn setup_simulation() {
+    crate::route::simulate::enable(crate::route::simulate::parse_fixture(SIM_FIXTURE).unwrap());
+}
+
+/// 获取模拟路由表
+fn simulated_routes() -> Vec<winroute::Route> {
+    crate::route::backend::new_backend()
+        .unwrap()
+        .get_routes()
+        .unwrap()
+}
+
+/// 判断模拟路由表中是否存在指定路由
+fn has_route(dest: &str, prefix: u8, ifindex: u32) -> bool {
+    let dest = dest.parse::<std::net::IpAddr>().unwrap();
+    simulated_routes().iter().any(|route| {
+        route.destination == dest && route.prefix == prefix && route.ifindex == Some(ifindex)
+    })
+}
+
+/// 写入临时配置文件并返回路径
+fn write_config_file(name: &str, content: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("net-route-rs-{}-{}.json", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test_case("1.1.1.1", None, true ; "测试删除唯一匹配的路由")]
+#[test_case("8.8.8.8", Some(28), true ; "测试按网卡删除路由")]
+#[test_case("8.8.8.8", None, false ; "测试匹配多条路由时拒绝删除")]
+#[test_case("9.9.9.9", None, true ; "测试删除不存在的路由")]
+fn simulate_remove_route(dest: &str, if_index: Option<u32>, expected: bool) {
+    setup_simulation();
+    let before = simulated_routes().len();
+    let result = crate::route::remove_route(dest, &None, &if_index, &true, &HostBits::Reject);
+    assert_eq!(result.is_ok(), expected);
+    let removed = before - simulated_routes().len();
+    if expected && dest != "9.9.9.9" {
+        assert_eq!(removed, 1);
+        assert!(!has_route(dest, 32, if_index.unwrap_or(12)));
+    } else {
+        assert_eq!(removed, 0);
+    }
+}
+
+#[test_case("baidu.com", 28, true ; "测试添加域名路由")]
+#[test_case("none.example", 28, false ; "测试添加无法解析的域名路由")]
+#[test_case("baidu.com", 99, false ; "测试添加不存在网卡的域名路由")]
+fn simulate_add_domain_route(domain: &str, if_index: u32, expected: bool) {
+    setup_simulation();
+    let result = crate::route::add_domain_route(
+        domain,
+        &AdapterSelection::Fixed(if_index),
+        &0,
+        Some(&Check::Icmp),
+        &Family::V4,
+        &false,
+    );
+    assert_eq!(result.is_ok(), expected);
+    assert_eq!(has_route("110.242.68.66", 32, if_index), expected);
+    assert_eq!(has_route("39.156.66.10", 32, if_index), expected);
+    // 默认只添加 IPv4 路由
+    assert!(!has_route("2400:da00::6666", 128, if_index));
+    if expected {
+        // 重复添加时路由已存在
+        assert!(
+            crate::route::add_domain_route(
+                domain,
+                &AdapterSelection::Fixed(if_index),
+                &0,
+                None,
+                &Family::V4,
+                &false
+            )
+            .is_err()
+        );
+    }
+}
+
+#[test_case(28, Family::V6, true, (false, true) ; "测试添加 IPv6 域名路由")]
+#[test_case(28, Family::Both, true, (true, true) ; "测试同时添加 IPv4 与 IPv6 域名路由")]
+#[test_case(12, Family::Both, true, (true, false) ; "测试跳过网卡缺少网关的地址族")]
+#[test_case(12, Family::V6, false, (false, false) ; "测试网卡没有 IPv6 网关")]
+fn simulate_add_domain_route_family(
+    if_index: u32,
+    family: Family,
+    expected: bool,
+    (v4, v6): (bool, bool),
+) {
+    setup_simulation();
+    let result = crate::route::add_domain_route(
+        "baidu.com",
+        &AdapterSelection::Fixed(if_index),
+        &0,
+        None,
+        &family,
+        &false,
+    );
+    assert_eq!(result.is_ok(), expected);
+    assert_eq!(has_route("110.242.68.66", 32, if_index), v4);
+    assert_eq!(has_route("2400:da00::6666", 128, if_index), v6);
+}
+
+#[test_case(false ; "测试应用配置文件")]
+#[test_case(true ; "测试取消应用配置文件")]
+fn simulate_apply_config_file(cancel: bool) {
+    setup_simulation();
+    let path = write_config_file(
+        if cancel { "cancel" } else { "apply" },
+        r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "ips": ["1.1.1.1"]}]}"#,
+    );
+    let apply = |cancel: &bool| {
+        crate::route::apply_config_file(
+            &Some(path.clone()),
+            &None,
+            &true,
+            cancel,
+            &HostBits::Reject,
+            &false,
+        )
+    };
+    assert!(apply(&false).is_ok());
+    // 取消时删除上一次应用添加的路由
+    let result = if cancel { apply(&true) } else { Ok(()) };
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    // 原有的路由不是由本工具添加的，保持不变，与其度量值相同的期望路由作为冲突跳过
+    assert!(has_route("1.1.1.1", 32, 12));
+    assert!(!has_route("1.1.1.1", 32, 28));
+    assert_eq!(has_route("110.242.68.66", 32, 28), !cancel);
+    assert_eq!(has_route("39.156.66.10", 32, 28), !cancel);
+    // 其他路由不受影响
+    assert!(has_route("0.0.0.0", 0, 12));
+    assert!(has_route("8.8.8.8", 32, 28));
+}
+
+#[test_case(r#""family": "v6""#, (false, true) ; "测试配置文件添加 IPv6 路由")]
+#[test_case(r#""family": "both""#, (true, true) ; "测试配置文件同时添加 IPv4 与 IPv6 路由")]
+#[test_case(r#""family": "v4""#, (true, false) ; "测试配置文件只添加 IPv4 路由")]
+fn simulate_apply_config_family(family: &str, (v4, v6): (bool, bool)) {
+    setup_simulation();
+    let path = write_config_file(
+        "family",
+        &format!(
+            r#"{{"routes": [{{"ifindex": 28, "domains": ["baidu.com"], "ips": [], {}}}]}}"#,
+            family
+        ),
+    );
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    assert_eq!(has_route("110.242.68.66", 32, 28), v4);
+    assert_eq!(has_route("2400:da00::6666", 128, 28), v6);
+}
+
+#[test_case("10.0.0.0/8", None, HostBits::Reject, Some(("10.0.0.0", 8)) ; "测试添加 CIDR 路由")]
+#[test_case("10.1.2.3/8", None, HostBits::Normalize, Some(("10.0.0.0", 8)) ; "测试添加修正主机位的路由")]
+#[test_case("10.1.2.3/8", None, HostBits::Reject, None ; "测试添加包含主机位的路由")]
+#[test_case("10.0.0.0", Some(40), HostBits::Reject, None ; "测试添加掩码超出范围的路由")]
+#[test_case("2001:db8::/32", None, HostBits::Reject, None ; "测试添加地址族与网关不一致的路由")]
+fn simulate_add_cidr_route(
+    dest: &str,
+    prefix: Option<u8>,
+    host_bits: HostBits,
+    expected: Option<(&str, u8)>,
+) {
+    setup_simulation();
+    let before = simulated_routes().len();
+    let result = crate::route::add_route(
+        dest,
+        &prefix,
+        &12,
+        &Some("192.168.1.1".to_string()),
+        &0,
+        None,
+        &host_bits,
+    );
+    assert_eq!(result.is_ok(), expected.is_some());
+    match expected {
+        Some((address, prefix)) => assert!(has_route(address, prefix, 12)),
+        None => assert_eq!(simulated_routes().len(), before),
+    }
+}
+
+#[test_case(r#"["10.0.0.0/8", "1.1.1.1"]"#, HostBits::Reject, true ; "测试配置文件中的 CIDR")]
+#[test_case(r#"["10.1.2.3/8"]"#, HostBits::Normalize, true ; "测试配置文件修正主机位")]
+#[test_case(r#"["10.1.2.3/8"]"#, HostBits::Reject, false ; "测试配置文件拒绝主机位")]
+fn simulate_apply_config_cidr(ips: &str, host_bits: HostBits, expected: bool) {
+    setup_simulation();
+    let path = write_config_file(
+        "cidr",
+        &format!(
+            r#"{{"routes": [{{"ifindex": 28, "domains": [], "ips": {}}}]}}"#,
+            ips
+        ),
+    );
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &host_bits,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(result.is_ok(), expected);
+    assert_eq!(has_route("10.0.0.0", 8, 28), expected);
+}
+
+#[test_case(false, 3 ; "测试不聚合配置文件的路由")]
+#[test_case(true, 1 ; "测试聚合配置文件的路由")]
+fn simulate_apply_config_aggregate(aggregate: bool, count: usize) {
+    setup_simulation();
+    let path = write_config_file(
+        if aggregate {
+            "aggregate"
+        } else {
+            "no-aggregate"
+        },
+        r#"{"routes": [
+            {"ifindex": 28, "domains": [], "ips": ["10.1.0.0/25", "10.1.0.128/25"]},
+            {"ifindex": 28, "domains": [], "ips": ["10.1.1.0/24"]}
+        ]}"#,
+    );
+    let before = simulated_routes().len();
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &HostBits::Reject,
+        &aggregate,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    assert_eq!(simulated_routes().len() - before, count);
+    assert_eq!(has_route("10.1.0.0", 23, 28), aggregate);
+    assert_eq!(has_route("10.1.1.0", 24, 28), !aggregate);
+}
+
+#[test_case("1.1.1.1", 12, true, PlanAction::Unchanged ; "测试路由一致时保持不变")]
+#[test_case("1.1.1.1", 28, true, PlanAction::Modify ; "测试网卡不一致时修改路由")]
+#[test_case("1.1.1.1", 28, false, PlanAction::Add ; "测试不修改未记录的路由")]
+#[test_case("9.9.9.9", 28, true, PlanAction::Add ; "测试路由不存在时添加路由")]
+fn plan_route_plan_new(dest: &str, ifindex: u32, owned: bool, expected: PlanAction) {
+    use crate::route::plan::{DesiredRoute, RoutePlan};
+    use winroute::Route;
+    let gateway = |ifindex: u32| {
+        if ifindex == 12 {
+            "192.168.1.1"
+        } else {
+            "10.0.0.1"
+        }
+    };
+    let route = |dest: &str, ifindex: u32| {
+        Route::new(dest.parse().unwrap(), 32)
+            .ifindex(ifindex)
+            .gateway(gateway(ifindex).parse().unwrap())
+            .metric(0)
+    };
+    let live = vec![
+        route("1.1.1.1", 12),
+        route("8.8.8.8", 12),
+        route("8.8.8.8", 28),
+    ];
+    let desired = vec![
+        DesiredRoute {
+            route: route(dest, ifindex),
+            source: "example.com".to_string(),
+            file: None,
+        },
+        DesiredRoute {
+            route: route("8.8.8.8", 28),
+            source: "8.8.8.8".to_string(),
+            file: None,
+        },
+    ];
+    let managed = live
+        .iter()
+        .filter(|_| owned)
+        .map(|route| crate::route::state::ManagedRoute {
+            route: route.clone(),
+            source: crate::route::state::RouteSource::config("config.json", "example.com"),
+            created_at: 0,
+        })
+        .collect::<Vec<_>>();
+    let plan = RoutePlan::new("config.json", &live, Some(&managed), &desired, &[]);
+    assert_eq!(plan.entries[0].action, expected);
+    assert_eq!(plan.entries[0].source, "example.com");
+    // 同一目标网段上多余的路由被删除，未记录的路由保持不变
+    assert_eq!(plan.count(PlanAction::Remove), owned as usize);
+    assert_eq!(
+        plan.count(PlanAction::Unchanged),
+        1 + (expected == PlanAction::Unchanged) as usize
+    );
+    // 快照只包含计划相关的路由
+    let snapshot_len = if dest == "1.1.1.1" { 3 } else { 2 };
+    assert_eq!(plan.snapshot.len(), snapshot_len);
+    assert!(plan.detect_drift(&live).is_empty());
+}
+
+#[test_case("8.8.8.8", 12, 0, PlanAction::Unchanged, (0, 1) ; "测试未记录的路由与期望一致时保持不变")]
+#[test_case("8.8.8.8", 28, 0, PlanAction::Conflict, (1, 1) ; "测试冲突时只删除需要修改的路由")]
+#[test_case("8.8.8.8", 28, 7, PlanAction::Modify, (0, 0) ; "测试度量值不同时不冲突")]
+#[test_case("1.1.1.1", 28, 0, PlanAction::Modify, (0, 0) ; "测试与将被修改的路由不冲突")]
+#[test_case("9.9.9.9", 28, 0, PlanAction::Add, (0, 0) ; "测试目标网段没有路由时不冲突")]
+fn plan_mark_conflicts(
+    dest: &str,
+    ifindex: u32,
+    metric: u32,
+    expected: PlanAction,
+    (conflicts, removes): (usize, usize),
+) {
+    use crate::route::plan::{DesiredRoute, RoutePlan};
+    use crate::route::state::{ManagedRoute, RouteSource};
+    use winroute::Route;
+    let route = |dest: &str, ifindex: u32, metric: u32| {
+        Route::new(dest.parse().unwrap(), 32)
+            .ifindex(ifindex)
+            .gateway(
+                if ifindex == 12 {
+                    "192.168.1.1"
+                } else {
+                    "10.0.0.1"
+                }
+                .parse()
+                .unwrap(),
+            )
+            .metric(metric)
+    };
+    // 8.8.8.8 经网卡 12 的路由不由本工具管理
+    let live = vec![
+        route("1.1.1.1", 12, 0),
+        route("8.8.8.8", 12, 0),
+        route("8.8.8.8", 28, 5),
+    ];
+    let managed = [route("1.1.1.1", 12, 0), route("8.8.8.8", 28, 5)]
+        .into_iter()
+        .map(|route| ManagedRoute {
+            route,
+            source: RouteSource::config("config.json", "example.com"),
+            created_at: 0,
+        })
+        .collect::<Vec<_>>();
+    let desired = vec![DesiredRoute {
+        route: route(dest, ifindex, metric),
+        source: "example.com".to_string(),
+        file: None,
+    }];
+    let mut plan = RoutePlan::new("config.json", &live, Some(&managed), &desired, &[]);
+    plan.mark_conflicts(&live);
+    let entry = plan
+        .entries
+        .iter()
+        .find(|entry| entry.desired.is_some())
+        .unwrap();
+    assert_eq!(entry.action, expected);
+    assert_eq!(plan.count(PlanAction::Conflict), conflicts);
+    assert_eq!(plan.count(PlanAction::Remove), removes);
+}
+
+#[test_case(false, true ; "测试执行已保存的计划")]
+#[test_case(true, false ; "测试路由表变化后拒绝执行计划")]
+fn simulate_apply_plan_file(drift: bool, expected: bool) {
+    setup_simulation();
+    let config_path = write_config_file(
+        if drift { "plan-drift" } else { "plan" },
+        r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "ips": ["1.1.1.1"]}]}"#,
+    );
+    let plan_path = format!("{}.plan", config_path);
+    let result = crate::route::plan_config_file(
+        &Some(config_path.clone()),
+        &None,
+        &false,
+        &HostBits::Reject,
+        &false,
+        &Some(plan_path.clone()),
+    );
+    assert!(result.is_ok());
+    // 生成计划不会修改路由表
+    assert!(has_route("1.1.1.1", 32, 12));
+    assert!(!has_route("110.242.68.66", 32, 28));
+    if drift {
+        let route = winroute::Route::new("110.242.68.66".parse().unwrap(), 32)
+            .ifindex(12)
+            .gateway("192.168.1.1".parse().unwrap());
+        let backend = crate::route::backend::new_backend().unwrap();
+        backend.add_route(&route).unwrap();
+    }
+    let result = crate::route::apply_plan_file(&plan_path, &true);
+    let _ = std::fs::remove_file(&config_path);
+    let _ = std::fs::remove_file(&plan_path);
+    assert_eq!(result.is_ok(), expected);
+    // 未记录的路由不由本工具管理，不会被修改，与其度量值相同的期望路由作为冲突跳过
+    assert!(has_route("1.1.1.1", 32, 12));
+    assert!(!has_route("1.1.1.1", 32, 28));
+    assert_eq!(has_route("110.242.68.66", 32, 28), expected);
+}
+
+#[test_case(true ; "测试执行计划失败时回滚")]
+fn simulate_apply_plan_rollback(expected_rollback: bool) {
+    use crate::route::plan::{DesiredRoute, RoutePlan};
+    use winroute::Route;
+    setup_simulation();
+    // 第二条路由的网卡不存在，添加时失败
+    let desired = vec![
+        DesiredRoute {
+            route: Route::new("1.1.1.1".parse().unwrap(), 32)
+                .ifindex(28)
+                .gateway("10.0.0.1".parse().unwrap())
+                .metric(0),
+            source: "1.1.1.1".to_string(),
+            file: None,
+        },
+        DesiredRoute {
+            route: Route::new("9.9.9.9".parse().unwrap(), 32)
+                .ifindex(99)
+                .gateway("10.0.0.1".parse().unwrap())
+                .metric(0),
+            source: "9.9.9.9".to_string(),
+            file: None,
+        },
+    ];
+    let plan = RoutePlan::new("config.json", &simulated_routes(), None, &desired, &[]);
+    assert_eq!(plan.count(PlanAction::Modify), 1);
+    let plan_path = std::env::temp_dir()
+        .join(format!("net-route-rs-rollback-{}.plan", std::process::id()))
+        .to_string_lossy()
+        .to_string();
+    plan.save(&plan_path).unwrap();
+    let before = simulated_routes();
+    let result = crate::route::apply_plan_file(&plan_path, &true);
+    let _ = std::fs::remove_file(&plan_path);
+    assert_eq!(result.is_err(), expected_rollback);
+    // 已修改的路由恢复到执行前的状态
+    assert!(has_route("1.1.1.1", 32, 12));
+    assert!(!has_route("1.1.1.1", 32, 28));
+    assert!(!has_route("9.9.9.9", 32, 99));
+    assert_eq!(simulated_routes().len(), before.len());
+}
+
+#[test_case(false ; "测试提交事务")]
+#[test_case(true ; "测试最后一步操作期间中断时回滚")]
+fn simulate_transaction_commit_interrupted(interrupt: bool) {
+    use crate::route::transaction::Transaction;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    setup_simulation();
+    let backend = crate::route::backend::new_backend().unwrap();
+    let interrupted = AtomicBool::new(false);
+    let mut transaction = Transaction::begin_with_interrupt(backend.as_ref(), &interrupted);
+    let route = winroute::Route::new("9.9.9.9".parse().unwrap(), 32)
+        .ifindex(28)
+        .gateway("10.0.0.1".parse().unwrap())
+        .metric(0);
+    let current = simulated_routes()
+        .into_iter()
+        .find(|route| route.destination.to_string() == "1.1.1.1")
+        .unwrap();
+    transaction.remove_route(&current).unwrap();
+    transaction.add_route(&route).unwrap();
+    // 中断信号在最后一步操作执行期间到达
+    interrupted.store(interrupt, Ordering::SeqCst);
+    let result = transaction.commit();
+    assert_eq!(result.is_err(), interrupt);
+    if result.is_err() {
+        transaction.rollback().unwrap();
+    }
+    assert_eq!(has_route("9.9.9.9", 32, 28), !interrupt);
+    assert_eq!(has_route("1.1.1.1", 32, 12), interrupt);
+}
+
+/// 启用包含路由记录的模拟网络环境，1.1.1.1/32 由配置文件为已不再解析到该地址的域名添加
+fn setup_managed_simulation(config_path: &str) {
+    let mut network = crate::route::simulate::parse_fixture(SIM_FIXTURE).unwrap();
+    let route = winroute::Route::new("1.1.1.1".parse().unwrap(), 32)
+        .ifindex(12)
+        .gateway("192.168.1.1".parse().unwrap());
+    network.managed.push(crate::route::state::ManagedRoute {
+        route,
+        source: crate::route::state::RouteSource::config(config_path, "old.example"),
+        created_at: 0,
+    });
+    crate::route::simulate::enable(network);
+}
+
+#[test_case(true ; "测试取消时按记录删除路由")]
+#[test_case(false ; "测试应用时删除不再需要的路由")]
+fn simulate_apply_config_managed(cancel: bool) {
+    let path = write_config_file(
+        if cancel {
+            "managed-cancel"
+        } else {
+            "managed-apply"
+        },
+        r#"{"routes": [{"ifindex": 28, "domains": [], "ips": ["9.9.9.9"]}]}"#,
+    );
+    setup_managed_simulation(&crate::route::state::canonical_config_path(&path));
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &cancel,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    // 记录的路由被删除，未记录的路由不受影响
+    assert!(!has_route("1.1.1.1", 32, 12));
+    assert!(has_route("8.8.8.8", 32, 12));
+    assert_eq!(has_route("9.9.9.9", 32, 28), !cancel);
+    let managed = crate::route::simulate::managed_routes().unwrap();
+    assert_eq!(managed.len(), if cancel { 0 } else { 1 });
+}
+
+#[test]
+fn simulate_apply_config_keeps_foreign_route() {
+    let path = write_config_file(
+        "managed-foreign",
+        r#"{"routes": [
+            {"ifindex": 28, "domains": [], "ips": ["1.1.1.1", "8.8.8.8"]},
+            {"ifindex": 28, "domains": [], "ips": ["0.0.0.0/0"], "metric": 20}
+        ]}"#,
+    );
+    setup_managed_simulation(&crate::route::state::canonical_config_path(&path));
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    // 记录的路由被修改，同一目标网段上未记录的路由保持不变
+    assert!(!has_route("1.1.1.1", 32, 12));
+    assert!(has_route("1.1.1.1", 32, 28));
+    assert!(has_route("8.8.8.8", 32, 12));
+    assert!(has_route("8.8.8.8", 32, 28));
+    // 与未记录的路由度量值相同的路由作为冲突跳过，度量值不同的路由可以同时存在
+    let metrics = |dest: &str| {
+        let dest = dest.parse::<std::net::IpAddr>().unwrap();
+        simulated_routes()
+            .into_iter()
+            .filter(|route| route.destination == dest && route.ifindex == Some(28))
+            .map(|route| route.metric.unwrap_or(0))
+            .collect::<Vec<u32>>()
+    };
+    assert_eq!(metrics("8.8.8.8"), vec![5]);
+    assert_eq!(metrics("0.0.0.0"), vec![20]);
+    assert!(has_route("0.0.0.0", 0, 12));
+}
+
+#[test_case("old.example", None, 0 ; "测试按记录删除域名路由")]
+#[test_case("old.example", Some(28), 1 ; "测试按网卡过滤记录")]
+#[test_case("baidu.com", None, 1 ; "测试域名没有记录时不删除路由")]
+fn simulate_remove_domain_route_managed(domain: &str, if_index: Option<u32>, remaining: usize) {
+    setup_managed_simulation("config.json");
+    // 未记录的相同路由不会被删除
+    let result = crate::route::remove_domain_route(domain, &if_index, &true, &None);
+    assert!(result.is_ok());
+    assert_eq!(has_route("1.1.1.1", 32, 12), remaining == 1);
+    assert!(has_route("8.8.8.8", 32, 12));
+    assert_eq!(
+        crate::route::simulate::managed_routes().unwrap().len(),
+        remaining
+    );
+}
+
+#[test_case("baidu.com", 28 ; "测试记录命令行添加的域名路由")]
+fn simulate_add_domain_route_records_state(domain: &str, if_index: u32) {
+    setup_simulation();
+    crate::route::add_domain_route(
+        domain,
+        &AdapterSelection::Fixed(if_index),
+        &0,
+        None,
+        &Family::V4,
+        &false,
+    )
+    .unwrap();
+    let route_state = crate::route::state::RouteState::load().unwrap();
+    assert_eq!(route_state.by_domain(domain).len(), 2);
+    // 删除后清除记录
+    crate::route::remove_domain_route(domain, &None, &true, &None).unwrap();
+    assert!(!has_route("110.242.68.66", 32, if_index));
+    assert!(crate::route::simulate::managed_routes().unwrap().is_empty());
+}
+
+#[test_case(true ; "测试恢复被修改的路由表")]
+#[test_case(false ; "测试路由表未变化时无需恢复")]
+fn simulate_backup_and_restore(modify: bool) {
+    setup_simulation();
+    let path = std::env::temp_dir()
+        .join(format!(
+            "net-route-rs-backup-{}-{}.json",
+            modify,
+            std::process::id()
+        ))
+        .to_string_lossy()
+        .to_string();
+    crate::route::backup_route_table(&path).unwrap();
+    let before = simulated_routes();
+    if modify {
+        crate::route::remove_route("8.8.8.8", &None, &Some(28), &true, &HostBits::Reject).unwrap();
+        let backend = crate::route::backend::new_backend().unwrap();
+        backend
+            .add_ip_route(
+                "9.9.9.9".parse().unwrap(),
+                &32,
+                &12,
+                "192.168.1.1".parse().unwrap(),
+                &0,
+            )
+            .unwrap();
+    }
+    let result = crate::route::restore_route_table(&Some(path.clone()), &false, &true);
+    let _ = std::fs::remove_file(&path);
+    assert!(result.is_ok());
+    let after = simulated_routes();
+    assert_eq!(after.len(), before.len());
+    assert!(before.iter().all(|route| after.contains(route)));
+    assert!(!has_route("9.9.9.9", 32, 12));
+}
+
+#[test_case(3, 5, 3 ; "测试只保留最近的快照")]
+#[test_case(10, 2, 2 ; "测试快照数量未超出保留数量")]
+fn snapshot_save_auto_snapshot(keep: usize, saves: usize, expected: usize) {
+    let dir = std::env::temp_dir().join(format!(
+        "net-route-rs-snapshots-{}-{}",
+        keep,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut saved = vec![];
+    for _ in 0..saves {
+        let routes = vec![winroute::Route::new("10.0.0.0".parse().unwrap(), 8)];
+        saved.push(crate::route::snapshot::save_auto_snapshot(&dir, routes, keep).unwrap());
+    }
+    let snapshots = crate::route::snapshot::list_auto_snapshots(&dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(snapshots.len(), expected);
+    // 保留的是最近保存的快照
+    assert_eq!(snapshots, saved[saves - expected..]);
+}
+
+/// 采集自 Linux 主机的 /proc/net/route
+#[cfg(target_os = "linux")]
+const PROC_NET_ROUTE: &str =
+    "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t010200C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t000200C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+wg0\t0000000A\t00000000\t0001\t0\t0\t5\t000000FF\t0\t0\t0
+lo\t0000A8C0\t00000000\t0201\t0\t0\t0\t0000FFFF\t0\t0\t0
+";
+
+/// 采集自 Linux 主机的 /proc/net/ipv6_route
+#[cfg(target_os = "linux")]
+const PROC_NET_IPV6_ROUTE: &str = "fd000000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fd000000000000000000000000000001 00000400 00000001 00000000 00000003     eth0
+00000000000000000000000000000001 80 00000000000000000000000000000000 00 00000000000000000000000000000000 00000000 00000002 00000000 80200001       lo
+ff000000000000000000000000000000 08 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000004 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo
+";
+
+#[cfg(target_os = "linux")]
+fn resolve_test_index(name: &str) -> Option<u32> {
+    match name {
+        "lo" => Some(1),
+        "eth0" => Some(4),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test_case(0, "0.0.0.0", 0, "192.0.2.1", Some(4), 100 ; "测试解析 IPv4 默认路由")]
+#[test_case(1, "192.0.2.0", 24, "0.0.0.0", Some(4), 0 ; "测试解析 IPv4 直连路由")]
+#[test_case(2, "10.0.0.0", 8, "0.0.0.0", None, 5 ; "测试解析未知网卡的 IPv4 路由")]
+fn procfs_parse_route(
+    idx: usize,
+    dest: &str,
+    prefix: u8,
+    gateway: &str,
+    ifindex: Option<u32>,
+    metric: u32,
+) {
+    let routes =
+        crate::route::procfs::parse_proc_route(PROC_NET_ROUTE, &resolve_test_index).unwrap();
+    // 拒绝路由不会出现在结果中
+    assert_eq!(routes.len(), 3);
+    let route = &routes[idx];
+    assert_eq!(route.destination.to_string(), dest);
+    assert_eq!(route.prefix, prefix);
+    assert_eq!(route.gateway.to_string(), gateway);
+    assert_eq!(route.ifindex, ifindex);
+    assert_eq!(route.metric, Some(metric));
+    assert_eq!(route.version, 4);
+}
+
+#[cfg(target_os = "linux")]
+#[test_case(0, "fd00::", 64, "::", 256 ; "测试解析 IPv6 网段路由")]
+#[test_case(1, "::", 0, "fd00::1", 1024 ; "测试解析 IPv6 默认路由")]
+fn procfs_parse_ipv6_route(idx: usize, dest: &str, prefix: u8, gateway: &str, metric: u32) {
+    let routes =
+        crate::route::procfs::parse_proc_ipv6_route(PROC_NET_IPV6_ROUTE, &resolve_test_index)
+            .unwrap();
+    // 本机地址、组播与拒绝路由不会出现在结果中
+    assert_eq!(routes.len(), 2);
+    let route = &routes[idx];
+    assert_eq!(route.destination.to_string(), dest);
+    assert_eq!(route.prefix, prefix);
+    assert_eq!(route.gateway.to_string(), gateway);
+    assert_eq!(route.ifindex, Some(4));
+    assert_eq!(route.metric, Some(metric));
+    assert_eq!(route.version, 6);
+}
+
+#[cfg(target_os = "linux")]
+#[test_case("eth0\t00000000\n" ; "测试解析字段不足的路由表")]
+#[test_case("eth0\tZZZZZZZZ\t010200C0\t0003\t0\t0\t0\t00000000\t0\t0\t0\n" ; "测试解析非法地址的路由表")]
+fn procfs_parse_route_error(line: &str) {
+    let content = format!("Iface\tDestination\n{}", line);
+    let result = crate::route::procfs::parse_proc_route(&content, &resolve_test_index);
+    assert!(result.is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test_case(true ; "测试读取系统 /proc/net 路由表")]
+fn procfs_route_get_routes(expected: bool) {
+    let backend = crate::route::procfs::ProcRoute::new();
+    assert_eq!(backend.get_routes().is_ok(), expected);
+    let route = winroute::Route::new("10.0.0.0".parse().unwrap(), 8);
+    assert!(backend.add_route(&route).is_err());
+    assert!(backend.remove_route(&route).is_err());
+}
+
+#[test_case("10.1.2.3", "10.0.0.0", 8, true ; "测试地址在网段内")]
+#[test_case("11.1.2.3", "10.0.0.0", 8, false ; "测试地址不在网段内")]
+#[test_case("10.1.2.3", "0.0.0.0", 0, true ; "测试默认路由包含所有地址")]
+#[test_case("2001:db8::1", "2001:db8::", 32, true ; "测试 IPv6 地址在网段内")]
+#[test_case("10.1.2.3", "::", 0, false ; "测试地址族不同")]
+fn cidr_contains(ip: &str, network: &str, prefix: u8, expected: bool) {
+    let ip = ip.parse().unwrap();
+    let network = network.parse().unwrap();
+    assert_eq!(
+        crate::route::cidr::contains(&network, prefix, &ip),
+        expected
+    );
+}
+
+#[test_case("10.0.0.0/8", None, HostBits::Reject, Some(("10.0.0.0", 8)) ; "测试解析 CIDR")]
+#[test_case("10.0.0.0", Some(8), HostBits::Reject, Some(("10.0.0.0", 8)) ; "测试解析单独指定的掩码")]
+#[test_case("10.0.0.0/8", Some(8), HostBits::Reject, Some(("10.0.0.0", 8)) ; "测试 CIDR 与掩码一致")]
+#[test_case("1.1.1.1", None, HostBits::Reject, Some(("1.1.1.1", 32)) ; "测试 IPv4 默认为主机地址")]
+#[test_case("2001:db8::1", None, HostBits::Reject, Some(("2001:db8::1", 128)) ; "测试 IPv6 默认为主机地址")]
+#[test_case("2001:db8::/32", None, HostBits::Reject, Some(("2001:db8::", 32)) ; "测试解析 IPv6 CIDR")]
+#[test_case("10.1.2.3/8", None, HostBits::Normalize, Some(("10.0.0.0", 8)) ; "测试修正主机位")]
+#[test_case("10.1.2.3/8", None, HostBits::Reject, None ; "测试拒绝主机位")]
+#[test_case("10.0.0.0/8", Some(16), HostBits::Reject, None ; "测试 CIDR 与掩码不一致")]
+#[test_case("10.0.0.0/40", None, HostBits::Reject, None ; "测试 IPv4 掩码超出范围")]
+#[test_case("10.0.0.0", Some(33), HostBits::Normalize, None ; "测试单独指定的掩码超出范围")]
+#[test_case("2001:db8::/129", None, HostBits::Reject, None ; "测试 IPv6 掩码超出范围")]
+#[test_case("10.0.0.0/abc", None, HostBits::Reject, None ; "测试非法掩码")]
+#[test_case("10.0.0/8", None, HostBits::Reject, None ; "测试非法地址")]
+fn cidr_parse(input: &str, prefix: Option<u8>, host_bits: HostBits, expected: Option<(&str, u8)>) {
+    let result = crate::route::cidr::Cidr::parse(input, prefix, &host_bits);
+    match expected {
+        Some((address, prefix)) => {
+            let cidr = result.unwrap();
+            assert_eq!(cidr.address.to_string(), address);
+            assert_eq!(cidr.prefix, prefix);
+        }
+        None => assert!(result.is_err()),
+    }
+}
+
+#[test_case("10.1.2.3/8", Some("已将 10.1.2.3/8 修正为 10.0.0.0/8") ; "测试返回主机位修正说明")]
+#[test_case("10.0.0.0/8", None ; "测试未修正时没有说明")]
+fn cidr_parse_with_notice(input: &str, expected: Option<&str>) {
+    let (_, notice) =
+        crate::route::cidr::Cidr::parse_with_notice(input, None, &HostBits::Normalize).unwrap();
+    assert_eq!(notice.as_deref(), expected);
+}
+
+#[test_case(&["10.0.0.0/25", "10.0.0.128/25"], &["10.0.0.0/24"] ; "测试合并相邻网段")]
+#[test_case(&["10.0.0.0/24", "10.0.0.5/32"], &["10.0.0.0/24"] ; "测试移除被包含的网段")]
+#[test_case(&["10.0.0.0/24", "10.0.0.0/24"], &["10.0.0.0/24"] ; "测试移除重复的网段")]
+#[test_case(&["10.0.0.0/32", "10.0.0.1/32", "10.0.0.2/32", "10.0.0.3/32"], &["10.0.0.0/30"] ; "测试逐级合并主机路由")]
+#[test_case(&["10.0.0.1/32", "10.0.0.2/32"], &["10.0.0.1/32", "10.0.0.2/32"] ; "测试不合并非同级网段")]
+#[test_case(&["10.0.0.0/32", "10.0.0.1/32", "10.0.0.2/31", "10.0.0.5/32"], &["10.0.0.0/30", "10.0.0.5/32"] ; "测试部分合并")]
+#[test_case(&["0.0.0.0/1", "128.0.0.0/1"], &["0.0.0.0/0"] ; "测试合并为默认路由")]
+#[test_case(&["2001:db8::/33", "2001:db8:8000::/33", "10.0.0.0/32"], &["10.0.0.0/32", "2001:db8::/32"] ; "测试分别聚合 IPv4 与 IPv6")]
+fn cidr_aggregate(input: &[&str], expected: &[&str]) {
+    let cidrs = input
+        .iter()
+        .map(|cidr| crate::route::cidr::Cidr::parse(cidr, None, &HostBits::Reject).unwrap())
+        .collect();
+    let aggregated = crate::route::cidr::aggregate(cidrs)
+        .iter()
+        .map(|cidr| cidr.to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(aggregated, expected);
+}
+
+#[test_case("10.1.2.3", Some(("10.1.2.0", 24, 28)), 4 ; "测试最长前缀优先")]
+#[test_case("10.9.9.9", Some(("10.0.0.0", 8, 28)), 3 ; "测试掩码相同时度量值小的优先")]
+#[test_case("172.16.0.1", Some(("0.0.0.0", 0, 12)), 1 ; "测试匹配默认路由")]
+#[test_case("2001:db8::1", None, 0 ; "测试没有匹配的路由")]
+fn lookup_longest_prefix_match(ip: &str, expected: Option<(&str, u8, u32)>, count: usize) {
+    use winroute::Route;
+    let route = |dest: &str, prefix: u8, ifindex: u32, metric: u32| {
+        Route::new(dest.parse().unwrap(), prefix)
+            .ifindex(ifindex)
+            .metric(metric)
+    };
+    let routes = vec![
+        route("0.0.0.0", 0, 12, 25),
+        route("10.0.0.0", 8, 12, 10),
+        route("10.0.0.0", 8, 28, 5),
+        route("10.1.2.0", 24, 28, 50),
+    ];
+    let candidates = crate::route::lookup::longest_prefix_match(&routes, &ip.parse().unwrap());
+    assert_eq!(candidates.len(), count);
+    match expected {
+        Some((dest, prefix, ifindex)) => {
+            let best = &candidates[0];
+            assert_eq!(best.destination.to_string(), dest);
+            assert_eq!(best.prefix, prefix);
+            assert_eq!(best.ifindex, Some(ifindex));
+        }
+        None => assert!(candidates.is_empty()),
+    }
+}
+
+/// 筛选与排序测试使用的路由列表
+fn filter_routes() -> Vec<winroute::Route> {
+    use winroute::Route;
+    vec![
+        Route::new("0.0.0.0".parse().unwrap(), 0)
+            .ifindex(12)
+            .gateway("192.168.1.1".parse().unwrap())
+            .metric(25),
+        Route::new("10.1.0.0".parse().unwrap(), 16)
+            .ifindex(28)
+            .gateway("10.0.0.1".parse().unwrap())
+            .metric(5),
+        Route::new("10.0.0.0".parse().unwrap(), 8)
+            .ifindex(28)
+            .gateway("10.0.0.1".parse().unwrap())
+            .metric(50),
+        Route::new("2001:db8::".parse().unwrap(), 32)
+            .ifindex(28)
+            .metric(10),
+    ]
+}
+
+#[test_case(RouteFilter::default(), vec!["0.0.0.0/0", "10.1.0.0/16", "10.0.0.0/8", "2001:db8::/32"] ; "测试不筛选")]
+#[test_case(RouteFilter { ifindex: Some(12), ..Default::default() }, vec!["0.0.0.0/0"] ; "测试按网卡筛选")]
+#[test_case(RouteFilter { dest_within: Some(Cidr::parse("10.0.0.0/8", None, &HostBits::Reject).unwrap()), ..Default::default() }, vec!["10.1.0.0/16", "10.0.0.0/8"] ; "测试按所在网段筛选")]
+#[test_case(RouteFilter { dest_contains: Some("10.1.2.3".parse().unwrap()), ..Default::default() }, vec!["0.0.0.0/0", "10.1.0.0/16", "10.0.0.0/8"] ; "测试按包含地址筛选")]
+#[test_case(RouteFilter { gateway: Some("10.0.0.1".parse().unwrap()), metric_max: Some(10), ..Default::default() }, vec!["10.1.0.0/16"] ; "测试按网关与度量值筛选")]
+#[test_case(RouteFilter { family: Some(Family::V6), ..Default::default() }, vec!["2001:db8::/32"] ; "测试按地址族筛选")]
+fn route_filter_matches(filter: RouteFilter, expected: Vec<&str>) {
+    let routes = filter.apply(filter_routes(), None);
+    let routes = routes
+        .iter()
+        .map(|route| format!("{}/{}", route.destination, route.prefix))
+        .collect::<Vec<String>>();
+    assert_eq!(routes, expected);
+}
+
+#[test_case(true ; "测试只展示托管路由")]
+fn route_filter_managed_only(managed_only: bool) {
+    let routes = filter_routes();
+    let mut state = crate::route::state::RouteState::default();
+    state.record(
+        &routes[1],
+        crate::route::state::RouteSource::cli(Some("example.com")),
+    );
+    let filter = RouteFilter {
+        managed_only,
+        ..Default::default()
+    };
+    assert_eq!(filter.apply(routes.clone(), Some(&state)).len(), 1);
+    assert!(filter.apply(routes, None).is_empty());
+}
+
+#[test_case(RouteSort::Dest, vec!["0.0.0.0/0", "10.0.0.0/8", "10.1.0.0/16", "2001:db8::/32"] ; "测试按目标地址排序")]
+#[test_case(RouteSort::Prefix, vec!["0.0.0.0/0", "10.0.0.0/8", "10.1.0.0/16", "2001:db8::/32"] ; "测试按掩码排序")]
+#[test_case(RouteSort::Metric, vec!["10.1.0.0/16", "2001:db8::/32", "0.0.0.0/0", "10.0.0.0/8"] ; "测试按度量值排序")]
+#[test_case(RouteSort::Ifindex, vec!["0.0.0.0/0", "10.0.0.0/8", "10.1.0.0/16", "2001:db8::/32"] ; "测试按网卡排序")]
+fn route_sort(sort: RouteSort, expected: Vec<&str>) {
+    let mut routes = filter_routes();
+    crate::route::filter::sort_routes(&mut routes, &sort);
+    let routes = routes
+        .iter()
+        .map(|route| format!("{}/{}", route.destination, route.prefix))
+        .collect::<Vec<String>>();
+    assert_eq!(routes, expected);
+}
+
+#[test_case(0, 10, 1, 1 ; "测试空列表不越界")]
+#[test_case(25, 10, 5, 3 ; "测试页码超出时使用最后一页")]
+#[test_case(25, 10, 2, 2 ; "测试正常页码")]
+fn route_parse_page_info(total: usize, page_size: usize, page: usize, expected: usize) {
+    assert_eq!(
+        crate::route::parse_page_info(total, page_size, page),
+        expected
+    );
+}
+
+#[test_case(true ; "测试筛选后为空时展示列表")]
+fn simulate_show_route_list_empty(all: bool) {
+    setup_simulation();
+    let filter = RouteFilter {
+        ifindex: Some(99),
+        ..Default::default()
+    };
+    let result = crate::route::show_route_list(10, 1, &all, &false, &filter, &None);
+    assert!(result.is_ok());
+}
+
+#[test_case("1.1.1.1", None, Some(10), None, None, Some(("192.168.1.1", 12, 10)) ; "测试修改度量值")]
+#[test_case("1.1.1.1", None, None, Some("192.168.1.254"), None, Some(("192.168.1.254", 12, 0)) ; "测试修改网关")]
+#[test_case("1.1.1.1", None, None, None, Some(28), Some(("10.0.0.1", 28, 0)) ; "测试修改网卡时使用新网卡的网关")]
+#[test_case("8.8.8.8", Some(28), Some(3), None, None, Some(("10.0.0.1", 28, 3)) ; "测试指定网卡修改多条匹配中的一条")]
+#[test_case("8.8.8.8", None, Some(3), None, None, None ; "测试匹配到多条路由时报错")]
+#[test_case("1.1.1.1", None, None, None, None, None ; "测试未指定修改内容时报错")]
+#[test_case("9.9.9.9", None, Some(3), None, None, None ; "测试路由不存在时报错")]
+fn simulate_set_route(
+    dest: &str,
+    if_index: Option<u32>,
+    metric: Option<u32>,
+    gateway: Option<&str>,
+    new_if_index: Option<u32>,
+    expected: Option<(&str, u32, u32)>,
+) {
+    setup_simulation();
+    let before = simulated_routes().len();
+    let result = crate::route::set_route(
+        dest,
+        &None,
+        &if_index,
+        &metric,
+        &gateway.map(str::to_string),
+        &new_if_index,
+        &HostBits::Reject,
+    );
+    assert_eq!(result.is_ok(), expected.is_some());
+    // 替换后路由数量不变
+    assert_eq!(simulated_routes().len(), before);
+    if let Some((gateway, ifindex, metric)) = expected {
+        let dest = dest.parse::<std::net::IpAddr>().unwrap();
+        let gateway = gateway.parse::<std::net::IpAddr>().unwrap();
+        assert!(simulated_routes().iter().any(|route| {
+            route.destination == dest
+                && route.gateway == gateway
+                && route.ifindex == Some(ifindex)
+                && route.metric == Some(metric)
+        }));
+    }
+}
+
+#[test_case(true ; "测试修改托管路由后转移状态记录")]
+fn simulate_set_route_managed(expected: bool) {
+    setup_managed_simulation("/tmp/config.json");
+    crate::route::set_route(
+        "1.1.1.1",
+        &None,
+        &None,
+        &None,
+        &None,
+        &Some(28),
+        &HostBits::Reject,
+    )
+    .unwrap();
+    let state = crate::route::state::RouteState::load().unwrap();
+    assert_eq!(state.routes.len(), 1);
+    assert_eq!(state.routes[0].route.ifindex == Some(28), expected);
+    assert_eq!(
+        state.routes[0].source.domain.as_deref(),
+        Some("old.example")
+    );
+}
+
+#[test_case("12", Some(IfIndex::Adapter(InterfaceSelector::Index(12))) ; "测试解析网卡索引")]
+#[test_case("auto", Some(IfIndex::Auto) ; "测试解析自动选择")]
+#[test_case("WLAN", Some(IfIndex::Adapter(InterfaceSelector::Name("WLAN".to_string()))) ; "测试解析网卡名称")]
+#[test_case("regex:(", None ; "测试无效的网卡选择器")]
+fn if_index_parse(input: &str, expected: Option<IfIndex>) {
+    assert_eq!(input.parse::<IfIndex>().ok(), expected);
+}
+
+#[test_case(IfIndex::Adapter(InterfaceSelector::Index(12)), vec![], true ; "测试指定网卡")]
+#[test_case(IfIndex::Adapter(InterfaceSelector::Index(12)), vec![28], false ; "测试指定网卡时不能使用候选网卡")]
+#[test_case(IfIndex::Auto, vec![], false ; "测试自动选择时缺少候选网卡")]
+#[test_case(IfIndex::Auto, vec![12, 28, 12], true ; "测试自动选择时去除重复的候选网卡")]
+fn adapter_selection_new(if_index: IfIndex, candidates: Vec<u32>, expected: bool) {
+    setup_simulation();
+    let candidates = candidates
+        .into_iter()
+        .map(InterfaceSelector::Index)
+        .collect::<Vec<InterfaceSelector>>();
+    let selection = AdapterSelection::new(&if_index, &candidates, SelectScope::Ip);
+    assert_eq!(selection.is_ok(), expected);
+    if let Ok(AdapterSelection::Auto { candidates, .. }) = selection {
+        assert_eq!(candidates, vec![12, 28]);
+    }
+}
+
+/// 启用经由两个网卡的往返时间不同的模拟网络环境
+///
+/// 110.242.68.66 经由网卡 28 更快，39.156.66.10 不可达
+fn setup_latency_simulation() {
+    let mut fixture = crate::route::simulate::parse_fixture(SIM_FIXTURE).unwrap();
+    let target = "110.242.68.66".parse().unwrap();
+    fixture.latency.entry(12).or_default().insert(target, 30);
+    fixture.latency.entry(28).or_default().insert(target, 10);
+    crate::route::simulate::enable(fixture);
+}
+
+#[test_case(SelectScope::Ip, 12 ; "测试为每个IP地址选择网卡")]
+#[test_case(SelectScope::Domain, 28 ; "测试整个域名使用同一个网卡")]
+fn simulate_add_domain_route_auto(scope: SelectScope, unreachable_if_index: u32) {
+    setup_latency_simulation();
+    let selection = AdapterSelection::new(
+        &IfIndex::Auto,
+        &[InterfaceSelector::Index(12), InterfaceSelector::Index(28)],
+        scope,
+    )
+    .unwrap();
+    let result = crate::route::add_domain_route(
+        "baidu.com",
+        &selection,
+        &0,
+        Some(&Check::Icmp),
+        &Family::V4,
+        &false,
+    );
+    assert!(result.is_ok());
+    assert!(has_route("110.242.68.66", 32, 28));
+    assert!(!has_route("110.242.68.66", 32, 12));
+    // 所有候选网卡都不可达时按粒度使用第一个候选网卡或整个域名选择的网卡
+    assert!(has_route("39.156.66.10", 32, unreachable_if_index));
+}
+
+#[test_case(None ; "测试自动选择网卡不能跳过检查")]
+fn simulate_add_domain_route_auto_requires_check(check: Option<&Check>) {
+    setup_latency_simulation();
+    let selection = AdapterSelection::new(
+        &IfIndex::Auto,
+        &[InterfaceSelector::Index(12), InterfaceSelector::Index(28)],
+        SelectScope::Ip,
+    )
+    .unwrap();
+    let result =
+        crate::route::add_domain_route("baidu.com", &selection, &0, check, &Family::V4, &false);
+    assert!(result.is_err());
+}
+
+#[test_case(r#""ips": ["110.242.68.66"], "candidates": [12, 28]"#, true ; "测试配置文件自动选择网卡")]
+#[test_case(r#""ips": ["110.242.68.66"], "candidates": ["以太网", "type:Ieee80211"]"#, true ; "测试配置文件使用选择器指定候选网卡")]
+#[test_case(r#""ips": ["110.242.68.0/24"], "candidates": [12, 28]"#, false ; "测试配置文件网段不能自动选择网卡")]
+fn simulate_apply_config_auto(ips: &str, expected: bool) {
+    setup_latency_simulation();
+    let path = write_config_file(
+        "auto",
+        &format!(
+            r#"{{"routes": [{{"ifindex": "auto", "domains": [], {}}}]}}"#,
+            ips
+        ),
+    );
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(result.is_ok(), expected);
+    assert_eq!(has_route("110.242.68.66", 32, 28), expected);
+}
+
+#[test_case(r#"{"defaults": {"ifindex": 28, "metric": 5}, "routes": [{"ips": ["1.2.3.4"]}]}"#, true, Some(("1.2.3.4", 32, "10.0.0.1", 28, 5)) ; "测试继承默认网卡与度量值")]
+#[test_case(r#"{"defaults": {"ifindex": 28, "metric": 5}, "routes": [{"ips": ["1.2.3.4"], "metric": 7}]}"#, true, Some(("1.2.3.4", 32, "10.0.0.1", 28, 7)) ; "测试条目覆盖默认值")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.2.3.4"], "gateway": "10.0.0.254"}]}"#, true, Some(("1.2.3.4", 32, "10.0.0.254", 28, 0)) ; "测试指定网关")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "prefix": 24}]}"#, true, Some(("110.242.68.0", 24, "10.0.0.1", 28, 0)) ; "测试域名解析结果使用网段")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.2.3.4"], "on_link": true}]}"#, true, Some(("1.2.3.4", 32, "0.0.0.0", 28, 0)) ; "测试添加直连路由")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.2.3.4"], "enabled": false}]}"#, true, None ; "测试跳过停用的条目")]
+#[test_case(r#"{"routes": [{"ips": ["1.2.3.4"]}]}"#, false, None ; "测试缺少网卡")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.2.3.4"], "gateway": "10.0.0.254", "on_link": true}]}"#, false, None ; "测试网关与直连同时设置")]
+fn simulate_apply_config_route_options(
+    content: &str,
+    expected: bool,
+    route: Option<(&str, u8, &str, u32, u32)>,
+) {
+    setup_simulation();
+    let path = write_config_file("route-options", content);
+    let result = crate::route::apply_config_file(
+        &Some(path.clone()),
+        &None,
+        &true,
+        &false,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(result.is_ok(), expected);
+    match route {
+        Some((dest, prefix, gateway, ifindex, metric)) => {
+            let dest = dest.parse::<std::net::IpAddr>().unwrap();
+            let gateway = gateway.parse::<std::net::IpAddr>().unwrap();
+            assert!(simulated_routes().iter().any(|route| {
+                route.destination == dest
+                    && route.prefix == prefix
+                    && route.gateway == gateway
+                    && route.ifindex == Some(ifindex)
+                    && route.metric == Some(metric)
+            }));
+        }
+        None => assert!(!has_route("1.2.3.4", 32, 28)),
+    }
+}
+
+#[test_case("config.json", ConfigFormat::Json ; "测试识别 JSON 配置文件")]
+#[test_case("config.jsonc", ConfigFormat::Jsonc ; "测试识别 JSONC 配置文件")]
+#[test_case("config.TOML", ConfigFormat::Toml ; "测试识别 TOML 配置文件")]
+#[test_case("config.yml", ConfigFormat::Yaml ; "测试识别 YAML 配置文件")]
+#[test_case("config", ConfigFormat::Json ; "测试没有扩展名时使用 JSON")]
+fn config_format_from_path(path: &str, expected: ConfigFormat) {
+    assert_eq!(ConfigFormat::from_path(path), expected);
+}
+
+#[test_case(ConfigFormat::Json, r#"{"defaults": {"ifindex": 28}, "routes": [{"domains": ["baidu.com"], "ips": ["10.0.0.0/8"], "metric": 5}]}"# ; "测试解析 JSON 配置")]
+#[test_case(ConfigFormat::Jsonc, "{\n  // 注释\n  \"defaults\": {\"ifindex\": 28},\n  \"routes\": [{\"domains\": [\"baidu.com\"], /* 网段 */ \"ips\": [\"10.0.0.0/8\"], \"metric\": 5}]\n}" ; "测试解析带注释的 JSON 配置")]
+#[test_case(ConfigFormat::Toml, "# 注释\n[defaults]\nifindex = 28\n\n[[routes]]\ndomains = [\"baidu.com\"]\nips = [\"10.0.0.0/8\"]\nmetric = 5\n" ; "测试解析 TOML 配置")]
+#[test_case(ConfigFormat::Yaml, "# 注释\ndefaults:\n  ifindex: 28\nroutes:\n  - domains: [baidu.com]\n    ips: [10.0.0.0/8]\n    metric: 5\n" ; "测试解析 YAML 配置")]
+fn config_format_parse(format: ConfigFormat, content: &str) {
+    let config = format.parse(content).unwrap();
+    let routes = config.resolved_routes().unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_eq!(
+        routes[0].ifindex,
+        Some(IfIndex::Adapter(InterfaceSelector::Index(28)))
+    );
+    assert_eq!(routes[0].domains, vec!["baidu.com"]);
+    assert_eq!(routes[0].ips, vec!["10.0.0.0/8"]);
+    assert_eq!(routes[0].metric, Some(5));
+}
+
+#[test_case(ConfigFormat::Json, "{\n  \"routes\": [\n    {\"ifindex\": 28,}\n  ]\n}", "第 3 行" ; "测试 JSON 错误位置")]
+#[test_case(ConfigFormat::Jsonc, "{\n  // 注释\n  \"routes\": [{\"ifindex\": \"regex:(\"}]\n}", "第 3 行" ; "测试 JSONC 错误位置")]
+#[test_case(ConfigFormat::Toml, "[[routes]]\nifindex = 28\nmetric = \"abc\"\n", "第 3 行第 10 列" ; "测试 TOML 错误位置")]
+#[test_case(ConfigFormat::Yaml, "routes:\n  - ifindex: 28\n    metric: abc\n", "第 3 行第 13 列" ; "测试 YAML 错误位置")]
+fn config_format_parse_error(format: ConfigFormat, content: &str, location: &str) {
+    let err = format.parse(content).unwrap_err();
+    assert!(err.message.contains(location), "{}", err.message);
+}
+
+#[test_case(ConfigFormat::Json ; "测试转换为 JSON")]
+#[test_case(ConfigFormat::Toml ; "测试转换为 TOML")]
+#[test_case(ConfigFormat::Yaml ; "测试转换为 YAML")]
+fn config_format_convert(to: ConfigFormat) {
+    let config = ConfigFormat::Json
+        .parse(r#"{"defaults": {"ifindex": "WLAN", "check": "tcp:443"}, "routes": [{"domains": ["baidu.com"], "ips": [], "prefix": 24}, {"ifindex": 12, "ips": ["10.0.0.0/8"], "gateway": "192.168.1.254"}]}"#)
+        .unwrap();
+    let converted = to.parse(&to.serialize(&config).unwrap()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&converted).unwrap(),
+        serde_json::to_value(&config).unwrap()
+    );
+}
+
+#[test_case(r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "ips": ["10.0.0.0/8"]}]}"#, false, vec![] ; "测试没有问题的配置")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["10.0.0/8", "10.1.2.3/8", "1.1.1.1"]}]}"#, false, vec![(1, "Invalid destination IP address"), (1, "包含主机位")] ; "测试报告所有无效地址")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "ips": ["1.1.1.1"]}, {"ifindex": 28, "domains": ["Baidu.com"], "ips": ["1.1.1.1/32"]}]}"#, false, vec![(2, "域名 Baidu.com 与第 1 个路由条目重复"), (2, "目标 1.1.1.1/32 与第 1 个路由条目重复")] ; "测试重复的域名与地址")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}, {"ifindex": "以太网", "ips": ["1.1.1.1"]}]}"#, false, vec![(2, "同时指向网卡 WLAN (28) 与网卡 以太网 (12)")] ; "测试同一目标指向不同网卡")]
+#[test_case(r#"{"routes": [{"ifindex": 99, "ips": ["1.1.1.1"]}, {"ips": ["8.8.8.8"]}]}"#, false, vec![(1, "没有与 99 匹配的网卡"), (2, "缺少 ifindex")] ; "测试网卡不存在或缺少网卡")]
+#[test_case(r#"{"routes": [{"ifindex": 12, "domains": ["baidu.com"], "family": "v6"}, {"ifindex": 12, "ips": ["2001:db8::1"]}]}"#, false, vec![(1, "没有可用的 IPv6 网关"), (2, "没有可用的 IPv6 网关")] ; "测试网卡没有网关")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com", "none.example"]}]}"#, false, vec![] ; "测试默认不解析域名")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com", "none.example"]}]}"#, true, vec![(1, "域名 none.example 无法解析")] ; "测试解析域名")]
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["110.242.68.66"]}, {"ifindex": 12, "domains": ["baidu.com"]}]}"#, true, vec![(2, "域名 baidu.com 解析得到的 110.242.68.66/32 同时指向网卡")] ; "测试解析结果指向不同网卡")]
+#[test_case(r#"{"routes": [{"ifindex": 99, "ips": ["bad"], "enabled": false}]}"#, false, vec![] ; "测试跳过停用的条目")]
+fn config_validate(content: &str, resolve: bool, expected: Vec<(usize, &str)>) {
+    setup_simulation();
+    let config = ConfigFormat::Json.parse(content).unwrap();
+    let adapters = crate::interface::Interface::new().get_interfaces().unwrap();
+    let issues = validate::validate_config(&config, &adapters, &HostBits::Reject, resolve);
+    assert_eq!(issues.len(), expected.len(), "{:?}", issues);
+    for (issue, (entry, message)) in issues.iter().zip(expected) {
+        assert_eq!(issue.origin.entry, entry);
+        assert!(issue.message.contains(message), "{}", issue.message);
+    }
+}
+
+#[test_case(vec![("10-a.json", r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#), ("20-b.json", r#"{"routes": [{"ifindex": 12, "ips": ["1.1.1.1", "bad"]}]}"#)], vec![("20-b.json", 1, "1.1.1.1 同时配置在"), ("20-b.json", 1, "Invalid destination IP address")] ; "测试同时报告文件之间的冲突与其他问题")]
+#[test_case(vec![("10-a.json", r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#), ("20-b.json", r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1", "2.2.2.2"]}]}"#)], vec![] ; "测试文件之间一致的地址不是问题")]
+fn config_validate_dir(files: Vec<(&str, &str)>, expected: Vec<(&str, usize, &str)>) {
+    setup_simulation();
+    let dir = write_config_dir("validate-dir", &files);
+    let loaded =
+        crate::route::config::include::load_config_with_conflicts(&dir.to_string_lossy(), &None);
+    let _ = std::fs::remove_dir_all(&dir);
+    let (config, mut issues) = loaded.unwrap();
+    let adapters = crate::interface::Interface::new().get_interfaces().unwrap();
+    issues.extend(validate::validate_config(
+        &config,
+        &adapters,
+        &HostBits::Reject,
+        false,
+    ));
+    assert_eq!(issues.len(), expected.len(), "{:?}", issues);
+    for (issue, (file, entry, message)) in issues.iter().zip(expected) {
+        assert!(
+            issue
+                .origin
+                .file
+                .as_ref()
+                .is_some_and(|path| path.ends_with(file))
+        );
+        assert_eq!(issue.origin.entry, entry);
+        assert!(issue.message.contains(message), "{}", issue.message);
+    }
+}
+
+#[test_case(r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#, true ; "测试检查通过")]
+#[test_case(r#"{"routes": [{"ifindex": 99, "ips": ["1.1.1.1"]}]}"#, false ; "测试存在问题时返回错误")]
+#[test_case(r#"{"routes": [{"ifindex": 28,}]}"#, false ; "测试配置文件无法解析时返回错误")]
+fn simulate_validate_config_file(content: &str, expected: bool) {
+    setup_simulation();
+    let path = write_config_file("validate", content);
+    let result =
+        crate::route::validate_config_file(&Some(path.clone()), &None, &HostBits::Reject, &false);
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(result.is_ok(), expected);
+}
+
+/// 在临时目录中写入一组配置文件，返回目录路径
+fn write_config_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("net-route-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (file, content) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+    }
+    dir
+}
+
+#[test_case("include", vec![("main.json", r#"{"include": ["conf.d/*.toml"], "defaults": {"ifindex": 28}, "routes": [{"ips": ["1.1.1.1"]}]}"#), ("conf.d/20-b.toml", "[[routes]]\nips = [\"3.3.3.3\"]\n"), ("conf.d/10-a.toml", "[defaults]\nifindex = 12\n\n[[routes]]\nips = [\"2.2.2.2\"]\n"), ("conf.d/ignored.json", "{}")], "main.json", vec![("main.json", "1.1.1.1", 28), ("10-a.toml", "2.2.2.2", 12), ("20-b.toml", "3.3.3.3", 28)] ; "测试通配符包含的文件按路径顺序合并")]
+#[test_case("dropin", vec![("20-b.yaml", "routes:\n  - ifindex: 28\n    ips: [3.3.3.3]\n"), ("10-a.json", r#"{"routes": [{"ifindex": 12, "ips": ["2.2.2.2"]}]}"#), ("readme.txt", "")], "", vec![("10-a.json", "2.2.2.2", 12), ("20-b.yaml", "3.3.3.3", 28)] ; "测试配置目录中的片段按文件名顺序合并")]
+#[test_case("dedupe", vec![("a.json", r#"{"include": ["b.json"], "routes": [{"ifindex": 28, "ips": ["1.1.1.1", "2.2.2.2"]}]}"#), ("b.json", r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#)], "a.json", vec![("a.json", "1.1.1.1", 28), ("a.json", "2.2.2.2", 28)] ; "测试不同文件中一致的地址只保留一次")]
+#[test_case("single", vec![("only.json", r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#)], "only.json", vec![("", "1.1.1.1", 28)] ; "测试单个配置文件不标记文件")]
+fn config_include_load(
+    name: &str,
+    files: Vec<(&str, &str)>,
+    root: &str,
+    expected: Vec<(&str, &str, u32)>,
+) {
+    let dir = write_config_dir(name, &files);
+    let path = dir.join(root).to_string_lossy().to_string();
+    let config = crate::route::config::include::load_config(&path, &None);
+    let _ = std::fs::remove_dir_all(&dir);
+    let routes = config.unwrap().resolved_routes().unwrap();
+    let actual = routes
+        .iter()
+        .flat_map(|route_config| {
+            let file = route_config.origin.file.clone().unwrap_or_default();
+            let ifindex = route_config.ifindex.clone();
+            route_config
+                .ips
+                .iter()
+                .map(move |ip| (file.clone(), ip.clone(), ifindex.clone()))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for ((file, ip, ifindex), (expected_file, expected_ip, expected_ifindex)) in
+        actual.iter().zip(expected)
+    {
+        assert!(file.ends_with(expected_file), "{}", file);
+        assert_eq!(ip, expected_ip);
+        assert_eq!(
+            *ifindex,
+            Some(IfIndex::Adapter(InterfaceSelector::Index(expected_ifindex)))
+        );
+    }
+}
+
+#[test_case("conflict", vec![("a.json", r#"{"include": ["b.json"], "routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#), ("b.json", r#"{"routes": [{"ifindex": 12, "ips": ["1.1.1.1"]}]}"#)], "1.1.1.1 同时配置在" ; "测试不同文件中不一致的地址")]
+#[test_case("cycle", vec![("a.json", r#"{"include": ["b.json"], "routes": []}"#), ("b.json", r#"{"include": ["a.json"], "routes": []}"#)], "循环包含" ; "测试循环包含")]
+#[test_case("missing", vec![("a.json", r#"{"include": ["none.json"], "routes": []}"#)], "none.json" ; "测试包含的文件不存在")]
+fn config_include_load_error(name: &str, files: Vec<(&str, &str)>, message: &str) {
+    let dir = write_config_dir(name, &files);
+    let path = dir.join("a.json").to_string_lossy().to_string();
+    let result = crate::route::config::include::load_config(&path, &None);
+    let _ = std::fs::remove_dir_all(&dir);
+    let error = result.unwrap_err();
+    assert!(error.message.contains(message), "{}", error.message);
+}
+
+#[test_case(true ; "测试计划记录路由所在的配置文件")]
+fn simulate_plan_config_dir(expected: bool) {
+    setup_simulation();
+    let dir = write_config_dir(
+        "plan-dir",
+        &[
+            (
+                "10-a.json",
+                r#"{"routes": [{"ifindex": 28, "ips": ["1.1.1.1"]}]}"#,
+            ),
+            (
+                "20-b.json",
+                r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"]}]}"#,
+            ),
+        ],
+    );
+    let backend = crate::route::backend::new_backend().unwrap();
+    let plan = crate::route::build_config_plan(
+        backend.as_ref(),
+        &Some(dir.to_string_lossy().to_string()),
+        &None,
+        &false,
+        &HostBits::Reject,
+        &false,
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+    let plan = plan.unwrap();
+    let file_of = |destination: &str| {
+        plan.entries
+            .iter()
+            .find(|entry| {
+                entry
+                    .desired
+                    .as_ref()
+                    .is_some_and(|route| route.destination.to_string() == destination)
+            })
+            .and_then(|entry| entry.file.clone())
+            .unwrap_or_default()
+    };
+    assert_eq!(file_of("1.1.1.1").ends_with("10-a.json"), expected);
+    assert_eq!(file_of("110.242.68.66").ends_with("20-b.json"), expected);
+}
+
+#[test_case(DomainListFormat::Text, "# 注释\nbaidu.com\n\n.qq.com  # 行尾注释\nBaidu.com\n", vec!["baidu.com", "qq.com"], vec![] ; "测试解析纯文本域名列表")]
+#[test_case(DomainListFormat::Dnsmasq, "# 注释\nserver=/baidu.com/114.114.114.114\nipset=/qq.com/163.com/cn\naddress=/ads.com/0.0.0.0\n", vec!["baidu.com", "qq.com", "163.com"], vec![] ; "测试解析 dnsmasq 域名列表")]
+#[test_case(DomainListFormat::Hosts, "127.0.0.1 localhost\n::1 localhost ip6-localhost\n0.0.0.0 ads.example.com tracker.example.com # 注释\nbroadcasthost\n", vec!["ads.example.com", "tracker.example.com"], vec![] ; "测试解析 hosts 文件")]
+#[test_case(DomainListFormat::Clash, "payload:\n  - DOMAIN,baidu.com\n  - DOMAIN-SUFFIX,qq.com\n  - DOMAIN-KEYWORD,google\n  - IP-CIDR,1.0.0.0/8,no-resolve\n  - IP-CIDR6,2400:da00::/32\n  - '+.163.com'\n  - 10.0.0.0/8\n", vec!["baidu.com", "qq.com", "163.com"], vec!["1.0.0.0/8", "2400:da00::/32", "10.0.0.0/8"] ; "测试解析 Clash 规则集")]
+#[test_case(DomainListFormat::Gfwlist, "W0F1dG9Qcm94eSAwLjIuOV0KISDms6jph4oKfHxnb29nbGUuY29tCnxodHRwOi8vZXhhbXBsZS5vcmcvcGF0aAouZ2l0aHViLmNvbQpAQHx8YmFpZHUuY29tCi9eaHR0cHM/OlwvXC9bXlwvXStibG9nc3BvdFwuKC4qKS8KfGh0dHA6Ly8xLjIuMy40Lwp3d3cueW91dHViZS5jb20vd2F0Y2gK", vec!["google.com", "example.org", "github.com", "www.youtube.com"], vec!["1.2.3.4"] ; "测试解析 gfwlist")]
+fn domain_list_parse(format: DomainListFormat, content: &str, domains: Vec<&str>, ips: Vec<&str>) {
+    let entries = format.parse(content).unwrap();
+    assert_eq!(entries.domains, domains);
+    assert_eq!(entries.ips, ips);
+}
+
+#[test_case(DomainListFormat::Clash, "payload: abc" ; "测试 Clash 规则集格式错误")]
+#[test_case(DomainListFormat::Gfwlist, "不是 base64" ; "测试 gfwlist 编码错误")]
+fn domain_list_parse_error(format: DomainListFormat, content: &str) {
+    assert!(format.parse(content).is_err());
+}
+
+#[test_case("lists/cn.txt", true ; "测试引用域名列表")]
+#[test_case("lists/none.txt", false ; "测试域名列表不存在")]
+fn config_domain_lists_load(list: &str, expected: bool) {
+    let config = format!(
+        r#"{{"routes": [{{"ifindex": 28, "domains": ["baidu.com"], "ips": ["1.0.0.0/8"], "domain_lists": [{{"path": "{}"}}, {{"path": "lists/rules.yaml", "format": "clash"}}]}}]}}"#,
+        list
+    );
+    let dir = write_config_dir(
+        "domain-lists",
+        &[
+            ("a.json", config.as_str()),
+            ("lists/cn.txt", "Baidu.com\nqq.com\n"),
+            (
+                "lists/rules.yaml",
+                "payload:\n  - DOMAIN,163.com\n  - IP-CIDR,1.0.0.0/8\n",
+            ),
+        ],
+    );
+    let path = dir.join("a.json").to_string_lossy().to_string();
+    let result = crate::route::config::include::load_config(&path, &None);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(result.is_ok(), expected);
+    if let Ok(config) = result {
+        let route_config = &config.routes[0];
+        assert_eq!(route_config.domains, vec!["baidu.com", "qq.com", "163.com"]);
+        assert_eq!(route_config.ips, vec!["1.0.0.0/8"]);
+        assert!(route_config.domain_lists.is_empty());
+    }
+}
+
+/// 测试使用的守护进程参数
+fn daemon_options(cleanup: bool) -> DaemonOptions {
+    DaemonOptions {
+        min_ttl: 60,
+        max_ttl: 3600,
+        grace: 600,
+        cleanup,
+    }
+}
+
+#[test_case(None, 60 ; "测试无法获取 TTL 时使用最短间隔")]
+#[test_case(Some(5), 60 ; "测试 TTL 小于最短间隔")]
+#[test_case(Some(300), 300 ; "测试 TTL 在范围内")]
+#[test_case(Some(86400), 3600 ; "测试 TTL 大于最长间隔")]
+fn daemon_refresh_interval(ttl: Option<u32>, expected: u64) {
+    assert_eq!(daemon_options(false).refresh_interval(ttl), expected);
+}
+
+#[test_case(0, "1970-01-01 00:00:00 UTC" ; "测试 Unix 纪元")]
+#[test_case(951782400, "2000-02-29 00:00:00 UTC" ; "测试闰日")]
+#[test_case(1700000000, "2023-11-14 22:13:20 UTC" ; "测试普通时间")]
+fn daemon_format_time(timestamp: u64, expected: &str) {
+    assert_eq!(daemon::format_time(timestamp), expected);
+}
+
+/// 构造 baidu.com 的 DNS 响应，包含一条 CNAME 与一条 A 记录
+fn dns_response(id: u16, flags: u16) -> Vec<u8> {
+    let mut packet = dns::build_query(id, "baidu.com", 1).unwrap();
+    packet[2..4].copy_from_slice(&flags.to_be_bytes());
+    packet[6..8].copy_from_slice(&2u16.to_be_bytes());
+    // CNAME，TTL 600
+    packet.extend([
+        0xc0, 0x0c, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x02, 0x58, 0x00, 0x06,
+    ]);
+    packet.extend([0x03, b'w', b'w', b'w', 0xc0, 0x0c]);
+    // A 110.242.68.66，TTL 120
+    packet.extend([
+        0xc0, 0x1b, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04,
+    ]);
+    packet.extend([110, 242, 68, 66]);
+    packet
+}
+
+#[test_case(0x1234, 0x8180, Some((vec!["110.242.68.66"], Some(120))) ; "测试解析 A 记录与最小 TTL")]
+#[test_case(0x4321, 0x8180, None ; "测试报文标识不一致")]
+#[test_case(0x1234, 0x8183, None ; "测试域名不存在")]
+#[test_case(0x1234, 0x8380, None ; "测试响应被截断")]
+fn dns_parse_response(id: u16, flags: u16, expected: Option<(Vec<&str>, Option<u32>)>) {
+    let result = dns::parse_response(&dns_response(id, flags), 0x1234, "baidu.com", 1);
+    match expected {
+        Some((ip_list, ttl)) => {
+            let resolved = result.unwrap();
+            let ip_list = ip_list
+                .iter()
+                .map(|ip| ip.parse::<std::net::IpAddr>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(resolved.ip_list, ip_list);
+            assert_eq!(resolved.ttl, ttl);
+        }
+        None => assert!(result.is_err()),
+    }
+}
+
+#[test_case("baidu.com", true ; "测试构造查询报文")]
+#[test_case("bad..com", false ; "测试域名格式错误")]
+fn dns_build_query(domain: &str, expected: bool) {
+    let result = dns::build_query(7, domain, 28);
+    assert_eq!(result.is_ok(), expected);
+    if let Ok(packet) = result {
+        assert_eq!(&packet[..2], &[0, 7]);
+        assert_eq!(&packet[12..23], b"\x05baidu\x03com\x00");
+        assert_eq!(&packet[23..], &[0, 28, 0, 1]);
+    }
+}
+
+#[test]
+fn dns_query_skip_stray_response() {
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut buffer = [0u8; 512];
+        let (_, client) = server.recv_from(&mut buffer).unwrap();
+        let id = u16::from_be_bytes([buffer[0], buffer[1]]);
+        // 标识不一致的报文与来自其他地址的报文都应被丢弃
+        server
+            .send_to(&dns_response(id.wrapping_add(1), 0x8180), client)
+            .unwrap();
+        let stranger = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut spoofed = dns_response(id, 0x8180);
+        let length = spoofed.len();
+        spoofed[length - 4..].copy_from_slice(&[6, 6, 6, 6]);
+        stranger.send_to(&spoofed, client).unwrap();
+        server.send_to(&dns_response(id, 0x8180), client).unwrap();
+    });
+    let resolved = dns::query(address, "baidu.com", 1).unwrap();
+    handle.join().unwrap();
+    assert_eq!(
+        resolved.ip_list,
+        vec!["110.242.68.66".parse::<std::net::IpAddr>().unwrap()]
+    );
+    assert_eq!(resolved.ttl, Some(120));
+}
+
+#[test_case(true ; "测试退出时删除路由")]
+#[test_case(false ; "测试退出时保留路由")]
+fn simulate_daemon_refresh(cleanup: bool) {
+    setup_simulation();
+    let path = write_config_file(
+        if cleanup { "daemon-cleanup" } else { "daemon" },
+        r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"], "ips": ["9.9.9.9"]}]}"#,
+    );
+    let start = crate::route::state::now();
+    let daemon = daemon::Daemon::start(&path, &None, &HostBits::Reject, daemon_options(cleanup));
+    let _ = std::fs::remove_file(&path);
+    let mut daemon = daemon.unwrap();
+    assert!(has_route("110.242.68.66", 32, 28));
+    assert!(has_route("39.156.66.10", 32, 28));
+    // 应用配置时无法获取 TTL，最短间隔后重新解析
+    let first = daemon.next_refresh().unwrap();
+    assert!(first >= start + 60);
+
+    let ip_list = ["110.242.68.66", "1.2.3.4"]
+        .iter()
+        .map(|ip| ip.parse().unwrap())
+        .collect();
+    crate::route::simulate::set_dns("baidu.com", ip_list);
+    daemon.refresh_due(first - 1);
+    assert!(!has_route("1.2.3.4", 32, 28));
+    daemon.refresh_due(first);
+    // 新地址立即添加，消失的地址在保留时间内不删除
+    assert!(has_route("1.2.3.4", 32, 28));
+    assert!(has_route("39.156.66.10", 32, 28));
+    assert_eq!(daemon.next_refresh(), Some(first + 300));
+    daemon.refresh_due(first + 600);
+    assert!(!has_route("39.156.66.10", 32, 28));
+    assert!(has_route("110.242.68.66", 32, 28));
+    let managed = crate::route::state::RouteState::load().unwrap();
+    assert_eq!(managed.by_domain("baidu.com").len(), 2);
+
+    daemon.shutdown().unwrap();
+    assert_eq!(has_route("1.2.3.4", 32, 28), !cleanup);
+    assert_eq!(has_route("9.9.9.9", 32, 28), !cleanup);
+}
+
+#[test]
+fn simulate_daemon_keep_routes_on_empty_answer() {
+    setup_simulation();
+    let path = write_config_file(
+        "daemon-empty",
+        r#"{"routes": [{"ifindex": 28, "domains": ["baidu.com"]}]}"#,
+    );
+    let daemon = daemon::Daemon::start(&path, &None, &HostBits::Reject, daemon_options(false));
+    let _ = std::fs::remove_file(&path);
+    let mut daemon = daemon.unwrap();
+    let first = daemon.next_refresh().unwrap();
+
+    // 没有地址记录的响应不说明地址已失效，超过保留时间也不删除路由
+    crate::route::simulate::set_dns("baidu.com", vec![]);
+    daemon.refresh_due(first);
+    assert_eq!(daemon.next_refresh(), Some(first + 60));
+    daemon.refresh_due(first + 600);
+    assert!(has_route("110.242.68.66", 32, 28));
+    assert!(has_route("39.156.66.10", 32, 28));
+}
+
+#[test_case(0, 60 ; "测试最短间隔为 0")]
+#[test_case(600, 60 ; "测试最短间隔大于最长间隔")]
+fn simulate_daemon_invalid_interval(min_ttl: u64, max_ttl: u64) {
+    setup_simulation();
+    let options = DaemonOptions {
+        min_ttl,
+        max_ttl,
+        ..daemon_options(false)
+    };
+    let result = daemon::Daemon::start("none.json", &None, &HostBits::Reject, options);
+    assert!(result.is_err());
+}
@@ -0,0 +1,223 @@
+use crate::base::NetRouteError;
+use crate::route::backend::RouteBackend;
+use prettytable::Table;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use winroute::Route;
+
+/// 是否有正在执行的事务
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// 事务执行期间用户是否按下了 Ctrl-C
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// 是否由调用方处理退出请求，守护进程模式下收到中断信号时不直接退出
+static KEEP_RUNNING: AtomicBool = AtomicBool::new(false);
+/// 守护进程模式下是否收到了退出请求
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// 只注册一次 Ctrl-C 处理函数
+static HANDLER: Once = Once::new();
+
+/// 注册 Ctrl-C 与终止信号处理函数
+///
+/// 事务执行期间只记录中断请求，由事务在下一步操作前或提交时回滚；
+/// 守护进程模式下记录退出请求，由守护进程完成清理后退出；
+/// 其他情况下直接退出程序
+fn install_interrupt_handler() {
+    HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            let active = ACTIVE.load(Ordering::SeqCst);
+            if active {
+                println!("收到中断信号，完成当前操作后回滚");
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            }
+            if KEEP_RUNNING.load(Ordering::SeqCst) {
+                println!("收到退出信号，正在停止");
+                SHUTDOWN.store(true, Ordering::SeqCst);
+            } else if !active {
+                std::process::exit(130);
+            }
+        });
+        if let Err(err) = result {
+            eprintln!("注册 Ctrl-C 处理函数失败，中断时无法自动回滚: {}", err);
+        }
+    });
+}
+
+/// 进入守护进程模式，收到中断或终止信号时不再直接退出，由 [`shutdown_requested`] 通知调用方
+pub fn watch_shutdown() {
+    KEEP_RUNNING.store(true, Ordering::SeqCst);
+    install_interrupt_handler();
+}
+
+/// 守护进程模式下是否收到了退出请求
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+/// 事务中已执行的路由变更
+enum Change {
+    /// 已添加的路由
+    Added(Route),
+    /// 已删除的路由
+    Removed(Route),
+}
+
+/// 路由表修改事务
+///
+/// 记录每一次修改，失败或被中断时按相反顺序撤销，使路由表恢复到事务开始前的状态
+pub struct Transaction<'a> {
+    backend: &'a dyn RouteBackend,
+    journal: Vec<Change>,
+    /// 中断标记，由 Ctrl-C 处理函数设置
+    interrupted: &'a AtomicBool,
+}
+
+impl<'a> Transaction<'a> {
+    /// 开始事务
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - 路由表后端
+    ///
+    pub fn begin(backend: &'a dyn RouteBackend) -> Transaction<'a> {
+        install_interrupt_handler();
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        ACTIVE.store(true, Ordering::SeqCst);
+        Transaction {
+            backend,
+            journal: vec![],
+            interrupted: &INTERRUPTED,
+        }
+    }
+
+    /// 使用指定的中断标记开始事务，不注册 Ctrl-C 处理函数，用于模拟中断
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - 路由表后端
+    /// * `interrupted` - 中断标记
+    ///
+    #[cfg(test)]
+    pub fn begin_with_interrupt(
+        backend: &'a dyn RouteBackend,
+        interrupted: &'a AtomicBool,
+    ) -> Transaction<'a> {
+        Transaction {
+            backend,
+            journal: vec![],
+            interrupted,
+        }
+    }
+
+    /// 检查用户是否中断了事务
+    fn check_interrupted(&self) -> Result<(), NetRouteError> {
+        if self.interrupted.load(Ordering::SeqCst) {
+            return Err(NetRouteError::new("用户中断操作".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 添加路由并记录
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    ///
+    pub fn add_route(&mut self, route: &Route) -> Result<(), NetRouteError> {
+        self.check_interrupted()?;
+        self.backend.add_route(route)?;
+        self.journal.push(Change::Added(route.clone()));
+        Ok(())
+    }
+
+    /// 删除路由并记录
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - 路由
+    ///
+    pub fn remove_route(&mut self, route: &Route) -> Result<(), NetRouteError> {
+        self.check_interrupted()?;
+        self.backend.remove_route(route)?;
+        self.journal.push(Change::Removed(route.clone()));
+        Ok(())
+    }
+
+    /// 按相反顺序撤销已执行的修改，并打印回滚报告
+    ///
+    /// 存在未能撤销的修改时返回错误
+    pub fn rollback(mut self) -> Result<(), NetRouteError> {
+        let mut table = Table::new();
+        table.add_row(prettytable::row![
+            "序号",
+            "回滚操作",
+            "目标地址",
+            "prefix",
+            "网关地址",
+            "目标网卡",
+            "结果"
+        ]);
+        let mut failed = 0;
+        let journal = std::mem::take(&mut self.journal);
+        for (idx, change) in journal.iter().rev().enumerate() {
+            let (action, route, result) = match change {
+                Change::Added(route) => {
+                    ("删除已添加的路由", route, self.backend.remove_route(route))
+                }
+                Change::Removed(route) => {
+                    ("恢复已删除的路由", route, self.backend.add_route(route))
+                }
+            };
+            let result = match result {
+                Ok(()) => "成功".to_string(),
+                Err(err) => {
+                    failed += 1;
+                    format!("失败: {}", err)
+                }
+            };
+            table.add_row(prettytable::row![
+                idx,
+                action,
+                route.destination,
+                route.prefix,
+                route.gateway,
+                route
+                    .ifindex
+                    .map_or("NONE".to_string(), |index| index.to_string()),
+                result
+            ]);
+        }
+        if journal.is_empty() {
+            println!("没有需要回滚的修改");
+            return Ok(());
+        }
+        println!("回滚报告:");
+        table.printstd();
+        println!(
+            "回滚完成: 成功 {} 条，失败 {} 条",
+            journal.len() - failed,
+            failed
+        );
+        if failed > 0 {
+            return Err(NetRouteError::new(format!(
+                "有 {} 条路由未能恢复，请根据回滚报告手动修复",
+                failed
+            )));
+        }
+        Ok(())
+    }
+
+    /// 提交事务，保留所有修改
+    ///
+    /// 最后一步操作执行期间用户按下 Ctrl-C 时返回错误，由调用方回滚
+    pub fn commit(&mut self) -> Result<(), NetRouteError> {
+        self.check_interrupted()?;
+        self.journal.clear();
+        Ok(())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::base::NetRouteError;
+use crate::route::backend::RouteBackend;
+use winroute::*;
+
+/// 基于 Windows IP Helper API 的路由表后端
+pub struct WinRoute {
+    manager: RouteManager,
+}
+
+impl WinRoute {
+    pub fn new() -> Result<WinRoute, NetRouteError> {
+        match RouteManager::new() {
+            Ok(manager) => Ok(WinRoute { manager }),
+            Err(e) => Err(NetRouteError::new(e.to_string())),
+        }
+    }
+}
+
+impl RouteBackend for WinRoute {
+    fn get_routes(&self) -> Result<Vec<Route>, NetRouteError> {
+        match self.manager.routes() {
+            Ok(routes) => Ok(routes),
+            Err(e) => Err(NetRouteError::new(e.to_string())),
+        }
+    }
+
+    fn add_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        self.manager
+            .add_route(route)
+            .map_err(|err| NetRouteError::new(format!("添加路由错误: {}", err)))?;
+        Ok(())
+    }
+
+    fn remove_route(&self, route: &Route) -> Result<(), NetRouteError> {
+        self.manager
+            .delete_route(route)
+            .map_err(|err| NetRouteError::new(format!("删除路由错误: {}", err)))?;
+        Ok(())
+    }
+}